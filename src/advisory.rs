@@ -0,0 +1,366 @@
+use super::graph;
+use super::http;
+use super::metadata;
+use super::registry;
+use super::resolution;
+use anyhow::{format_err, Context, Result};
+
+/// Kind of known finding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum AdvisoryKind {
+    /// Security advisory from an advisory feed.
+    Vulnerability,
+
+    /// Collection deprecated on the registry.
+    Deprecation,
+}
+
+/// Known finding which applies to a collection version.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Advisory {
+    /// Advisory identifier. For example: `GHSA-7rfm-3f8g-mx2m`
+    /// Deprecations are identified as `DEPRECATED`.
+    pub id: String,
+    pub kind: AdvisoryKind,
+    pub summary: Option<String>,
+
+    /// Other identifiers, such as CVE numbers.
+    pub aliases: Vec<String>,
+
+    /// Lowest later version which fixes the advisory, if known.
+    pub fixed_version: Option<String>,
+
+    pub url: Option<String>,
+}
+
+/// Resolved collection and the advisories which apply to it.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct DependencyAdvisories {
+    pub node: graph::DependencyNode,
+    pub advisories: Vec<Advisory>,
+
+    /// Reason the registry deprecation status could not be checked, if it could not.
+    pub deprecation_error: Option<String>,
+}
+
+/// Advisories in the OSV format, see https://ossf.github.io/osv-schema/
+#[derive(Debug, Clone, Default)]
+pub struct AdvisoryDatabase {
+    entries: Vec<serde_json::Value>,
+}
+
+impl AdvisoryDatabase {
+    /// Parse OSV JSON: a single advisory, a list of advisories, or an object with a
+    /// `vulns` list as returned by the OSV query API.
+    pub fn parse(content: &str) -> Result<Self> {
+        let json: serde_json::Value =
+            serde_json::from_str(content).context("Failed to parse advisory JSON.")?;
+        let entries = match json {
+            serde_json::Value::Array(entries) => entries,
+            serde_json::Value::Object(_) if json["vulns"].is_array() => {
+                json["vulns"].as_array().cloned().unwrap_or_default()
+            }
+            serde_json::Value::Object(_) if json["id"].is_string() => vec![json],
+            _ => return Err(format_err!("Failed to find advisories in JSON.")),
+        };
+        Ok(Self { entries })
+    }
+
+    pub fn extend(&mut self, other: Self) {
+        self.entries.extend(other.entries);
+    }
+
+    /// Returns advisories affecting the given collection version.
+    ///
+    /// Affected packages of any ecosystem naming Ansible or Galaxy, or of no ecosystem,
+    /// are matched by fully qualified collection name.
+    pub fn find(&self, package_name: &str, package_version: &str) -> Vec<Advisory> {
+        let version = resolution::parse_version(package_version);
+        let mut advisories = Vec::new();
+        for entry in &self.entries {
+            let id = match entry["id"].as_str() {
+                Some(id) => id,
+                None => continue,
+            };
+            if entry["withdrawn"].is_string() {
+                continue;
+            }
+            let affected = entry["affected"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter(|affected| is_matching_package(&affected["package"], package_name))
+                .find_map(|affected| get_affected_status(affected, package_version, &version));
+            let fixed_version = match affected {
+                Some(fixed_version) => fixed_version,
+                None => continue,
+            };
+            let get_string = |value: &serde_json::Value| {
+                value
+                    .as_str()
+                    .filter(|value| !value.is_empty())
+                    .map(|value| value.to_string())
+            };
+            advisories.push(Advisory {
+                id: id.to_string(),
+                kind: AdvisoryKind::Vulnerability,
+                summary: get_string(&entry["summary"]).or_else(|| get_string(&entry["details"])),
+                aliases: entry["aliases"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|alias| alias.as_str().map(|alias| alias.to_string()))
+                    .collect(),
+                fixed_version,
+                url: entry["references"]
+                    .as_array()
+                    .and_then(|references| references.first())
+                    .and_then(|reference| get_string(&reference["url"])),
+            });
+        }
+        advisories
+    }
+}
+
+fn is_matching_package(package: &serde_json::Value, package_name: &str) -> bool {
+    let is_ansible_ecosystem = match package["ecosystem"].as_str() {
+        Some(ecosystem) => {
+            let ecosystem = ecosystem.to_lowercase();
+            ecosystem.contains("ansible") || ecosystem.contains("galaxy")
+        }
+        None => true,
+    };
+    is_ansible_ecosystem
+        && package["name"]
+            .as_str()
+            .map(|name| name.eq_ignore_ascii_case(package_name))
+            .unwrap_or(false)
+}
+
+/// Returns Some if an OSV `affected` entry applies to the version, with the fixing version
+/// if known.
+///
+/// The version is affected if it is listed in `versions`, or if it falls within a SEMVER or
+/// ECOSYSTEM range: at or after an `introduced` event and before the next `fixed` event,
+/// or at most the next `last_affected` event.
+fn get_affected_status(
+    affected: &serde_json::Value,
+    package_version: &str,
+    version: &Option<semver::Version>,
+) -> Option<Option<String>> {
+    let is_listed = affected["versions"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .any(|listed_version| listed_version.as_str() == Some(package_version));
+    if is_listed {
+        return Some(None);
+    }
+    let version = version.as_ref()?;
+
+    for range in affected["ranges"].as_array().into_iter().flatten() {
+        if !matches!(range["type"].as_str(), Some("SEMVER") | Some("ECOSYSTEM")) {
+            continue;
+        }
+        let mut events: Vec<(&str, semver::Version, &str)> = Vec::new();
+        for event in range["events"].as_array().into_iter().flatten() {
+            let event = match event.as_object().and_then(|event| event.iter().next()) {
+                Some((kind, event_version)) => (kind.as_str(), event_version.as_str()),
+                None => continue,
+            };
+            let (kind, event_version) = match event {
+                (kind, Some(event_version)) => (kind, event_version),
+                _ => continue,
+            };
+            let parsed_version = if event_version == "0" {
+                Some(semver::Version::new(0, 0, 0))
+            } else {
+                resolution::parse_version(event_version)
+            };
+            if let Some(parsed_version) = parsed_version {
+                events.push((kind, parsed_version, event_version));
+            }
+        }
+        events.sort_by(|(_, a, _), (_, b, _)| resolution::compare_versions(a, b));
+
+        let mut is_affected = false;
+        for (kind, event_version, _) in &events {
+            let ordering = resolution::compare_versions(event_version, version);
+            match *kind {
+                "introduced" if ordering != std::cmp::Ordering::Greater => is_affected = true,
+                "fixed" if ordering != std::cmp::Ordering::Greater => is_affected = false,
+                "last_affected" if ordering == std::cmp::Ordering::Less => is_affected = false,
+                _ => {}
+            }
+        }
+        if is_affected {
+            let fixed_version = events
+                .iter()
+                .find(|(kind, event_version, _)| {
+                    *kind == "fixed"
+                        && resolution::compare_versions(event_version, version)
+                            == std::cmp::Ordering::Greater
+                })
+                .map(|(_, _, event_version)| event_version.to_string());
+            return Some(fixed_version);
+        }
+    }
+    None
+}
+
+/// Returns advisory feed locations given by VOUCH_ANSIBLE_ADVISORY_FEEDS.
+///
+/// Locations are comma separated https or file URLs, or file paths, of OSV JSON documents.
+/// http URLs are refused.
+pub fn get_feed_locations() -> Vec<String> {
+    std::env::var("VOUCH_ANSIBLE_ADVISORY_FEEDS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|location| location.trim().to_string())
+        .filter(|location| !location.is_empty())
+        .collect()
+}
+
+/// Load and merge advisory feeds.
+///
+/// Fails if no feeds are given, so that an unchecked project is not reported as free of
+/// advisories. https feeds are fetched as by `http::get_download`, with the client response
+/// size limit.
+pub fn load_database(client: &http::Client, locations: &[String]) -> Result<AdvisoryDatabase> {
+    if locations.is_empty() {
+        return Err(format_err!(
+            "No advisory feeds configured. Set VOUCH_ANSIBLE_ADVISORY_FEEDS."
+        ));
+    }
+    let mut database = AdvisoryDatabase::default();
+    for location in locations {
+        let content = match url::Url::parse(location) {
+            Ok(url) if matches!(url.scheme(), "https" | "http") => {
+                registry::check_remote_url(&url)?;
+                let response = http::get_download(client, None, &url)
+                    .context(format!("Failed to fetch advisory feed: {}", url))?;
+                let mut content = String::new();
+                std::io::Read::read_to_string(
+                    &mut http::BoundedReader::new(response, client.max_response_size()),
+                    &mut content,
+                )
+                .context(format!("Failed to read advisory feed: {}", url))?;
+                content
+            }
+            Ok(url) if url.scheme() == "file" => {
+                let path = url
+                    .to_file_path()
+                    .map_err(|_| format_err!("Failed to parse file URL: {}", url))?;
+                std::fs::read_to_string(&path)
+                    .context(format!("Failed to read file: {}", path.display()))?
+            }
+            _ => std::fs::read_to_string(location)
+                .context(format!("Failed to read file: {}", location))?,
+        };
+        database.extend(
+            AdvisoryDatabase::parse(&content)
+                .context(format!("Failed to parse advisory feed: {}", location))?,
+        );
+    }
+    Ok(database)
+}
+
+#[test]
+fn test_load_database() -> Result<()> {
+    let client = http::build_client(&http::HttpConfig::default())?;
+    assert!(load_database(&client, &[]).is_err());
+    assert!(load_database(&client, &["http://example.com/osv.json".to_string()]).is_err());
+
+    let directory = tempfile::tempdir()?;
+    let path = directory.path().join("osv.json");
+    std::fs::write(
+        &path,
+        serde_json::json!({"id": "GHSA-0000-0000-0001", "affected": [{
+            "package": {"name": "community.general"},
+            "versions": ["1.0.0"],
+        }]})
+        .to_string(),
+    )?;
+    let database = load_database(&client, &[path.display().to_string()])?;
+    assert_eq!(database.find("community.general", "1.0.0").len(), 1);
+    Ok(())
+}
+
+/// Returns a deprecation advisory if the registry marks the collection as deprecated.
+pub fn get_deprecation_advisory(
+    collection_metadata: &metadata::CollectionMetadata,
+) -> Option<Advisory> {
+    if !collection_metadata.deprecated {
+        return None;
+    }
+    Some(Advisory {
+        id: "DEPRECATED".to_string(),
+        kind: AdvisoryKind::Deprecation,
+        summary: collection_metadata.deprecation_message.clone(),
+        aliases: Vec::new(),
+        fixed_version: None,
+        url: None,
+    })
+}
+
+#[test]
+fn test_find_advisories() -> Result<()> {
+    let database = AdvisoryDatabase::parse(
+        &serde_json::json!({"vulns": [
+            {
+                "id": "GHSA-0000-0000-0001",
+                "aliases": ["CVE-2021-0001"],
+                "summary": "Secret disclosed in module output",
+                "affected": [{
+                    "package": {"ecosystem": "Ansible Galaxy", "name": "community.general"},
+                    "ranges": [{"type": "SEMVER", "events": [
+                        {"introduced": "0"}, {"fixed": "2.5.1"},
+                        {"introduced": "3.0.0"}, {"fixed": "3.2.0"},
+                    ]}],
+                }],
+                "references": [{"type": "ADVISORY", "url": "https://example.com/GHSA-0000-0000-0001"}],
+            },
+            {
+                "id": "GHSA-0000-0000-0002",
+                "affected": [{
+                    "package": {"ecosystem": "PyPI", "name": "community.general"},
+                    "versions": ["3.1.0"],
+                }],
+            },
+            {
+                "id": "GHSA-0000-0000-0003",
+                "affected": [{
+                    "package": {"name": "community.general"},
+                    "ranges": [{"type": "ECOSYSTEM", "events": [
+                        {"introduced": "3.1.0"}, {"last_affected": "3.1.0"},
+                    ]}],
+                }],
+            },
+        ]})
+        .to_string(),
+    )?;
+
+    let ids = |version: &str| -> Vec<String> {
+        database
+            .find("community.general", version)
+            .into_iter()
+            .map(|advisory| advisory.id)
+            .collect()
+    };
+    assert_eq!(
+        ids("3.1.0"),
+        vec!["GHSA-0000-0000-0001", "GHSA-0000-0000-0003"]
+    );
+    assert_eq!(ids("2.0.0"), vec!["GHSA-0000-0000-0001"]);
+    assert!(ids("2.5.1").is_empty());
+    assert!(ids("3.2.0").is_empty());
+
+    let advisories = database.find("community.general", "3.0.0");
+    assert_eq!(advisories[0].fixed_version, Some("3.2.0".to_string()));
+    assert_eq!(advisories[0].aliases, vec!["CVE-2021-0001"]);
+    assert_eq!(
+        advisories[0].url,
+        Some("https://example.com/GHSA-0000-0000-0001".to_string())
+    );
+    Ok(())
+}
@@ -0,0 +1,946 @@
+use super::http;
+use super::registry;
+use super::tarball;
+use anyhow::{format_err, Context, Result};
+
+/// Downloaded artifact whose sha256 digest differs from the registry advertised digest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChecksumMismatchError {
+    pub url: String,
+    pub expected_sha256: String,
+    pub actual_sha256: String,
+}
+
+impl std::fmt::Display for ChecksumMismatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Checksum mismatch for artifact {}: expected sha256 {}, found {}",
+            self.url, self.expected_sha256, self.actual_sha256
+        )
+    }
+}
+
+impl std::error::Error for ChecksumMismatchError {}
+
+/// Download artifact to the given path, verifying its sha256 digest.
+///
+/// The registry token is sent if the artifact is served from the registry host. The body is
+/// written to a temporary file beside `path`, which is renamed into place only if the digest
+/// matches. On mismatch the error downcasts to `ChecksumMismatchError`.
+/// Downloads larger than `max_size` bytes are aborted. Returns the lowercase hex digest.
+pub fn download_artifact(
+    client: &http::Client,
+    registry: &registry::Registry,
+    url: &url::Url,
+    expected_sha256: &str,
+    max_size: u64,
+    path: &std::path::Path,
+) -> Result<String> {
+    let reader = tarball::open_artifact(client, Some(registry), url)?;
+    write_verified(reader, url, expected_sha256, max_size, path)
+}
+
+fn write_verified<R: std::io::Read>(
+    mut reader: R,
+    url: &url::Url,
+    expected_sha256: &str,
+    max_size: u64,
+    path: &std::path::Path,
+) -> Result<String> {
+    let mut file_name = path
+        .file_name()
+        .ok_or(format_err!("Invalid artifact path: {}", path.display()))?
+        .to_os_string();
+    file_name.push(".part");
+    let part_path = path.with_file_name(file_name);
+
+    let result = (|| {
+        let mut file = std::fs::File::create(&part_path)
+            .context(format!("Failed to create file: {}", part_path.display()))?;
+        let mut hasher = <sha2::Sha256 as sha2::Digest>::new();
+        let mut buffer = vec![0; 64 * 1024];
+        let mut size: u64 = 0;
+        loop {
+            let count = std::io::Read::read(&mut reader, &mut buffer)
+                .context(format!("Failed to download artifact: {}", url))?;
+            if count == 0 {
+                break;
+            }
+            size += count as u64;
+            if size > max_size {
+                return Err(format_err!("Artifact exceeds {} bytes: {}", max_size, url));
+            }
+            sha2::Digest::update(&mut hasher, &buffer[..count]);
+            std::io::Write::write_all(&mut file, &buffer[..count])?;
+        }
+        std::io::Write::flush(&mut file)?;
+
+        let actual_sha256 = hex::encode(sha2::Digest::finalize(hasher));
+        if !actual_sha256.eq_ignore_ascii_case(expected_sha256) {
+            return Err(ChecksumMismatchError {
+                url: url.to_string(),
+                expected_sha256: expected_sha256.to_ascii_lowercase(),
+                actual_sha256,
+            }
+            .into());
+        }
+        Ok(actual_sha256)
+    })();
+
+    match result {
+        Ok(actual_sha256) => {
+            std::fs::rename(&part_path, path)
+                .context(format!("Failed to write artifact: {}", path.display()))?;
+            Ok(actual_sha256)
+        }
+        Err(error) => {
+            let _ = std::fs::remove_file(&part_path);
+            Err(error)
+        }
+    }
+}
+
+#[test]
+fn test_write_verified() -> Result<()> {
+    let directory =
+        std::env::temp_dir().join(format!("vouch-ansible-download-{}", std::process::id()));
+    std::fs::create_dir_all(&directory)?;
+    let path = directory.join("community-general-3.0.0.tar.gz");
+    let url = url::Url::parse("https://example.com/community-general-3.0.0.tar.gz")?;
+    let content = b"artifact";
+    let sha256 = "c7c5c1d70c5dec4416ab6158afd0b223ef40c29b1dc1f97ed9428b94d4cadb1c";
+    let other_sha256 = "0".repeat(64);
+
+    let error = write_verified(&content[..], &url, &other_sha256, 1024, &path).unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<ChecksumMismatchError>(),
+        Some(&ChecksumMismatchError {
+            url: url.to_string(),
+            expected_sha256: other_sha256,
+            actual_sha256: sha256.to_string(),
+        })
+    );
+    assert!(!path.exists());
+    assert!(!directory
+        .join("community-general-3.0.0.tar.gz.part")
+        .exists());
+
+    assert!(write_verified(&content[..], &url, sha256, 4, &path).is_err());
+    assert!(!path.exists());
+
+    assert_eq!(
+        write_verified(&content[..], &url, &sha256.to_uppercase(), 1024, &path)?,
+        sha256
+    );
+    assert_eq!(std::fs::read(&path)?, content);
+
+    std::fs::remove_dir_all(&directory)?;
+    Ok(())
+}
+
+/// Kind of difference between an extracted collection and its FILES.json file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum FileMismatchKind {
+    /// Listed file is absent.
+    Missing,
+
+    /// Listed file content differs from its recorded sha256 digest.
+    Modified,
+
+    /// File is present but not listed.
+    Unexpected,
+}
+
+/// File of an extracted collection which does not match the published artifact.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct FileMismatch {
+    /// Path relative to the collection directory, `/` separated.
+    pub path: String,
+    pub kind: FileMismatchKind,
+    pub expected_sha256: Option<String>,
+    pub actual_sha256: Option<String>,
+}
+
+/// Verify the files of an extracted collection against its FILES.json file.
+///
+/// The FILES.json digest is first checked against `file_manifest_file` of MANIFEST.json.
+/// Every listed file is then hashed, and files which are not listed are reported too.
+/// Returns the mismatching files, which is empty if the content matches the artifact.
+pub fn verify_extracted_collection(directory: &std::path::Path) -> Result<Vec<FileMismatch>> {
+    let manifest_path = directory.join("MANIFEST.json");
+    let manifest: serde_json::Value = serde_json::from_slice(
+        &std::fs::read(&manifest_path)
+            .context(format!("Failed to read file: {}", manifest_path.display()))?,
+    )
+    .context(format!("Failed to parse JSON: {}", manifest_path.display()))?;
+
+    let file_manifest_file = &manifest["file_manifest_file"];
+    let files_name = file_manifest_file["name"].as_str().unwrap_or("FILES.json");
+    let files_path = get_listed_path(directory, files_name)?;
+    let mut mismatches = Vec::new();
+    check_file(&files_path, files_name, file_manifest_file, &mut mismatches)?;
+    if !mismatches.is_empty() {
+        return Ok(mismatches);
+    }
+
+    let files: serde_json::Value = serde_json::from_slice(
+        &std::fs::read(&files_path)
+            .context(format!("Failed to read file: {}", files_path.display()))?,
+    )
+    .context(format!("Failed to parse JSON: {}", files_path.display()))?;
+    let entries = files["files"].as_array().ok_or(format_err!(
+        "Failed to find files list in {}.",
+        files_path.display()
+    ))?;
+
+    let mut listed_paths = std::collections::BTreeSet::new();
+    listed_paths.insert("MANIFEST.json".to_string());
+    listed_paths.insert(files_name.to_string());
+    for entry in entries {
+        if entry["ftype"].as_str() != Some("file") {
+            continue;
+        }
+        let name = entry["name"]
+            .as_str()
+            .ok_or(format_err!("Failed to parse file entry name: {}", entry))?;
+        check_file(
+            &get_listed_path(directory, name)?,
+            name,
+            entry,
+            &mut mismatches,
+        )?;
+        listed_paths.insert(name.to_string());
+    }
+
+    for path in get_relative_file_paths(directory)? {
+        if !listed_paths.contains(&path) {
+            mismatches.push(FileMismatch {
+                path,
+                kind: FileMismatchKind::Unexpected,
+                expected_sha256: None,
+                actual_sha256: None,
+            });
+        }
+    }
+    Ok(mismatches)
+}
+
+/// Returns path of a listed file, which must stay within the collection directory.
+fn get_listed_path(directory: &std::path::Path, name: &str) -> Result<std::path::PathBuf> {
+    let relative_path = std::path::Path::new(name);
+    if !relative_path
+        .components()
+        .all(|component| matches!(component, std::path::Component::Normal(_)))
+    {
+        return Err(format_err!(
+            "Invalid file path in collection manifest: {}",
+            name
+        ));
+    }
+    Ok(directory.join(relative_path))
+}
+
+fn check_file(
+    path: &std::path::Path,
+    name: &str,
+    entry: &serde_json::Value,
+    mismatches: &mut Vec<FileMismatch>,
+) -> Result<()> {
+    if entry["chksum_type"].as_str() != Some("sha256") {
+        return Err(format_err!(
+            "Unsupported checksum type for {}: {}",
+            name,
+            entry["chksum_type"]
+        ));
+    }
+    let expected_sha256 = entry["chksum_sha256"]
+        .as_str()
+        .ok_or(format_err!("Failed to find sha256 digest for {}.", name))?
+        .to_ascii_lowercase();
+    if !path.is_file() {
+        mismatches.push(FileMismatch {
+            path: name.to_string(),
+            kind: FileMismatchKind::Missing,
+            expected_sha256: Some(expected_sha256),
+            actual_sha256: None,
+        });
+        return Ok(());
+    }
+    let actual_sha256 = get_file_sha256(path)?;
+    if actual_sha256 != expected_sha256 {
+        mismatches.push(FileMismatch {
+            path: name.to_string(),
+            kind: FileMismatchKind::Modified,
+            expected_sha256: Some(expected_sha256),
+            actual_sha256: Some(actual_sha256),
+        });
+    }
+    Ok(())
+}
+
+fn get_file_sha256(path: &std::path::Path) -> Result<String> {
+    let mut file =
+        std::fs::File::open(path).context(format!("Failed to open file: {}", path.display()))?;
+    let mut hasher = <sha2::Sha256 as sha2::Digest>::new();
+    std::io::copy(&mut file, &mut hasher)
+        .context(format!("Failed to read file: {}", path.display()))?;
+    Ok(hex::encode(sha2::Digest::finalize(hasher)))
+}
+
+/// Returns `/` separated paths of all files below a directory, relative to it.
+fn get_relative_file_paths(directory: &std::path::Path) -> Result<Vec<String>> {
+    let mut paths = Vec::new();
+    let mut pending = vec![std::path::PathBuf::new()];
+    while let Some(relative_directory) = pending.pop() {
+        let current_directory = directory.join(&relative_directory);
+        for entry in std::fs::read_dir(&current_directory).context(format!(
+            "Failed to read directory: {}",
+            current_directory.display()
+        ))? {
+            let entry = entry?;
+            let relative_path = relative_directory.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                pending.push(relative_path);
+            } else {
+                let components: Vec<_> = relative_path
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy().to_string())
+                    .collect();
+                paths.push(components.join("/"));
+            }
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+#[test]
+fn test_verify_extracted_collection() -> Result<()> {
+    let directory =
+        std::env::temp_dir().join(format!("vouch-ansible-files-json-{}", std::process::id()));
+    std::fs::create_dir_all(directory.join("plugins/modules"))?;
+    let sha256 =
+        |content: &str| hex::encode(<sha2::Sha256 as sha2::Digest>::digest(content.as_bytes()));
+    let write_manifests = |files: &serde_json::Value| -> Result<()> {
+        let files = files.to_string();
+        std::fs::write(directory.join("FILES.json"), &files)?;
+        let manifest = serde_json::json!({
+            "file_manifest_file": {
+                "name": "FILES.json",
+                "ftype": "file",
+                "chksum_type": "sha256",
+                "chksum_sha256": sha256(&files),
+            }
+        });
+        std::fs::write(directory.join("MANIFEST.json"), manifest.to_string())?;
+        Ok(())
+    };
+    let file_entry = |name: &str, content: &str| {
+        serde_json::json!({
+            "name": name,
+            "ftype": "file",
+            "chksum_type": "sha256",
+            "chksum_sha256": sha256(content),
+        })
+    };
+
+    std::fs::write(directory.join("README.md"), "readme")?;
+    std::fs::write(directory.join("plugins/modules/ping.py"), "modified")?;
+    std::fs::write(directory.join("plugins/modules/extra.py"), "extra")?;
+    write_manifests(&serde_json::json!({
+        "files": [
+            {"name": ".", "ftype": "dir", "chksum_type": null, "chksum_sha256": null},
+            file_entry("README.md", "readme"),
+            file_entry("plugins/modules/ping.py", "ping"),
+            file_entry("plugins/modules/missing.py", "missing"),
+        ],
+        "format": 1,
+    }))?;
+
+    let mismatches = verify_extracted_collection(&directory)?;
+    let kinds: Vec<_> = mismatches
+        .iter()
+        .map(|mismatch| (mismatch.path.as_str(), mismatch.kind))
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            ("plugins/modules/ping.py", FileMismatchKind::Modified),
+            ("plugins/modules/missing.py", FileMismatchKind::Missing),
+            ("plugins/modules/extra.py", FileMismatchKind::Unexpected),
+        ]
+    );
+
+    std::fs::write(directory.join("FILES.json"), "{}")?;
+    let mismatches = verify_extracted_collection(&directory)?;
+    assert_eq!(mismatches[0].path, "FILES.json");
+    assert_eq!(mismatches[0].kind, FileMismatchKind::Modified);
+
+    write_manifests(&serde_json::json!({"files": [file_entry("../outside", "")]}))?;
+    assert!(verify_extracted_collection(&directory).is_err());
+
+    std::fs::remove_dir_all(&directory)?;
+    Ok(())
+}
+
+/// Returns sha256 digests of the regular files in a gzipped tarball stream, by path.
+///
+/// Paths are `/` separated and relative to the tarball root.
+pub fn read_file_digests<R: std::io::Read>(
+    reader: R,
+) -> Result<std::collections::BTreeMap<String, String>> {
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(reader));
+    let mut digests = std::collections::BTreeMap::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let components: Vec<String> = entry
+            .path()?
+            .components()
+            .filter_map(|component| match component {
+                std::path::Component::Normal(name) => Some(name.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .collect();
+        let mut hasher = <sha2::Sha256 as sha2::Digest>::new();
+        std::io::copy(&mut entry, &mut hasher)?;
+        digests.insert(
+            components.join("/"),
+            hex::encode(sha2::Digest::finalize(hasher)),
+        );
+    }
+    Ok(digests)
+}
+
+/// Kind of change to a file between two file trees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum FileChange {
+    Added,
+    Removed,
+    Modified,
+}
+
+/// File which differs between two file trees.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct FileDifference {
+    /// `/` separated path.
+    pub path: String,
+    pub change: FileChange,
+    pub old_sha256: Option<String>,
+    pub new_sha256: Option<String>,
+}
+
+/// Returns the differences between two sets of file digests, ordered by path.
+pub fn diff_file_digests(
+    old_digests: &std::collections::BTreeMap<String, String>,
+    new_digests: &std::collections::BTreeMap<String, String>,
+) -> Vec<FileDifference> {
+    let paths: std::collections::BTreeSet<&String> =
+        old_digests.keys().chain(new_digests.keys()).collect();
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let old_sha256 = old_digests.get(path);
+            let new_sha256 = new_digests.get(path);
+            let change = match (old_sha256, new_sha256) {
+                (Some(old_sha256), Some(new_sha256)) if old_sha256 == new_sha256 => return None,
+                (Some(_), Some(_)) => FileChange::Modified,
+                (None, _) => FileChange::Added,
+                (_, None) => FileChange::Removed,
+            };
+            Some(FileDifference {
+                path: path.clone(),
+                change,
+                old_sha256: old_sha256.cloned(),
+                new_sha256: new_sha256.cloned(),
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn test_diff_file_digests() {
+    let old_digests = maplit::btreemap! {
+        "README.md".to_string() => "a".repeat(64),
+        "plugins/modules/ping.py".to_string() => "b".repeat(64),
+        "plugins/modules/old.py".to_string() => "c".repeat(64),
+    };
+    let new_digests = maplit::btreemap! {
+        "README.md".to_string() => "a".repeat(64),
+        "plugins/modules/ping.py".to_string() => "d".repeat(64),
+        "plugins/modules/new.py".to_string() => "e".repeat(64),
+    };
+    assert_eq!(
+        diff_file_digests(&old_digests, &new_digests),
+        vec![
+            FileDifference {
+                path: "plugins/modules/new.py".to_string(),
+                change: FileChange::Added,
+                old_sha256: None,
+                new_sha256: Some("e".repeat(64)),
+            },
+            FileDifference {
+                path: "plugins/modules/old.py".to_string(),
+                change: FileChange::Removed,
+                old_sha256: Some("c".repeat(64)),
+                new_sha256: None,
+            },
+            FileDifference {
+                path: "plugins/modules/ping.py".to_string(),
+                change: FileChange::Modified,
+                old_sha256: Some("b".repeat(64)),
+                new_sha256: Some("d".repeat(64)),
+            },
+        ]
+    );
+}
+
+/// File level changes between two versions of a collection.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct VersionDiff {
+    /// Fully qualified collection name. For example: `community.general`
+    pub name: String,
+    pub old_version: String,
+    pub new_version: String,
+
+    /// Changes from the old to the new artifact, ordered by path.
+    pub differences: Vec<FileDifference>,
+}
+
+/// Published artifact compared with the tagged source in its repository.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ProvenanceReport {
+    pub package_version: String,
+
+    /// Source repository from galaxy.yml.
+    pub repository_url: String,
+
+    /// Git reference compared against, usually a version tag.
+    pub reference: String,
+
+    /// Changes from the tagged source to the published artifact.
+    pub differences: Vec<FileDifference>,
+
+    /// True if every published file is in the tagged source with identical content.
+    ///
+    /// Source files absent from the artifact, usually excluded by `build_ignore`, do not
+    /// count as a mismatch.
+    pub matches_source: bool,
+}
+
+/// Files generated by `ansible-galaxy collection build`, which are absent from the source.
+static BUILD_GENERATED_FILES: &[&str] = &["MANIFEST.json", "FILES.json"];
+
+/// Compare collection files in a git archive with those of the published artifact.
+///
+/// The collection root within the archive is the shallowest directory holding a galaxy.yml
+/// file, which is then dropped, as ansible-galaxy does when building.
+pub fn compare_with_source(
+    source_digests: &std::collections::BTreeMap<String, String>,
+    artifact_digests: &std::collections::BTreeMap<String, String>,
+) -> Result<(Vec<FileDifference>, bool)> {
+    let galaxy_file_path = source_digests
+        .keys()
+        .filter(|path| *path == "galaxy.yml" || path.ends_with("/galaxy.yml"))
+        .min_by_key(|path| path.matches('/').count())
+        .ok_or(format_err!("Failed to find galaxy.yml in source archive."))?;
+    let root = &galaxy_file_path[..galaxy_file_path.len() - "galaxy.yml".len()];
+    let source_digests = source_digests
+        .iter()
+        .filter_map(|(path, digest)| Some((path.strip_prefix(root)?.to_string(), digest.clone())))
+        .filter(|(path, _)| path != "galaxy.yml")
+        .collect();
+    let artifact_digests = artifact_digests
+        .iter()
+        .filter(|(path, _)| !BUILD_GENERATED_FILES.contains(&path.as_str()))
+        .map(|(path, digest)| (path.clone(), digest.clone()))
+        .collect();
+
+    let differences = diff_file_digests(&source_digests, &artifact_digests);
+    let matches_source = differences
+        .iter()
+        .all(|difference| difference.change == FileChange::Removed);
+    Ok((differences, matches_source))
+}
+
+#[test]
+fn test_compare_with_source() -> Result<()> {
+    let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+        Vec::new(),
+        flate2::Compression::default(),
+    ));
+    for (path, content) in [
+        ("repo-1.0.0/galaxy.yml", "namespace: example"),
+        ("repo-1.0.0/README.md", "readme"),
+        ("repo-1.0.0/plugins/modules/ping.py", "ping"),
+        ("repo-1.0.0/.github/workflows/ci.yml", "ci"),
+        ("repo-1.0.0/tests/sanity/galaxy.yml", "nested"),
+    ] {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_cksum();
+        builder.append_data(&mut header, path, content.as_bytes())?;
+    }
+    let source_digests = read_file_digests(builder.into_inner()?.finish()?.as_slice())?;
+    assert_eq!(source_digests.len(), 5);
+
+    let sha256 =
+        |content: &str| hex::encode(<sha2::Sha256 as sha2::Digest>::digest(content.as_bytes()));
+    let artifact_digests = maplit::btreemap! {
+        "MANIFEST.json".to_string() => sha256("{}"),
+        "FILES.json".to_string() => sha256("{}"),
+        "README.md".to_string() => sha256("readme"),
+        "plugins/modules/ping.py".to_string() => sha256("ping"),
+    };
+    let (differences, matches_source) = compare_with_source(&source_digests, &artifact_digests)?;
+    assert!(matches_source);
+    assert_eq!(
+        differences
+            .iter()
+            .map(|difference| (difference.path.as_str(), difference.change))
+            .collect::<Vec<_>>(),
+        vec![
+            (".github/workflows/ci.yml", FileChange::Removed),
+            ("tests/sanity/galaxy.yml", FileChange::Removed),
+        ]
+    );
+
+    let mut artifact_digests = artifact_digests;
+    artifact_digests.insert("plugins/modules/ping.py".to_string(), sha256("injected"));
+    artifact_digests.insert("plugins/modules/extra.py".to_string(), sha256("extra"));
+    let (differences, matches_source) = compare_with_source(&source_digests, &artifact_digests)?;
+    assert!(!matches_source);
+    assert_eq!(differences[1].path, "plugins/modules/extra.py");
+    assert_eq!(differences[1].change, FileChange::Added);
+    assert_eq!(differences[2].path, "plugins/modules/ping.py");
+    assert_eq!(differences[2].change, FileChange::Modified);
+    Ok(())
+}
+
+/// Structural overview of the content of a collection artifact.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct ContentInventory {
+    /// Module names from `plugins/modules`, including its subdirectories.
+    pub modules: Vec<String>,
+
+    /// Other plugin names by plugin type. For example: `filter`, `lookup` or `module_utils`
+    pub plugins: std::collections::BTreeMap<String, Vec<String>>,
+
+    /// Role names from `roles`.
+    pub roles: Vec<String>,
+
+    /// Playbook paths directly within `playbooks`.
+    pub playbooks: Vec<String>,
+
+    /// Python and system requirement files, such as `requirements.txt`, `bindep.txt` and
+    /// `meta/execution-environment.yml`.
+    pub requirement_files: Vec<String>,
+
+    pub file_count: usize,
+
+    /// Total uncompressed size of all files in bytes.
+    pub total_size: u64,
+
+    /// Files which warrant particular scrutiny, see `ContentWarningKind`.
+    pub warnings: Vec<ContentWarning>,
+}
+
+/// Kind of file which cannot be reviewed as source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum ContentWarningKind {
+    /// ELF, PE, Mach-O or Java class file.
+    CompiledBinary,
+
+    /// Shared library by file extension: `.so`, `.dll` or `.dylib`.
+    NativeLibrary,
+
+    /// Compiled Python: `.pyc` or `.pyo`.
+    PythonBytecode,
+
+    /// Non-text file larger than `MAX_OPAQUE_FILE_SIZE`.
+    OpaqueBlob,
+}
+
+/// Artifact file reported for review.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ContentWarning {
+    pub path: String,
+    pub kind: ContentWarningKind,
+    pub size: u64,
+}
+
+/// Size above which non-text files are reported as opaque blobs. Smaller ones, such as
+/// documentation images, are common.
+pub static MAX_OPAQUE_FILE_SIZE: u64 = 512 * 1024;
+
+/// Number of leading bytes of each file inspected for binary content.
+static CONTENT_SNIFF_SIZE: u64 = 8 * 1024;
+
+/// Classify a file given its name, leading bytes and size.
+fn get_content_warning_kind(file_name: &str, head: &[u8], size: u64) -> Option<ContentWarningKind> {
+    let magic_numbers: &[&[u8]] = &[
+        b"\x7fELF",
+        b"\xfe\xed\xfa\xce",
+        b"\xfe\xed\xfa\xcf",
+        b"\xce\xfa\xed\xfe",
+        b"\xcf\xfa\xed\xfe",
+        b"\xca\xfe\xba\xbe",
+    ];
+    let extension = std::path::Path::new(file_name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_lowercase())
+        .unwrap_or_default();
+    if matches!(extension.as_str(), "pyc" | "pyo") {
+        return Some(ContentWarningKind::PythonBytecode);
+    }
+    if matches!(extension.as_str(), "so" | "dll" | "dylib") || file_name.contains(".so.") {
+        return Some(ContentWarningKind::NativeLibrary);
+    }
+    if magic_numbers.iter().any(|magic| head.starts_with(magic)) || is_pe_executable(head) {
+        return Some(ContentWarningKind::CompiledBinary);
+    }
+    if size > MAX_OPAQUE_FILE_SIZE && !is_text(head) {
+        return Some(ContentWarningKind::OpaqueBlob);
+    }
+    None
+}
+
+/// Returns true if the bytes start with a DOS header pointing at a PE signature, as in
+/// Windows executables and DLLs.
+fn is_pe_executable(head: &[u8]) -> bool {
+    if !head.starts_with(b"MZ") || head.len() < 0x40 {
+        return false;
+    }
+    let pe_offset = u32::from_le_bytes([head[0x3c], head[0x3d], head[0x3e], head[0x3f]]) as usize;
+    head.get(pe_offset..pe_offset.saturating_add(4)) == Some(b"PE\0\0")
+}
+
+/// Returns true if the bytes look like text: UTF-8 without NUL bytes. An incomplete UTF-8
+/// sequence at the end, where the inspected prefix was cut, is accepted.
+fn is_text(head: &[u8]) -> bool {
+    if head.contains(&0) {
+        return false;
+    }
+    match std::str::from_utf8(head) {
+        Ok(_) => true,
+        Err(error) => error.error_len().is_none(),
+    }
+}
+
+#[test]
+fn test_get_content_warning_kind() {
+    let large_size = MAX_OPAQUE_FILE_SIZE + 1;
+    assert_eq!(
+        get_content_warning_kind("helper", b"\x7fELF\x02\x01", 100),
+        Some(ContentWarningKind::CompiledBinary)
+    );
+    assert_eq!(
+        get_content_warning_kind("_speedups.cpython-39-x86_64-linux-gnu.so", b"", 100),
+        Some(ContentWarningKind::NativeLibrary)
+    );
+    assert_eq!(
+        get_content_warning_kind("ping.cpython-39.pyc", b"a\r\r\n", 100),
+        Some(ContentWarningKind::PythonBytecode)
+    );
+    assert_eq!(
+        get_content_warning_kind("data.bin", b"\x00\x01\x02", large_size),
+        Some(ContentWarningKind::OpaqueBlob)
+    );
+    let mut pe_header = vec![0u8; 0x84];
+    pe_header[..2].copy_from_slice(b"MZ");
+    pe_header[0x3c] = 0x80;
+    pe_header[0x80..].copy_from_slice(b"PE\0\0");
+    assert_eq!(
+        get_content_warning_kind("setup.exe", &pe_header, 100),
+        Some(ContentWarningKind::CompiledBinary)
+    );
+    assert_eq!(
+        get_content_warning_kind(
+            "NOTES.txt",
+            "MZ notes, which are long enough to hold a \
+            DOS header but have no PE signature."
+                .as_bytes(),
+            100
+        ),
+        None
+    );
+    assert_eq!(
+        get_content_warning_kind("logo.png", b"\x89PNG\x00", 100),
+        None
+    );
+    assert_eq!(
+        get_content_warning_kind("README.md", "# Caf\u{e9}".as_bytes(), large_size),
+        None
+    );
+    assert_eq!(
+        get_content_warning_kind("cut.txt", &"\u{e9}".as_bytes()[..1], large_size),
+        None
+    );
+}
+
+/// Plugin source file extensions. Modules may be written in Python, PowerShell or C#.
+static PLUGIN_FILE_EXTENSIONS: &[&str] = &["py", "ps1", "psm1", "cs", "yml", "yaml"];
+
+/// Summarise the content of a gzipped collection tarball stream.
+///
+/// Compiled binaries, native libraries, Python bytecode and large non-text files are
+/// reported as warnings, since they cannot be reviewed as source.
+pub fn read_content_inventory<R: std::io::Read>(reader: R) -> Result<ContentInventory> {
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(reader));
+    let mut modules = std::collections::BTreeSet::new();
+    let mut plugins =
+        std::collections::BTreeMap::<String, std::collections::BTreeSet<String>>::new();
+    let mut roles = std::collections::BTreeSet::new();
+    let mut inventory = ContentInventory::default();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let components: Vec<String> = entry
+            .path()?
+            .components()
+            .filter_map(|component| match component {
+                std::path::Component::Normal(name) => Some(name.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .collect();
+        let size = entry.header().size()?;
+        inventory.file_count += 1;
+        inventory.total_size += size;
+
+        let path = components.join("/");
+        let file_name = components.last().map(|name| name.as_str()).unwrap_or("");
+        let mut head = Vec::new();
+        std::io::Read::read_to_end(
+            &mut std::io::Read::take(&mut entry, CONTENT_SNIFF_SIZE),
+            &mut head,
+        )?;
+        if let Some(kind) = get_content_warning_kind(file_name, &head, size) {
+            inventory.warnings.push(ContentWarning {
+                path: path.clone(),
+                kind,
+                size,
+            });
+        }
+        let components: Vec<&str> = components.iter().map(|name| name.as_str()).collect();
+        match components.as_slice() {
+            ["plugins", plugin_type, .., _] => {
+                let file_path = std::path::Path::new(file_name);
+                let is_plugin_file = file_name != "__init__.py"
+                    && file_path
+                        .extension()
+                        .and_then(|extension| extension.to_str())
+                        .map(|extension| PLUGIN_FILE_EXTENSIONS.contains(&extension))
+                        .unwrap_or(false);
+                if is_plugin_file {
+                    let name = file_path
+                        .file_stem()
+                        .map(|stem| stem.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    if *plugin_type == "modules" {
+                        modules.insert(name);
+                    } else {
+                        plugins
+                            .entry(plugin_type.to_string())
+                            .or_default()
+                            .insert(name);
+                    }
+                }
+            }
+            ["roles", role, _, ..] => {
+                roles.insert(role.to_string());
+            }
+            ["playbooks", playbook]
+                if playbook.ends_with(".yml") || playbook.ends_with(".yaml") =>
+            {
+                inventory.playbooks.push(path.clone());
+            }
+            _ => {}
+        }
+        if file_name.ends_with("requirements.txt")
+            || file_name == "bindep.txt"
+            || path == "meta/execution-environment.yml"
+        {
+            inventory.requirement_files.push(path);
+        }
+    }
+    inventory.modules = modules.into_iter().collect();
+    inventory.plugins = plugins
+        .into_iter()
+        .map(|(plugin_type, names)| (plugin_type, names.into_iter().collect()))
+        .collect();
+    inventory.roles = roles.into_iter().collect();
+    inventory.playbooks.sort();
+    inventory.requirement_files.sort();
+    Ok(inventory)
+}
+
+#[test]
+fn test_read_content_inventory() -> Result<()> {
+    let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+        Vec::new(),
+        flate2::Compression::default(),
+    ));
+    for path in [
+        "MANIFEST.json",
+        "plugins/modules/ping.py",
+        "plugins/modules/cloud/instance.py",
+        "plugins/modules/win_ping.ps1",
+        "plugins/modules/__init__.py",
+        "plugins/filter/json_query.py",
+        "plugins/lookup/vault.py",
+        "plugins/lookup/README.md",
+        "plugins/module_utils/common.py",
+        "roles/server/tasks/main.yml",
+        "roles/server/defaults/main.yml",
+        "playbooks/deploy.yml",
+        "playbooks/vars/main.yml",
+        "requirements.txt",
+        "meta/execution-environment.yml",
+        "meta/runtime.yml",
+    ] {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(2);
+        header.set_cksum();
+        builder.append_data(&mut header, path, &b"{}"[..])?;
+    }
+    let mut header = tar::Header::new_gnu();
+    header.set_size(4);
+    header.set_cksum();
+    builder.append_data(&mut header, "roles/server/files/tool", &b"\x7fELF"[..])?;
+    let inventory = read_content_inventory(builder.into_inner()?.finish()?.as_slice())?;
+    assert_eq!(
+        inventory,
+        ContentInventory {
+            modules: vec![
+                "instance".to_string(),
+                "ping".to_string(),
+                "win_ping".to_string()
+            ],
+            plugins: maplit::btreemap! {
+                "filter".to_string() => vec!["json_query".to_string()],
+                "lookup".to_string() => vec!["vault".to_string()],
+                "module_utils".to_string() => vec!["common".to_string()],
+            },
+            roles: vec!["server".to_string()],
+            playbooks: vec!["playbooks/deploy.yml".to_string()],
+            requirement_files: vec![
+                "meta/execution-environment.yml".to_string(),
+                "requirements.txt".to_string()
+            ],
+            file_count: 17,
+            total_size: 36,
+            warnings: vec![ContentWarning {
+                path: "roles/server/files/tool".to_string(),
+                kind: ContentWarningKind::CompiledBinary,
+                size: 4,
+            }],
+        }
+    );
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use anyhow::{format_err, Context, Result};
+
+/// Client ID used by ansible-galaxy when exchanging offline tokens.
+static CLIENT_ID: &str = "cloud-services";
+
+/// Access tokens are refreshed this long before they are due to expire.
+static EXPIRY_MARGIN: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Short-lived access token obtained from an SSO server.
+#[derive(Debug, Clone)]
+pub struct AccessToken {
+    pub token: String,
+    pub expires_at: std::time::Instant,
+}
+
+impl AccessToken {
+    /// Returns true if the token can still be used.
+    pub fn is_valid(&self) -> bool {
+        std::time::Instant::now() + EXPIRY_MARGIN < self.expires_at
+    }
+}
+
+/// Exchange an offline (refresh) token for an access token.
+///
+/// Implements the Keycloak refresh token grant used by Red Hat Automation Hub.
+pub fn exchange_offline_token(
+    client: &reqwest::blocking::Client,
+    auth_url: &url::Url,
+    offline_token: &str,
+) -> Result<AccessToken> {
+    let response = client
+        .post(auth_url.as_str())
+        .form(&[
+            ("grant_type", "refresh_token"),
+            ("client_id", CLIENT_ID),
+            ("refresh_token", offline_token),
+        ])
+        .send()
+        .context(format!("Failed to reach SSO server: {}", auth_url))?;
+
+    let status = response.status();
+    let body = response.text()?;
+    if !status.is_success() {
+        return Err(format_err!(
+            "Failed to exchange offline token ({}): {}",
+            status,
+            body
+        ));
+    }
+    parse_token_response(&body)
+}
+
+/// Exchange an offline (refresh) token for an access token using an async client.
+#[cfg(feature = "async")]
+pub async fn exchange_offline_token_async(
+    client: &reqwest::Client,
+    auth_url: &url::Url,
+    offline_token: &str,
+) -> Result<AccessToken> {
+    let response = client
+        .post(auth_url.as_str())
+        .form(&[
+            ("grant_type", "refresh_token"),
+            ("client_id", CLIENT_ID),
+            ("refresh_token", offline_token),
+        ])
+        .send()
+        .await
+        .context(format!("Failed to reach SSO server: {}", auth_url))?;
+
+    let status = response.status();
+    let body = response.text().await?;
+    if !status.is_success() {
+        return Err(format_err!(
+            "Failed to exchange offline token ({}): {}",
+            status,
+            body
+        ));
+    }
+    parse_token_response(&body)
+}
+
+fn parse_token_response(body: &str) -> Result<AccessToken> {
+    let json: serde_json::Value =
+        serde_json::from_str(body).context(format!("JSON was not well-formatted:\n{}", body))?;
+    let token = json["access_token"]
+        .as_str()
+        .ok_or(format_err!("Failed to find access_token in SSO response."))?;
+    let expires_in = json["expires_in"].as_u64().unwrap_or(0);
+    Ok(AccessToken {
+        token: token.to_string(),
+        expires_at: std::time::Instant::now() + std::time::Duration::from_secs(expires_in),
+    })
+}
+
+#[test]
+fn test_parse_token_response() -> Result<()> {
+    let access_token = parse_token_response(
+        r#"{"access_token": "abc", "expires_in": 900, "token_type": "Bearer"}"#,
+    )?;
+    assert_eq!(access_token.token, "abc");
+    assert!(access_token.is_valid());
+
+    let access_token = parse_token_response(r#"{"access_token": "abc"}"#)?;
+    assert!(!access_token.is_valid());
+    Ok(())
+}
@@ -1,7 +1,219 @@
-use vouch_ansible_lib;
+use structopt::StructOpt;
 use vouch_lib::extension::FromLib;
 
+/// Extension debugging commands. Not part of the vouch extension interface.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "vouch-ansible debug")]
+struct DebugArguments {
+    /// Trace registry requests and cache hits to stderr.
+    #[structopt(long)]
+    trace: bool,
+
+    /// Consider prereleases when selecting latest versions.
+    #[structopt(long)]
+    include_prereleases: bool,
+
+    /// Skip collection versions which require a different ansible-core version than installed.
+    #[structopt(long)]
+    check_requires_ansible: bool,
+
+    #[structopt(subcommand)]
+    command: DebugCommand,
+}
+
+#[derive(Debug, StructOpt)]
+enum DebugCommand {
+    /// Search registries for collections matching a query.
+    Search { query: String },
+
+    /// Suggest similarly named collections for a package name.
+    Suggest { package_name: String },
+
+    /// List popular collections which a package name may be a typosquat of.
+    Typosquat { package_name: String },
+
+    /// Show supplementary collection metadata.
+    Metadata { package_name: String },
+
+    /// Show publish timestamps of a collection version.
+    Version {
+        package_name: String,
+        package_version: Option<String>,
+    },
+
+    /// List all versions of a collection, newest first.
+    Versions { package_name: String },
+
+    /// Show publisher namespace details for a namespace or collection name.
+    Namespace { name: String },
+
+    /// Show documentation links for a collection version.
+    Docs {
+        package_name: String,
+        package_version: Option<String>,
+
+        /// Include the v3 docs-blob.
+        #[structopt(long)]
+        docs_blob: bool,
+    },
+
+    /// Show changelog entries between two collection versions.
+    Changelog {
+        package_name: String,
+        from_version: String,
+        to_version: String,
+    },
+
+    /// List installed collections for a project, including collections bundled with ansible.
+    Installed {
+        /// Project directory. Defaults to the current directory.
+        #[structopt(parse(from_os_str))]
+        working_directory: Option<std::path::PathBuf>,
+
+        /// Show only the installation of this collection used for version resolution.
+        #[structopt(long)]
+        package_name: Option<String>,
+    },
+
+    /// List dependency file entries which are skipped because they cannot be parsed.
+    Diagnostics {
+        /// Project directory. Defaults to the current directory.
+        #[structopt(parse(from_os_str))]
+        working_directory: Option<std::path::PathBuf>,
+    },
+
+    /// List collections whose requirements conflict across the project dependency files.
+    Conflicts {
+        /// Project directory. Defaults to the current directory.
+        #[structopt(parse(from_os_str))]
+        working_directory: Option<std::path::PathBuf>,
+    },
+
+    /// Resolve project dependencies to exact versions and write a lockfile.
+    Lock {
+        /// Project directory. Defaults to the current directory.
+        #[structopt(parse(from_os_str))]
+        working_directory: Option<std::path::PathBuf>,
+
+        /// Extension argument, as passed by vouch. For example:
+        /// --extension-arg=--version-resolution=registry or --extension-arg=--trace
+        #[structopt(long = "extension-arg", number_of_values = 1)]
+        extension_args: Vec<String>,
+    },
+}
+
+fn run_debug_command(
+    extension: &vouch_ansible_lib::AnsibleExtension,
+    command: DebugCommand,
+) -> anyhow::Result<()> {
+    let output = match command {
+        DebugCommand::Search { query } => {
+            serde_json::to_string_pretty(&extension.search_collections(&query)?)?
+        }
+        DebugCommand::Suggest { package_name } => {
+            serde_json::to_string_pretty(&extension.suggest_collections(&package_name)?)?
+        }
+        DebugCommand::Typosquat { package_name } => {
+            serde_json::to_string_pretty(&extension.typosquat_targets(&package_name)?)?
+        }
+        DebugCommand::Metadata { package_name } => {
+            serde_json::to_string_pretty(&extension.collection_metadata(&package_name)?)?
+        }
+        DebugCommand::Version {
+            package_name,
+            package_version,
+        } => serde_json::to_string_pretty(
+            &extension.version_metadata(&package_name, &package_version.as_deref())?,
+        )?,
+        DebugCommand::Versions { package_name } => {
+            serde_json::to_string_pretty(&extension.get_all_versions(&package_name)?)?
+        }
+        DebugCommand::Namespace { name } => {
+            serde_json::to_string_pretty(&extension.namespace_metadata(&name)?)?
+        }
+        DebugCommand::Docs {
+            package_name,
+            package_version,
+            docs_blob,
+        } => serde_json::to_string_pretty(&extension.documentation_metadata(
+            &package_name,
+            &package_version.as_deref(),
+            docs_blob,
+        )?)?,
+        DebugCommand::Changelog {
+            package_name,
+            from_version,
+            to_version,
+        } => serde_json::to_string_pretty(&extension.changelog_between(
+            &package_name,
+            &from_version,
+            &to_version,
+        )?)?,
+        DebugCommand::Installed {
+            working_directory,
+            package_name,
+        } => {
+            let working_directory = match working_directory {
+                Some(working_directory) => std::fs::canonicalize(working_directory)?,
+                None => std::env::current_dir()?,
+            };
+            match package_name {
+                Some(package_name) => serde_json::to_string_pretty(
+                    &extension.installed_collection(&working_directory, &package_name)?,
+                )?,
+                None => serde_json::to_string_pretty(
+                    &extension.installed_collections(&working_directory)?,
+                )?,
+            }
+        }
+        DebugCommand::Diagnostics { working_directory } => {
+            let working_directory = match working_directory {
+                Some(working_directory) => std::fs::canonicalize(working_directory)?,
+                None => std::env::current_dir()?,
+            };
+            serde_json::to_string_pretty(&extension.dependency_diagnostics(&working_directory)?)?
+        }
+        DebugCommand::Conflicts { working_directory } => {
+            let working_directory = match working_directory {
+                Some(working_directory) => std::fs::canonicalize(working_directory)?,
+                None => std::env::current_dir()?,
+            };
+            serde_json::to_string_pretty(&extension.dependency_conflicts(&working_directory)?)?
+        }
+        DebugCommand::Lock {
+            working_directory,
+            extension_args,
+        } => {
+            let working_directory = match working_directory {
+                Some(working_directory) => std::fs::canonicalize(working_directory)?,
+                None => std::env::current_dir()?,
+            };
+            let path = extension.write_lockfile(&working_directory, &extension_args)?;
+            std::fs::read_to_string(path)?.trim_end().to_string()
+        }
+    };
+    println!("{}", output);
+    Ok(())
+}
+
 fn main() {
     let mut extension = vouch_ansible_lib::AnsibleExtension::new();
+
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(|arg| arg.as_str()) == Some("debug") {
+        let arguments = DebugArguments::from_iter(args.iter().skip(1));
+        if arguments.trace {
+            extension.set_trace(true);
+        }
+        if arguments.include_prereleases {
+            extension.set_include_prereleases(true);
+        }
+        if arguments.check_requires_ansible {
+            extension.set_check_requires_ansible(true);
+        }
+        run_debug_command(&extension, arguments.command).unwrap();
+        return;
+    }
+
     vouch_lib::extension::commands::run(&mut extension).unwrap();
 }
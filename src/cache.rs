@@ -0,0 +1,231 @@
+use anyhow::{Context, Result};
+use sha2::Digest;
+
+/// Cached registry response and its validators.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CachedResponse {
+    pub url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub body: String,
+
+    /// Unix time in seconds at which the response was stored.
+    #[serde(default)]
+    pub stored_at: u64,
+
+    /// Registry responded 404 Not Found. The body is empty.
+    #[serde(default)]
+    pub not_found: bool,
+}
+
+impl CachedResponse {
+    /// Returns true if the response was stored less than `ttl` ago.
+    pub fn is_fresh(&self, ttl: std::time::Duration) -> bool {
+        get_unix_time().saturating_sub(self.stored_at) < ttl.as_secs()
+    }
+}
+
+/// Returns current Unix time in seconds.
+pub fn get_unix_time() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// Response cache freshness settings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachePolicy {
+    /// Ignore stored responses and always query the registry. Responses are still stored.
+    pub bypass: bool,
+
+    /// Freshness lifetime of collection, versions list and search responses.
+    pub ttl: std::time::Duration,
+
+    /// Freshness lifetime of collection version responses, which rarely change once published.
+    pub version_ttl: std::time::Duration,
+
+    /// Freshness lifetime of 404 Not Found results. Short so that newly published packages
+    /// are found soon after release.
+    pub not_found_ttl: std::time::Duration,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        Self {
+            bypass: false,
+            ttl: std::time::Duration::from_secs(60 * 60),
+            version_ttl: std::time::Duration::from_secs(7 * 24 * 60 * 60),
+            not_found_ttl: std::time::Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+impl CachePolicy {
+    /// Returns policy read from the VOUCH_ANSIBLE_CACHE_BYPASS, VOUCH_ANSIBLE_CACHE_TTL_SECS,
+    /// VOUCH_ANSIBLE_CACHE_VERSION_TTL_SECS and VOUCH_ANSIBLE_CACHE_NOT_FOUND_TTL_SECS
+    /// environment variables.
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let get_seconds = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .and_then(|value| value.parse::<u64>().ok())
+                .map(std::time::Duration::from_secs)
+        };
+        Self {
+            bypass: std::env::var("VOUCH_ANSIBLE_CACHE_BYPASS")
+                .ok()
+                .and_then(|value| super::config::parse_bool(&value))
+                .unwrap_or(default.bypass),
+            ttl: get_seconds("VOUCH_ANSIBLE_CACHE_TTL_SECS").unwrap_or(default.ttl),
+            version_ttl: get_seconds("VOUCH_ANSIBLE_CACHE_VERSION_TTL_SECS")
+                .unwrap_or(default.version_ttl),
+            not_found_ttl: get_seconds("VOUCH_ANSIBLE_CACHE_NOT_FOUND_TTL_SECS")
+                .unwrap_or(default.not_found_ttl),
+        }
+    }
+
+    /// Returns freshness lifetime for the given API URL.
+    pub fn get_ttl(&self, url: &str) -> std::time::Duration {
+        let path = url::Url::parse(url)
+            .map(|url| url.path().to_string())
+            .unwrap_or_default();
+        let is_version_url = path.trim_end_matches('/').rsplit('/').nth(1) == Some("versions");
+        if is_version_url {
+            self.version_ttl
+        } else {
+            self.ttl
+        }
+    }
+}
+
+#[test]
+fn test_cache_policy_ttl() {
+    let policy = CachePolicy::default();
+    assert_eq!(
+        policy.get_ttl(
+            "https://galaxy.ansible.com/api/v2/collections/community/general/versions/3.0.0/"
+        ),
+        policy.version_ttl
+    );
+    assert_eq!(
+        policy.get_ttl("https://galaxy.ansible.com/api/v2/collections/community/general/versions/"),
+        policy.ttl
+    );
+}
+
+/// On-disk registry response store, keyed by URL.
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    directory: std::path::PathBuf,
+    pub policy: CachePolicy,
+}
+
+impl ResponseCache {
+    pub fn new(directory: &std::path::Path, policy: &CachePolicy) -> Self {
+        Self {
+            directory: directory.to_path_buf(),
+            policy: policy.clone(),
+        }
+    }
+
+    /// Returns stored response for URL if within its freshness lifetime and not bypassed.
+    pub fn get_fresh(&self, url: &str) -> Option<CachedResponse> {
+        if self.policy.bypass {
+            return None;
+        }
+        self.get(url).filter(|response| {
+            let ttl = if response.not_found {
+                self.policy.not_found_ttl
+            } else {
+                self.policy.get_ttl(url)
+            };
+            response.is_fresh(ttl)
+        })
+    }
+
+    fn entry_path(&self, url: &str) -> std::path::PathBuf {
+        let digest = sha2::Sha256::digest(url.as_bytes());
+        self.directory.join(format!("{}.json", hex::encode(digest)))
+    }
+
+    /// Returns cached response for URL, if any, regardless of age.
+    ///
+    /// Unreadable entries are treated as missing.
+    pub fn get(&self, url: &str) -> Option<CachedResponse> {
+        let content = std::fs::read_to_string(self.entry_path(url)).ok()?;
+        let response: CachedResponse = serde_json::from_str(&content).ok()?;
+        if response.url != url {
+            return None;
+        }
+        Some(response)
+    }
+
+    /// Store response for URL.
+    pub fn insert(&self, response: &CachedResponse) -> Result<()> {
+        std::fs::create_dir_all(&self.directory).context(format!(
+            "Failed to create cache directory: {}",
+            self.directory.display()
+        ))?;
+        // Write then rename so concurrent readers never see a partial entry.
+        let path = self.entry_path(&response.url);
+        let temporary_path = path.with_extension(format!("{}.tmp", std::process::id()));
+        std::fs::write(&temporary_path, serde_json::to_string(response)?)?;
+        std::fs::rename(&temporary_path, &path)?;
+        Ok(())
+    }
+
+    /// Remove stored response for URL, if any. Failures to remove are ignored.
+    pub fn remove(&self, url: &str) {
+        let _ = std::fs::remove_file(self.entry_path(url));
+    }
+}
+
+/// Returns default cache directory.
+///
+/// Uses VOUCH_ANSIBLE_CACHE_DIR, then `$XDG_CACHE_HOME/vouch-ansible`,
+/// then `.cache/vouch-ansible` in the home directory.
+pub fn get_default_directory() -> Option<std::path::PathBuf> {
+    if let Some(directory) = std::env::var_os("VOUCH_ANSIBLE_CACHE_DIR") {
+        return Some(std::path::PathBuf::from(directory));
+    }
+    let cache_home = std::env::var_os("XDG_CACHE_HOME")
+        .map(std::path::PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| super::config::get_home_directory().map(|home| home.join(".cache")))?;
+    Some(cache_home.join("vouch-ansible"))
+}
+
+#[test]
+fn test_response_cache() -> Result<()> {
+    let directory = std::env::temp_dir().join(format!("vouch-ansible-test-{}", std::process::id()));
+    let cache = ResponseCache::new(&directory, &CachePolicy::default());
+    let url = "https://galaxy.ansible.com/api/v3/collections/community/general/";
+    assert_eq!(cache.get(url), None);
+
+    let response = CachedResponse {
+        url: url.to_string(),
+        etag: Some("\"abc\"".to_string()),
+        last_modified: None,
+        body: "{}".to_string(),
+        stored_at: get_unix_time(),
+        not_found: false,
+    };
+    cache.insert(&response)?;
+    assert_eq!(cache.get_fresh(url), Some(response.clone()));
+
+    let stale_response = CachedResponse {
+        stored_at: 0,
+        ..response
+    };
+    cache.insert(&stale_response)?;
+    assert_eq!(cache.get_fresh(url), None);
+    assert_eq!(cache.get(url), Some(stale_response));
+
+    cache.remove(url);
+    assert_eq!(cache.get(url), None);
+
+    std::fs::remove_dir_all(&directory)?;
+    Ok(())
+}
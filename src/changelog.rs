@@ -0,0 +1,208 @@
+use anyhow::{format_err, Context, Result};
+
+/// Changelog file paths within a collection artifact.
+pub static CHANGELOG_FILE_PATHS: &[&str] = &["changelogs/changelog.yaml", "CHANGELOG.rst"];
+
+/// Changes made in a single collection release.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct ChangelogEntry {
+    pub version: String,
+    pub release_date: Option<String>,
+
+    /// Change items by category. For example: `minor_changes`, `bugfixes`.
+    pub changes: std::collections::BTreeMap<String, Vec<String>>,
+}
+
+/// Parse changelog file content given its path within the artifact.
+pub fn parse_changelog(file_path: &str, content: &[u8]) -> Result<Vec<ChangelogEntry>> {
+    if file_path.ends_with(".yaml") {
+        parse_changelog_yaml(content)
+    } else {
+        Ok(parse_changelog_rst(&String::from_utf8_lossy(content)))
+    }
+}
+
+/// Parse antsibull-changelog `changelogs/changelog.yaml` content.
+fn parse_changelog_yaml(content: &[u8]) -> Result<Vec<ChangelogEntry>> {
+    let changelog: serde_json::Value =
+        serde_yaml::from_slice(content).context("Failed to parse changelog.yaml.")?;
+    let releases = changelog["releases"]
+        .as_object()
+        .ok_or(format_err!("Failed to parse changelog.yaml releases."))?;
+
+    let mut entries = Vec::new();
+    for (version, release) in releases {
+        let mut changes = std::collections::BTreeMap::new();
+        if let Some(release_changes) = release["changes"].as_object() {
+            for (category, items) in release_changes {
+                let items = match items {
+                    serde_json::Value::String(item) => vec![item.clone()],
+                    serde_json::Value::Array(items) => items
+                        .iter()
+                        .filter_map(|item| item.as_str())
+                        .map(|item| item.to_string())
+                        .collect(),
+                    _ => continue,
+                };
+                changes.insert(category.clone(), items);
+            }
+        }
+        entries.push(ChangelogEntry {
+            version: version.clone(),
+            release_date: release["release_date"].as_str().map(|d| d.to_string()),
+            changes,
+        });
+    }
+    Ok(entries)
+}
+
+/// Parse rendered CHANGELOG.rst content.
+///
+/// Releases are `vX.Y.Z` titles underlined with `=`, categories are titles underlined with `-`
+/// and items are `- ` bullets. Indented lines continue the previous item.
+fn parse_changelog_rst(content: &str) -> Vec<ChangelogEntry> {
+    let lines: Vec<&str> = content.lines().collect();
+    let is_underline =
+        |line: &str, character: char| !line.is_empty() && line.chars().all(|c| c == character);
+
+    let mut entries: Vec<ChangelogEntry> = Vec::new();
+    let mut category: Option<String> = None;
+    for (index, line) in lines.iter().enumerate() {
+        let next_line = lines
+            .get(index + 1)
+            .map(|line| line.trim_end())
+            .unwrap_or("");
+        if is_underline(next_line, '=') {
+            let version = line.trim().trim_start_matches('v');
+            if super::resolution::parse_version(version).is_some() {
+                entries.push(ChangelogEntry {
+                    version: version.to_string(),
+                    ..Default::default()
+                });
+                category = None;
+            }
+            continue;
+        }
+        let entry = match entries.last_mut() {
+            Some(entry) => entry,
+            None => continue,
+        };
+        if is_underline(next_line, '-') {
+            let name = line.trim().to_lowercase().replace(' ', "_");
+            entry.changes.entry(name.clone()).or_default();
+            category = Some(name);
+            continue;
+        }
+
+        let items = match category
+            .as_ref()
+            .and_then(|category| entry.changes.get_mut(category))
+        {
+            Some(items) => items,
+            None => continue,
+        };
+        if let Some(item) = line.strip_prefix("- ") {
+            items.push(item.trim().to_string());
+        } else if line.starts_with(char::is_whitespace) && !line.trim().is_empty() {
+            if let Some(item) = items.last_mut() {
+                item.push(' ');
+                item.push_str(line.trim());
+            }
+        }
+    }
+    entries
+}
+
+/// Returns entries for versions greater than `from_version` up to and including `to_version`,
+/// newest first.
+pub fn select_entries_between(
+    entries: Vec<ChangelogEntry>,
+    from_version: &str,
+    to_version: &str,
+) -> Result<Vec<ChangelogEntry>> {
+    let parse_version = |version: &str| -> Result<semver::Version> {
+        super::resolution::parse_version(version)
+            .ok_or(format_err!("Failed to parse version: {}", version))
+    };
+    let from_version = parse_version(from_version)?;
+    let to_version = parse_version(to_version)?;
+
+    let mut entries: Vec<(semver::Version, ChangelogEntry)> = entries
+        .into_iter()
+        .filter_map(|entry| Some((parse_version(&entry.version).ok()?, entry)))
+        .filter(|(version, _)| version > &from_version && version <= &to_version)
+        .collect();
+    entries.sort_by(|(a, _), (b, _)| super::resolution::compare_versions(b, a));
+    Ok(entries.into_iter().map(|(_, entry)| entry).collect())
+}
+
+#[test]
+fn test_select_entries_between() -> Result<()> {
+    let content = r#"
+ancestor: null
+releases:
+  1.0.0:
+    changes:
+      release_summary: Initial release.
+    release_date: '2021-01-01'
+  1.1.0:
+    changes:
+      minor_changes:
+        - Add foo option.
+  2.0.0:
+    changes:
+      breaking_changes:
+        - Remove bar module.
+"#;
+    let entries = parse_changelog("changelogs/changelog.yaml", content.as_bytes())?;
+    let entries = select_entries_between(entries, "1.0.0", "2.0.0")?;
+    assert_eq!(
+        entries
+            .iter()
+            .map(|e| e.version.as_str())
+            .collect::<Vec<_>>(),
+        vec!["2.0.0", "1.1.0"]
+    );
+    assert_eq!(
+        entries[1].changes["minor_changes"],
+        vec!["Add foo option.".to_string()]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_parse_changelog_rst() {
+    let content = r#"
+==============================
+Community General Release Notes
+==============================
+
+v1.1.0
+======
+
+Minor Changes
+-------------
+
+- foo - add option, which
+  continues here.
+
+v1.0.0
+======
+
+Bugfixes
+--------
+
+- bar - fix crash.
+"#;
+    let entries = parse_changelog_rst(content);
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].version, "1.1.0");
+    assert_eq!(
+        entries[0].changes["minor_changes"],
+        vec!["foo - add option, which continues here.".to_string()]
+    );
+    assert_eq!(
+        entries[1].changes["bugfixes"],
+        vec!["bar - fix crash.".to_string()]
+    );
+}
@@ -0,0 +1,110 @@
+use super::scheduler::get_host;
+use anyhow::{format_err, Result};
+
+/// Default number of consecutive failed requests after which a host is skipped.
+static DEFAULT_FAILURE_THRESHOLD: u32 = 5;
+
+#[derive(Debug, Default)]
+struct HostCircuit {
+    consecutive_failures: u32,
+    last_error: Option<String>,
+}
+
+/// Stops requests to registry hosts which keep failing. Shared by all clones.
+///
+/// A host is skipped for the rest of the run once its consecutive failure count reaches the
+/// threshold, so an unreachable registry costs a few timeouts rather than one per package.
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    /// Consecutive connection failures, timeouts or server errors before a host is skipped.
+    /// Zero disables the circuit breaker.
+    pub failure_threshold: u32,
+
+    hosts_: std::sync::Arc<std::sync::Mutex<std::collections::HashMap<String, HostCircuit>>>,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self {
+            failure_threshold: DEFAULT_FAILURE_THRESHOLD,
+            hosts_: Default::default(),
+        }
+    }
+}
+
+impl CircuitBreaker {
+    /// Returns circuit breaker read from the VOUCH_ANSIBLE_CIRCUIT_BREAKER_THRESHOLD
+    /// environment variable.
+    pub fn from_env() -> Self {
+        Self {
+            failure_threshold: std::env::var("VOUCH_ANSIBLE_CIRCUIT_BREAKER_THRESHOLD")
+                .ok()
+                .and_then(|value| value.parse::<u32>().ok())
+                .unwrap_or(DEFAULT_FAILURE_THRESHOLD),
+            hosts_: Default::default(),
+        }
+    }
+
+    /// Returns an error if requests to the URL host are being skipped.
+    pub fn check(&self, url: &str) -> Result<()> {
+        if self.failure_threshold == 0 {
+            return Ok(());
+        }
+        let host = get_host(url);
+        let hosts = self.lock_hosts();
+        match hosts.get(&host) {
+            Some(circuit) if circuit.consecutive_failures >= self.failure_threshold => {
+                Err(format_err!(
+                    "Skipping registry host {} after {} consecutive failed requests. Last error: {}",
+                    host,
+                    circuit.consecutive_failures,
+                    circuit.last_error.as_deref().unwrap_or("unknown")
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Record that the URL host responded.
+    pub fn record_success(&self, url: &str) {
+        self.lock_hosts().remove(&get_host(url));
+    }
+
+    /// Record a failed request to the URL host.
+    pub fn record_failure(&self, url: &str, error: &anyhow::Error) {
+        let mut hosts = self.lock_hosts();
+        let circuit = hosts.entry(get_host(url)).or_default();
+        circuit.consecutive_failures += 1;
+        circuit.last_error = Some(format!("{:#}", error));
+    }
+
+    fn lock_hosts(
+        &self,
+    ) -> std::sync::MutexGuard<'_, std::collections::HashMap<String, HostCircuit>> {
+        match self.hosts_.lock() {
+            Ok(hosts) => hosts,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+#[test]
+fn test_circuit_breaker() {
+    let circuit_breaker = CircuitBreaker {
+        failure_threshold: 2,
+        hosts_: Default::default(),
+    };
+    let url = "https://hub.example.com/api/galaxy/";
+    let error = format_err!("Connection refused.");
+
+    circuit_breaker.record_failure(url, &error);
+    circuit_breaker.record_success(url);
+    circuit_breaker.record_failure(url, &error);
+    assert!(circuit_breaker.check(url).is_ok());
+
+    circuit_breaker.record_failure(url, &error);
+    assert!(circuit_breaker.check(url).is_err());
+    assert!(circuit_breaker
+        .check("https://galaxy.ansible.com/api/")
+        .is_ok());
+}
@@ -0,0 +1,450 @@
+use anyhow::{format_err, Context, Result};
+
+/// Parsed ansible.cfg INI file.
+#[derive(Debug, Clone, Default)]
+pub struct AnsibleConfig {
+    sections: std::collections::HashMap<String, std::collections::HashMap<String, String>>,
+}
+
+impl AnsibleConfig {
+    /// Parse ansible.cfg file.
+    pub fn from_file(file_path: &std::path::Path) -> Result<Self> {
+        let content = std::fs::read_to_string(file_path)
+            .context(format!("Failed to read file: {}", file_path.display()))?;
+        Self::parse(&content).context(format!("Failed to parse INI: {}", file_path.display()))
+    }
+
+    /// Returns the ansible.cfg file which applies to a project directory, or an empty config
+    /// if there is none or it cannot be parsed. See `find_project_config_file`.
+    pub fn load(project_directory: &std::path::Path) -> Self {
+        find_project_config_file(project_directory)
+            .and_then(|path| Self::from_file(&path).ok())
+            .unwrap_or_default()
+    }
+
+    /// Parse ansible.cfg content.
+    ///
+    /// Follows Python configparser conventions: `key = value` or `key: value` entries,
+    /// `#` and `;` comment lines, `;` inline comments, and indented continuation lines.
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut sections =
+            std::collections::HashMap::<String, std::collections::HashMap<String, String>>::new();
+        let mut section: Option<String> = None;
+        let mut key: Option<String> = None;
+
+        for (line_index, line) in content.lines().enumerate() {
+            let trimmed_line = line.trim();
+            if trimmed_line.is_empty()
+                || trimmed_line.starts_with('#')
+                || trimmed_line.starts_with(';')
+            {
+                continue;
+            }
+
+            // Continuation of the previous value.
+            if line.starts_with(char::is_whitespace) {
+                if let (Some(section), Some(key)) = (&section, &key) {
+                    if let Some(value) = sections
+                        .get_mut(section)
+                        .and_then(|entries| entries.get_mut(key))
+                    {
+                        value.push('\n');
+                        value.push_str(strip_inline_comment(trimmed_line));
+                        continue;
+                    }
+                }
+            }
+
+            if trimmed_line.starts_with('[') && trimmed_line.ends_with(']') {
+                let name = trimmed_line[1..trimmed_line.len() - 1].trim().to_string();
+                sections.entry(name.clone()).or_default();
+                section = Some(name);
+                key = None;
+                continue;
+            }
+
+            let separator_index = trimmed_line.find(['=', ':']).ok_or(format_err!(
+                "Failed to parse line {}: {}",
+                line_index + 1,
+                trimmed_line
+            ))?;
+            let current_section = section.as_ref().ok_or(format_err!(
+                "Found entry before first section header on line {}.",
+                line_index + 1
+            ))?;
+            let entry_key = trimmed_line[..separator_index].trim().to_lowercase();
+            let entry_value = strip_inline_comment(trimmed_line[separator_index + 1..].trim());
+            sections
+                .entry(current_section.clone())
+                .or_default()
+                .insert(entry_key.clone(), entry_value.to_string());
+            key = Some(entry_key);
+        }
+
+        Ok(Self { sections })
+    }
+
+    /// Returns value for given section and key.
+    pub fn get(&self, section: &str, key: &str) -> Option<&str> {
+        self.sections
+            .get(section)
+            .and_then(|entries| entries.get(&key.to_lowercase()))
+            .map(|value| value.as_str())
+    }
+
+    /// Returns Galaxy server definitions in `[galaxy] server_list` order.
+    ///
+    /// The ANSIBLE_GALAXY_SERVER_LIST and ANSIBLE_GALAXY_SERVER_<NAME>_URL/_TOKEN/_AUTH_URL
+    /// environment variables take precedence over the config file, as in ansible-galaxy.
+    pub fn galaxy_servers(&self) -> Vec<GalaxyServer> {
+        self.galaxy_servers_from_env(|name| std::env::var(name).ok())
+    }
+
+    fn galaxy_servers_from_env<F: Fn(&str) -> Option<String>>(
+        &self,
+        env_var: F,
+    ) -> Vec<GalaxyServer> {
+        let server_list = match env_var("ANSIBLE_GALAXY_SERVER_LIST").or_else(|| {
+            self.get("galaxy", "server_list")
+                .map(|value| value.to_string())
+        }) {
+            Some(server_list) => server_list,
+            None => return Vec::new(),
+        };
+
+        let mut servers = Vec::new();
+        for name in server_list.split(',') {
+            let name = name.trim();
+            if name.is_empty() {
+                continue;
+            }
+            let section = format!("galaxy_server.{}", name);
+            let get_option = |key: &str| {
+                let env_var_name = format!(
+                    "ANSIBLE_GALAXY_SERVER_{}_{}",
+                    name.to_uppercase(),
+                    key.to_uppercase()
+                );
+                env_var(&env_var_name).or_else(|| self.get(&section, key).map(|v| v.to_string()))
+            };
+
+            let url = match get_option("url") {
+                Some(url) => url,
+                None => continue,
+            };
+            let auth_url = get_option("auth_url");
+            // The token file holds the token of the Galaxy server only, so is not sent
+            // elsewhere.
+            let token = match (get_option("token"), &auth_url) {
+                (None, None) if self.is_token_file_server(&url, &env_var) => {
+                    self.galaxy_token_from_file(&env_var)
+                }
+                (token, _) => token,
+            };
+            servers.push(GalaxyServer {
+                name: name.to_string(),
+                url,
+                token,
+                auth_url,
+                mirrors: get_option("mirrors")
+                    .map(|mirrors| parse_list(&mirrors))
+                    .unwrap_or_default(),
+                distribution: get_option("distribution"),
+                proxy_compatibility: get_option("proxy_compatibility")
+                    .and_then(|value| parse_bool(&value)),
+            });
+        }
+        servers
+    }
+
+    /// Returns true if the server URL has the host of the Galaxy server given by
+    /// ANSIBLE_GALAXY_SERVER, `[galaxy] server`, or galaxy.ansible.com by default.
+    fn is_token_file_server<F: Fn(&str) -> Option<String>>(&self, url: &str, env_var: &F) -> bool {
+        let galaxy_server = env_var("ANSIBLE_GALAXY_SERVER")
+            .or_else(|| self.get("galaxy", "server").map(|v| v.to_string()))
+            .unwrap_or_else(|| "https://galaxy.ansible.com".to_string());
+        match (url::Url::parse(url), url::Url::parse(&galaxy_server)) {
+            (Ok(url), Ok(galaxy_server)) => {
+                url.host_str() == galaxy_server.host_str()
+                    && url.port_or_known_default() == galaxy_server.port_or_known_default()
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the Galaxy server token stored by `ansible-galaxy login` or written by hand,
+    /// if any.
+    ///
+    /// Read from the `token` key of the YAML file given by ANSIBLE_GALAXY_TOKEN_PATH,
+    /// `[galaxy] token_path`, or `~/.ansible/galaxy_token`.
+    fn galaxy_token_from_file<F: Fn(&str) -> Option<String>>(&self, env_var: &F) -> Option<String> {
+        let token_path = env_var("ANSIBLE_GALAXY_TOKEN_PATH")
+            .or_else(|| self.get("galaxy", "token_path").map(|v| v.to_string()))
+            .map(std::path::PathBuf::from)
+            .or_else(|| {
+                get_home_directory_from_env(env_var)
+                    .map(|home| home.join(".ansible").join("galaxy_token"))
+            })?;
+        let content = std::fs::read_to_string(token_path).ok()?;
+        let token_file: serde_yaml::Value = serde_yaml::from_str(&content).ok()?;
+        token_file["token"]
+            .as_str()
+            .filter(|token| !token.is_empty())
+            .map(|token| token.to_string())
+    }
+}
+
+/// Parse INI boolean value.
+pub fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "1" | "yes" | "true" | "on" => Some(true),
+        "0" | "no" | "false" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// Parse comma or newline separated list.
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split([',', '\n'])
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// Strip `;` prefixed inline comment.
+fn strip_inline_comment(value: &str) -> &str {
+    match value.find(" ;") {
+        Some(index) => value[..index].trim_end(),
+        None => value,
+    }
+}
+
+/// Galaxy server definition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GalaxyServer {
+    pub name: String,
+    pub url: String,
+    pub token: Option<String>,
+    pub auth_url: Option<String>,
+
+    /// Mirror root URLs. Not an ansible-galaxy option, ignored by ansible itself.
+    pub mirrors: Vec<String>,
+
+    /// Pulp distribution base path. Not an ansible-galaxy option, ignored by ansible itself.
+    pub distribution: Option<String>,
+
+    /// Artifactory/Nexus compatibility mode. Auto-detected from the URL when unset.
+    /// Not an ansible-galaxy option, ignored by ansible itself.
+    pub proxy_compatibility: Option<bool>,
+}
+
+/// Returns the user home directory given by HOME or, on Windows, USERPROFILE.
+pub fn get_home_directory() -> Option<std::path::PathBuf> {
+    get_home_directory_from_env(|name| std::env::var(name).ok())
+}
+
+/// Expand a leading `~` to the home directory, which is USERPROFILE on Windows.
+pub fn expand_home_directory(path: &str) -> std::path::PathBuf {
+    match (path.strip_prefix('~'), get_home_directory()) {
+        (Some(relative_path), Some(home_directory)) => {
+            home_directory.join(relative_path.trim_start_matches(['/', '\\']))
+        }
+        _ => std::path::PathBuf::from(path),
+    }
+}
+
+fn get_home_directory_from_env<F: Fn(&str) -> Option<String>>(
+    env_var: F,
+) -> Option<std::path::PathBuf> {
+    env_var("HOME")
+        .or_else(|| env_var("USERPROFILE"))
+        .filter(|home| !home.is_empty())
+        .map(std::path::PathBuf::from)
+}
+
+#[test]
+fn test_get_home_directory_from_env() {
+    let env = maplit::hashmap! {"USERPROFILE" => r"C:\Users\user"};
+    assert_eq!(
+        get_home_directory_from_env(|name| env.get(name).map(|value| value.to_string())),
+        Some(std::path::PathBuf::from(r"C:\Users\user"))
+    );
+}
+
+/// Returns path to the ansible.cfg file which applies to the current process.
+///
+/// See `find_project_config_file`, with the current working directory as project directory.
+pub fn find_config_file() -> Option<std::path::PathBuf> {
+    find_project_config_file(&std::env::current_dir().ok()?)
+}
+
+/// Returns path to the ansible.cfg file which applies to a project directory.
+///
+/// Follows the ansible search order: the ANSIBLE_CONFIG environment variable, `ansible.cfg`
+/// in the project directory, `~/.ansible.cfg` and then `/etc/ansible/ansible.cfg`.
+/// The first file which exists is used.
+pub fn find_project_config_file(project_directory: &std::path::Path) -> Option<std::path::PathBuf> {
+    get_config_file_candidates(
+        project_directory,
+        std::env::var_os("ANSIBLE_CONFIG").map(std::path::PathBuf::from),
+        get_home_directory(),
+    )
+    .into_iter()
+    .find(|path| path.is_file())
+}
+
+/// Returns ansible.cfg paths in search order. ANSIBLE_CONFIG may name a directory which
+/// contains an ansible.cfg file.
+fn get_config_file_candidates(
+    project_directory: &std::path::Path,
+    ansible_config: Option<std::path::PathBuf>,
+    home_directory: Option<std::path::PathBuf>,
+) -> Vec<std::path::PathBuf> {
+    let mut candidates = Vec::new();
+    if let Some(path) = ansible_config.filter(|path| !path.as_os_str().is_empty()) {
+        if path.is_dir() {
+            candidates.push(path.join("ansible.cfg"));
+        } else {
+            candidates.push(path);
+        }
+    }
+    candidates.push(project_directory.join("ansible.cfg"));
+    if let Some(home_directory) = home_directory {
+        candidates.push(home_directory.join(".ansible.cfg"));
+    }
+    candidates.push(std::path::PathBuf::from("/etc/ansible/ansible.cfg"));
+    candidates
+}
+
+#[test]
+fn test_get_config_file_candidates() {
+    assert_eq!(
+        get_config_file_candidates(
+            std::path::Path::new("/project"),
+            Some(std::path::PathBuf::from("/config/custom.cfg")),
+            Some(std::path::PathBuf::from("/home/user")),
+        ),
+        vec![
+            std::path::PathBuf::from("/config/custom.cfg"),
+            std::path::PathBuf::from("/project/ansible.cfg"),
+            std::path::PathBuf::from("/home/user/.ansible.cfg"),
+            std::path::PathBuf::from("/etc/ansible/ansible.cfg"),
+        ]
+    );
+    assert_eq!(
+        get_config_file_candidates(std::path::Path::new("/project"), None, None),
+        vec![
+            std::path::PathBuf::from("/project/ansible.cfg"),
+            std::path::PathBuf::from("/etc/ansible/ansible.cfg"),
+        ]
+    );
+}
+
+#[test]
+fn test_galaxy_servers() -> Result<()> {
+    let config = AnsibleConfig::parse(
+        r#"
+[defaults]
+collections_path = ./collections
+
+[galaxy]
+server_list = automation_hub, release_galaxy
+
+# Certified content.
+[galaxy_server.automation_hub]
+url=https://console.redhat.com/api/automation-hub/content/published/
+auth_url=https://sso.redhat.com/auth/realms/redhat-external/protocol/openid-connect/token
+token=my_ah_token ; offline token
+
+[galaxy_server.release_galaxy]
+url: https://galaxy.ansible.com/
+"#,
+    )?;
+
+    assert_eq!(
+        config.galaxy_servers(),
+        vec![
+            GalaxyServer {
+                name: "automation_hub".to_string(),
+                url: "https://console.redhat.com/api/automation-hub/content/published/"
+                    .to_string(),
+                token: Some("my_ah_token".to_string()),
+                auth_url: Some(
+                    "https://sso.redhat.com/auth/realms/redhat-external/protocol/openid-connect/token"
+                        .to_string()
+                ),
+                mirrors: Vec::new(),
+                distribution: None,
+                proxy_compatibility: None,
+            },
+            GalaxyServer {
+                name: "release_galaxy".to_string(),
+                url: "https://galaxy.ansible.com/".to_string(),
+                token: None,
+                auth_url: None,
+                mirrors: Vec::new(),
+                distribution: None,
+                proxy_compatibility: None,
+            },
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_parse_crlf() -> Result<()> {
+    let config = AnsibleConfig::parse(
+        "[defaults]\r\ncollections_path = ./collections\r\n  ./vendor ; vendored\r\n",
+    )?;
+    assert_eq!(
+        config.get("defaults", "collections_path"),
+        Some("./collections\n./vendor")
+    );
+    Ok(())
+}
+
+#[test]
+fn test_galaxy_token_from_file() -> Result<()> {
+    let directory =
+        std::env::temp_dir().join(format!("vouch-ansible-token-test-{}", std::process::id()));
+    std::fs::create_dir_all(&directory)?;
+    let token_path = directory.join("galaxy_token");
+    std::fs::write(&token_path, "token: my_galaxy_token\n")?;
+
+    let config = AnsibleConfig::parse(&format!(
+        "[galaxy]\nserver_list = release_galaxy, private_hub\ntoken_path = {}\n\n\
+         [galaxy_server.release_galaxy]\nurl = https://galaxy.ansible.com/\n\n\
+         [galaxy_server.private_hub]\nurl = https://hub.example.com/api/galaxy/\n",
+        token_path.display()
+    ))?;
+    let servers = config.galaxy_servers_from_env(|_| None);
+    assert_eq!(servers[0].token, Some("my_galaxy_token".to_string()));
+    assert_eq!(servers[1].token, None);
+
+    std::fs::remove_dir_all(&directory)?;
+    Ok(())
+}
+
+#[test]
+fn test_galaxy_servers_from_env() {
+    let env = maplit::hashmap! {
+        "ANSIBLE_GALAXY_SERVER_LIST" => "private_hub",
+        "ANSIBLE_GALAXY_SERVER_PRIVATE_HUB_URL" => "https://hub.example.com/api/galaxy/",
+        "ANSIBLE_GALAXY_SERVER_PRIVATE_HUB_TOKEN" => "secret",
+    };
+    let servers = AnsibleConfig::default()
+        .galaxy_servers_from_env(|name| env.get(name).map(|value| value.to_string()));
+    assert_eq!(
+        servers,
+        vec![GalaxyServer {
+            name: "private_hub".to_string(),
+            url: "https://hub.example.com/api/galaxy/".to_string(),
+            token: Some("secret".to_string()),
+            auth_url: None,
+            mirrors: Vec::new(),
+            distribution: None,
+            proxy_compatibility: None,
+        }]
+    );
+}
@@ -0,0 +1,148 @@
+use super::resolution;
+
+/// Version requirement of a collection as declared by one dependency file.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct DeclaredRequirement {
+    pub path: std::path::PathBuf,
+    pub version_requirement: String,
+}
+
+/// Collection whose declared requirements cannot all be satisfied by one version.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct DependencyConflict {
+    /// Fully qualified collection name. For example: `community.general`
+    pub name: String,
+    pub requirements: Vec<DeclaredRequirement>,
+}
+
+impl std::fmt::Display for DependencyConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let requirements = self
+            .requirements
+            .iter()
+            .map(|requirement| {
+                format!(
+                    "{} ({})",
+                    requirement.version_requirement,
+                    requirement.path.display()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(
+            f,
+            "Conflicting version requirements for {}: {}",
+            self.name, requirements
+        )
+    }
+}
+
+/// Returns collections with incompatible requirements across dependency files.
+///
+/// Takes the collection requirements, as written, of each dependency file.
+pub fn find_conflicts(
+    file_requirements: &[(std::path::PathBuf, Vec<(String, String)>)],
+) -> Vec<DependencyConflict> {
+    let mut requirements_by_name =
+        std::collections::BTreeMap::<&str, Vec<DeclaredRequirement>>::new();
+    for (path, requirements) in file_requirements {
+        for (name, version_requirement) in requirements {
+            requirements_by_name
+                .entry(name)
+                .or_default()
+                .push(DeclaredRequirement {
+                    path: path.clone(),
+                    version_requirement: version_requirement.clone(),
+                });
+        }
+    }
+
+    requirements_by_name
+        .into_iter()
+        .filter(|(_, requirements)| {
+            let parsed_requirements: Vec<_> = requirements
+                .iter()
+                .filter_map(|requirement| {
+                    resolution::VersionRequirement::parse(&requirement.version_requirement).ok()
+                })
+                .collect();
+            !are_compatible(&parsed_requirements)
+        })
+        .map(|(name, requirements)| DependencyConflict {
+            name: name.to_string(),
+            requirements,
+        })
+        .collect()
+}
+
+/// Returns true if some version satisfies all requirements.
+///
+/// Candidate versions are the versions named by the requirements and the next patch, minor
+/// and major release of each, which covers typical comparator combinations.
+fn are_compatible(requirements: &[resolution::VersionRequirement]) -> bool {
+    if requirements.iter().all(|requirement| requirement.is_any()) {
+        return true;
+    }
+    let mut candidates = Vec::new();
+    for requirement in requirements {
+        for comparator in &requirement.requirement.comparators {
+            let version = semver::Version {
+                major: comparator.major,
+                minor: comparator.minor.unwrap_or(0),
+                patch: comparator.patch.unwrap_or(0),
+                pre: comparator.pre.clone(),
+                build: semver::BuildMetadata::EMPTY,
+            };
+            candidates.push(semver::Version::new(
+                version.major,
+                version.minor,
+                version.patch + 1,
+            ));
+            candidates.push(semver::Version::new(version.major, version.minor + 1, 0));
+            candidates.push(semver::Version::new(version.major + 1, 0, 0));
+            candidates.push(version);
+        }
+    }
+    candidates.iter().any(|candidate| {
+        requirements
+            .iter()
+            .all(|requirement| requirement.matches(candidate, true))
+    })
+}
+
+#[test]
+fn test_find_conflicts() {
+    let file_requirements = vec![
+        (
+            std::path::PathBuf::from("galaxy.yml"),
+            vec![
+                ("community.general".to_string(), ">=3.0.0".to_string()),
+                ("ansible.posix".to_string(), ">=1.0.0, <2.0.0".to_string()),
+            ],
+        ),
+        (
+            std::path::PathBuf::from("requirements.yml"),
+            vec![
+                ("community.general".to_string(), "<3.0.0".to_string()),
+                ("ansible.posix".to_string(), ">1.5".to_string()),
+            ],
+        ),
+    ];
+    let conflicts = find_conflicts(&file_requirements);
+    assert_eq!(
+        conflicts,
+        vec![DependencyConflict {
+            name: "community.general".to_string(),
+            requirements: vec![
+                DeclaredRequirement {
+                    path: std::path::PathBuf::from("galaxy.yml"),
+                    version_requirement: ">=3.0.0".to_string(),
+                },
+                DeclaredRequirement {
+                    path: std::path::PathBuf::from("requirements.yml"),
+                    version_requirement: "<3.0.0".to_string(),
+                },
+            ],
+        }]
+    );
+}
@@ -0,0 +1,22 @@
+/// Dependency file entry which was skipped because it could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct DependencyDiagnostic {
+    pub path: std::path::PathBuf,
+
+    /// Dependency name, or the entry as JSON if it has no name.
+    pub entry: String,
+
+    pub message: String,
+}
+
+impl std::fmt::Display for DependencyDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Skipped dependency {} in {}: {}",
+            self.entry,
+            self.path.display(),
+            self.message
+        )
+    }
+}
@@ -0,0 +1,236 @@
+/// Classified extension failure, for hosts which handle some failures differently.
+///
+/// Returned within `anyhow::Error`. Use `AnsibleExtensionError::find` to recover it, which
+/// also searches context added on the way.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnsibleExtensionError {
+    /// Registry resource does not exist: a 404 response, or a collection missing from a
+    /// local directory registry.
+    NotFound { url: String },
+
+    /// Registry request rate limit exceeded (429).
+    RateLimited {
+        url: String,
+        retry_after: Option<std::time::Duration>,
+    },
+
+    /// Registry credentials are missing or were rejected (401 or 403).
+    AuthRequired { url: String, status: u16 },
+
+    /// Connection failure, timeout or registry server error.
+    Network { url: String, message: String },
+
+    /// Invalid MANIFEST.json file.
+    ParseManifest {
+        path: std::path::PathBuf,
+        location: Option<ParseLocation>,
+        message: String,
+    },
+
+    /// Invalid galaxy.yml file.
+    ParseGalaxyYml {
+        path: std::path::PathBuf,
+        location: Option<ParseLocation>,
+        message: String,
+    },
+
+    /// Invalid requirements.yml file.
+    ParseRequirementsYml {
+        path: std::path::PathBuf,
+        location: Option<ParseLocation>,
+        message: String,
+    },
+
+    /// External command, such as git or gpg, exited unsuccessfully.
+    SubprocessFailed {
+        command: String,
+        status: Option<i32>,
+        stderr: String,
+    },
+}
+
+/// Position of a parse failure within a file. Lines and columns start at 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseLocation {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl AnsibleExtensionError {
+    /// Returns the classified failure within an error chain, if any.
+    pub fn find(error: &anyhow::Error) -> Option<&Self> {
+        error.chain().find_map(|cause| cause.downcast_ref::<Self>())
+    }
+
+    pub(crate) fn from_manifest_error(path: &std::path::Path, error: &serde_json::Error) -> Self {
+        let (location, message) = get_json_error_parts(error);
+        Self::ParseManifest {
+            path: path.to_path_buf(),
+            location,
+            message,
+        }
+    }
+
+    pub(crate) fn from_galaxy_yml_error(path: &std::path::Path, error: &serde_yaml::Error) -> Self {
+        let (location, message) = get_yaml_error_parts(error);
+        Self::ParseGalaxyYml {
+            path: path.to_path_buf(),
+            location,
+            message,
+        }
+    }
+
+    pub(crate) fn from_requirements_yml_error(
+        path: &std::path::Path,
+        error: &serde_yaml::Error,
+    ) -> Self {
+        let (location, message) = get_yaml_error_parts(error);
+        Self::ParseRequirementsYml {
+            path: path.to_path_buf(),
+            location,
+            message,
+        }
+    }
+}
+
+/// Returns the location of a JSON parse error and its message without the location suffix.
+fn get_json_error_parts(error: &serde_json::Error) -> (Option<ParseLocation>, String) {
+    // Line 0 is given for errors without a position, such as read failures.
+    let location = Some(ParseLocation {
+        line: error.line(),
+        column: error.column(),
+    })
+    .filter(|location| location.line > 0);
+    (
+        location,
+        strip_location_suffix(&error.to_string(), location),
+    )
+}
+
+/// Returns the location of a YAML parse error and its message without the location suffix.
+fn get_yaml_error_parts(error: &serde_yaml::Error) -> (Option<ParseLocation>, String) {
+    let location = error.location().map(|location| ParseLocation {
+        line: location.line(),
+        column: location.column(),
+    });
+    (
+        location,
+        strip_location_suffix(&error.to_string(), location),
+    )
+}
+
+fn strip_location_suffix(message: &str, location: Option<ParseLocation>) -> String {
+    let location = match location {
+        Some(location) => location,
+        None => return message.to_string(),
+    };
+    let suffix = format!(" at line {} column {}", location.line, location.column);
+    message.strip_suffix(&suffix).unwrap_or(message).to_string()
+}
+
+impl std::fmt::Display for AnsibleExtensionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound { url } => write!(f, "Registry resource not found: {}", url),
+            Self::RateLimited { url, .. } => {
+                write!(f, "Registry rate limit exceeded (429): {}", url)
+            }
+            Self::AuthRequired { url, status } => write!(
+                f,
+                "Registry authentication required ({}), check the registry token: {}",
+                status, url
+            ),
+            Self::Network { url, message } => write!(f, "{}: {}", message, url),
+            Self::ParseManifest {
+                path,
+                location,
+                message,
+            }
+            | Self::ParseGalaxyYml {
+                path,
+                location,
+                message,
+            }
+            | Self::ParseRequirementsYml {
+                path,
+                location,
+                message,
+            } => match location {
+                Some(location) => write!(
+                    f,
+                    "{}:{}:{}: {}",
+                    path.display(),
+                    location.line,
+                    location.column,
+                    message
+                ),
+                None => write!(f, "Failed to parse {}: {}", path.display(), message),
+            },
+            Self::SubprocessFailed {
+                command,
+                status,
+                stderr,
+            } => {
+                match status {
+                    Some(status) => write!(f, "Command failed ({}): {}", status, command)?,
+                    None => write!(f, "Command terminated: {}", command)?,
+                }
+                if !stderr.trim().is_empty() {
+                    write!(f, "\n{}", stderr.trim_end())?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for AnsibleExtensionError {}
+
+/// Returns true if the error chain holds `AnsibleExtensionError::NotFound`.
+pub fn is_not_found(error: &anyhow::Error) -> bool {
+    matches!(
+        AnsibleExtensionError::find(error),
+        Some(AnsibleExtensionError::NotFound { .. })
+    )
+}
+
+#[test]
+fn test_find() {
+    let error = anyhow::Error::new(AnsibleExtensionError::NotFound {
+        url: "https://galaxy.ansible.com/api/v2/collections/community/missing/".to_string(),
+    })
+    .context("Failed to find package community.missing in any registry.");
+    assert!(matches!(
+        AnsibleExtensionError::find(&error),
+        Some(AnsibleExtensionError::NotFound { .. })
+    ));
+    assert_eq!(
+        AnsibleExtensionError::find(&anyhow::format_err!("Other failure.")),
+        None
+    );
+}
+
+#[test]
+fn test_parse_error_location() -> anyhow::Result<()> {
+    let error = serde_json::from_str::<serde_json::Value>("{\n  \"name\": }").unwrap_err();
+    assert_eq!(
+        AnsibleExtensionError::from_manifest_error(std::path::Path::new("MANIFEST.json"), &error)
+            .to_string(),
+        "MANIFEST.json:2:11: expected value"
+    );
+
+    let error =
+        serde_yaml::from_str::<serde_json::Value>("dependencies:\n  - a\n  b: c\n").unwrap_err();
+    let error =
+        AnsibleExtensionError::from_galaxy_yml_error(std::path::Path::new("galaxy.yml"), &error);
+    assert!(matches!(
+        error,
+        AnsibleExtensionError::ParseGalaxyYml {
+            location: Some(ParseLocation { line: 3, .. }),
+            ..
+        }
+    ));
+    assert!(error.to_string().starts_with("galaxy.yml:3:"));
+    assert!(!error.to_string().contains(" at line "));
+    Ok(())
+}
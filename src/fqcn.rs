@@ -0,0 +1,88 @@
+/// Package name which is not of the `namespace.name` form used by collections and roles.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidPackageNameError {
+    pub package_name: String,
+    pub hint: String,
+}
+
+impl std::fmt::Display for InvalidPackageNameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Invalid package name {:?}: {}",
+            self.package_name, self.hint
+        )
+    }
+}
+
+impl std::error::Error for InvalidPackageNameError {}
+
+/// Check that a package name is a fully qualified collection name: `namespace.collection`.
+///
+/// Each part must start with a letter or digit and contain only letters, digits and
+/// underscores. Hyphens are also accepted for standalone roles, such as
+/// `dev-sec.ssh-hardening`.
+pub fn check_package_name(package_name: &str) -> Result<(), InvalidPackageNameError> {
+    let error = |hint: String| InvalidPackageNameError {
+        package_name: package_name.to_string(),
+        hint,
+    };
+    let parts: Vec<&str> = package_name.split('.').collect();
+    match parts.len() {
+        2 => {}
+        // Artifact file names join the namespace and name with a hyphen.
+        1 if package_name.contains('-') => {
+            return Err(error(format!(
+                "expected namespace.collection. Did you mean {}?",
+                package_name.replacen('-', ".", 1)
+            )))
+        }
+        1 => {
+            return Err(error(
+                "expected namespace.collection, for example community.general.".to_string(),
+            ))
+        }
+        _ => {
+            return Err(error(format!(
+            "expected namespace.collection. For a module or plugin name, use its collection: {}.",
+            parts[..2].join(".")
+        )))
+        }
+    }
+    for part in parts {
+        let is_valid = part
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphanumeric())
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !is_valid {
+            return Err(error(format!(
+                "{:?} must start with a letter or digit and contain only letters, digits and underscores.",
+                part
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_check_package_name() {
+    assert!(check_package_name("community.general").is_ok());
+    assert!(check_package_name("dev-sec.ssh-hardening").is_ok());
+    assert!(check_package_name("community").is_err());
+    assert!(check_package_name("community.").is_err());
+    assert!(check_package_name("community._general").is_err());
+    assert!(check_package_name("community.gen/eral").is_err());
+    assert_eq!(
+        check_package_name("community.general.ufw")
+            .unwrap_err()
+            .hint,
+        "expected namespace.collection. For a module or plugin name, use its collection: community.general."
+    );
+    assert_eq!(
+        check_package_name("community-general").unwrap_err().hint,
+        "expected namespace.collection. Did you mean community.general?"
+    );
+}
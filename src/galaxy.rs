@@ -1,30 +1,244 @@
+use super::error::AnsibleExtensionError;
+use super::error::AnsibleExtensionError::{ParseGalaxyYml, ParseManifest};
 use anyhow::{format_err, Context, Result};
 
 static HOST_NAME: &str = "galaxy.ansible.com";
 
-/// Returns global dependencies.
-pub fn get_global_dependencies() -> Result<std::collections::HashMap<String, String>> {
-    let handle = std::process::Command::new("ansible-galaxy")
-        .args(["collection", "list", "--format", "json"])
+/// Returns installed collections in precedence order.
+///
+/// Collections are read directly from the collections paths which apply to the project
+/// directory, followed by collections bundled with the ansible installation given by
+/// `ansible_version_output`. If none are found, falls back to `ansible-galaxy collection list`,
+/// which also finds collections installed in other locations.
+pub fn get_installed_collections(
+    project_directory: &std::path::Path,
+    ansible_version_output: Option<&str>,
+) -> Result<Vec<super::installed::InstalledCollection>> {
+    let mut collections = super::installed::find_installed_collections(
+        &super::installed::get_collections_paths(project_directory),
+    );
+    if let Some(ansible_version_output) = ansible_version_output {
+        if let Some(ansible_module_location) = parse_ansible_module_location(ansible_version_output)
+        {
+            collections.extend(super::installed::find_bundled_collections(
+                &ansible_module_location,
+                parse_ansible_core_version(ansible_version_output).as_ref(),
+            ));
+        }
+    }
+    if !collections.is_empty() {
+        return Ok(collections);
+    }
+    get_ansible_galaxy_collections(project_directory)
+}
+
+/// Returns the directory of collections bundled with ansible, given `ansible --version` output.
+pub fn get_bundled_collections_path(ansible_version_output: &str) -> Option<std::path::PathBuf> {
+    parse_ansible_module_location(ansible_version_output)?
+        .parent()
+        .map(|site_packages| site_packages.join("ansible_collections"))
+}
+
+/// Returns an identifier of the ansible installation which installed collections are listed
+/// with: the ansible-galaxy command and the `ansible --version` output, which gives the ansible
+/// executable location and version.
+pub fn get_ansible_installation_id(ansible_version_output: Option<&str>) -> String {
+    let (ansible_galaxy_bin, command_args) = get_ansible_galaxy_command();
+    format!(
+        "{} {}\n{}",
+        ansible_galaxy_bin.display(),
+        command_args.join(" "),
+        ansible_version_output.unwrap_or_default()
+    )
+}
+
+/// Returns standalone roles installed by ansible-galaxy in precedence order.
+///
+/// Roles are read directly from the roles paths which apply to the project directory. If none
+/// are found, falls back to `ansible-galaxy role list`.
+pub fn get_installed_roles(
+    project_directory: &std::path::Path,
+) -> Result<Vec<super::installed::InstalledRole>> {
+    let roles = super::installed::find_installed_roles(&super::installed::get_roles_paths(
+        project_directory,
+    ));
+    if !roles.is_empty() {
+        return Ok(roles);
+    }
+    Ok(run_ansible_galaxy(project_directory, &["role", "list"])?
+        .map(|stdout| parse_role_list(&stdout))
+        .unwrap_or_default())
+}
+
+/// Parse `ansible-galaxy role list` output. Roles are listed under `# <roles path>` headings
+/// as `- <name>, <version>`. Roles of unknown version are skipped.
+fn parse_role_list(stdout: &str) -> Vec<super::installed::InstalledRole> {
+    let mut roles = Vec::new();
+    let mut roles_path = std::path::PathBuf::new();
+    for line in stdout.lines() {
+        let line = line.trim();
+        if let Some(path) = line.strip_prefix('#') {
+            roles_path = std::path::PathBuf::from(path.trim());
+            continue;
+        }
+        let (name, version) = match line
+            .strip_prefix("- ")
+            .and_then(|entry| entry.split_once(','))
+        {
+            Some((name, version)) => (name.trim(), version.trim()),
+            None => continue,
+        };
+        if version.is_empty() || version.starts_with('(') {
+            continue;
+        }
+        roles.push(super::installed::InstalledRole {
+            name: name.to_string(),
+            version: version.to_string(),
+            path: roles_path.join(name),
+        });
+    }
+    roles
+}
+
+#[test]
+fn test_parse_role_list() {
+    let stdout = "# /home/user/.ansible/roles\n\
+                  - geerlingguy.docker, 6.1.0\n\
+                  - webserver, (unknown version)\n\
+                  # /etc/ansible/roles\n\
+                  [WARNING]: - the configured path /usr/share/ansible/roles does not exist.\n";
+    assert_eq!(
+        parse_role_list(stdout),
+        vec![super::installed::InstalledRole {
+            name: "geerlingguy.docker".to_string(),
+            version: "6.1.0".to_string(),
+            path: std::path::PathBuf::from("/home/user/.ansible/roles/geerlingguy.docker"),
+        }]
+    );
+}
+
+/// Returns the program and leading arguments which run ansible-galaxy.
+///
+/// VOUCH_ANSIBLE_GALAXY_PYTHON names a Python interpreter which runs
+/// `python -m ansible.cli.galaxy`, for virtualenvs without console scripts on PATH.
+/// Otherwise VOUCH_ANSIBLE_GALAXY_BIN overrides the ansible-galaxy executable path, for
+/// example when ansible-galaxy is installed in a virtualenv which is not on PATH.
+fn get_ansible_galaxy_command() -> (std::path::PathBuf, Vec<&'static str>) {
+    let get_path = |name: &str| {
+        std::env::var_os(name)
+            .filter(|path| !path.is_empty())
+            .map(std::path::PathBuf::from)
+    };
+    if let Some(python) = get_path("VOUCH_ANSIBLE_GALAXY_PYTHON") {
+        return (python, vec!["-m", "ansible.cli.galaxy"]);
+    }
+    (
+        get_path("VOUCH_ANSIBLE_GALAXY_BIN")
+            .unwrap_or_else(|| std::path::PathBuf::from("ansible-galaxy")),
+        Vec::new(),
+    )
+}
+
+static DEFAULT_ANSIBLE_GALAXY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+static DEFAULT_MAX_ANSIBLE_GALAXY_OUTPUT_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Run ansible-galaxy in the project directory, so that the project ansible.cfg applies, and
+/// return its stdout. Returns None if ansible-galaxy is not installed.
+///
+/// The command is killed after VOUCH_ANSIBLE_GALAXY_TIMEOUT_SECS (default 60) seconds, or
+/// once it writes more than VOUCH_ANSIBLE_GALAXY_MAX_OUTPUT_BYTES (default 16 MiB) to stdout.
+fn run_ansible_galaxy(
+    project_directory: &std::path::Path,
+    args: &[&str],
+) -> Result<Option<String>> {
+    let (ansible_galaxy_bin, command_args) = get_ansible_galaxy_command();
+    let child = std::process::Command::new(&ansible_galaxy_bin)
+        .args(&command_args)
+        .args(args)
+        .current_dir(project_directory)
         .stdin(std::process::Stdio::null())
-        .stderr(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
         .stdout(std::process::Stdio::piped())
-        .output()?;
-    let stdout = String::from_utf8_lossy(&handle.stdout);
-    let stdout = stdout.to_string();
+        .spawn();
+    let child = match child {
+        Ok(child) => child,
+        // Projects can still be scanned without ansible installed.
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            eprintln!(
+                "vouch-ansible: warning: ansible-galaxy not found, \
+                 continuing without installed collections and roles: {}",
+                ansible_galaxy_bin.display()
+            );
+            return Ok(None);
+        }
+        Err(error) => {
+            return Err(error).context(format!(
+                "Failed to run ansible-galaxy: {}",
+                ansible_galaxy_bin.display()
+            ))
+        }
+    };
+    let (timeout, max_output_size) = get_ansible_command_limits();
+    let output = wait_with_limits(child, timeout, max_output_size).context(format!(
+        "Failed to run ansible-galaxy: {}",
+        ansible_galaxy_bin.display()
+    ))?;
+    Ok(Some(String::from_utf8_lossy(&output.stdout).to_string()))
+}
+
+/// Returns the timeout and stdout size limit of ansible and ansible-galaxy commands.
+fn get_ansible_command_limits() -> (std::time::Duration, u64) {
+    let timeout = super::http::get_env_seconds("VOUCH_ANSIBLE_GALAXY_TIMEOUT_SECS")
+        .unwrap_or(DEFAULT_ANSIBLE_GALAXY_TIMEOUT);
+    let max_output_size = std::env::var("VOUCH_ANSIBLE_GALAXY_MAX_OUTPUT_BYTES")
+        .ok()
+        .and_then(|value| value.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_MAX_ANSIBLE_GALAXY_OUTPUT_SIZE);
+    (timeout, max_output_size)
+}
+
+/// Returns collections listed by `ansible-galaxy collection list`.
+fn get_ansible_galaxy_collections(
+    project_directory: &std::path::Path,
+) -> Result<Vec<super::installed::InstalledCollection>> {
+    let stdout = match run_ansible_galaxy(
+        project_directory,
+        &["collection", "list", "--format", "json"],
+    )? {
+        Some(stdout) => stdout,
+        None => return Ok(Vec::new()),
+    };
+    let mut collections = match serde_json::from_str::<serde_json::Value>(&stdout) {
+        Ok(json) => parse_collection_list_json(&json)?,
+        // Older ansible-galaxy versions lack `--format json` and print usage instead.
+        Err(_) => match run_ansible_galaxy(project_directory, &["collection", "list"])? {
+            Some(stdout) => parse_collection_list_table(&stdout),
+            None => Vec::new(),
+        },
+    };
+    // JSON object keys are not in collections path order.
+    super::installed::sort_by_precedence(
+        &mut collections,
+        &super::installed::get_collections_paths(project_directory),
+    );
+    Ok(collections)
+}
 
-    let mut dependencies = std::collections::HashMap::<String, String>::new();
+/// Parse `ansible-galaxy collection list --format json` output.
+fn parse_collection_list_json(
+    json: &serde_json::Value,
+) -> Result<Vec<super::installed::InstalledCollection>> {
+    let mut collections = Vec::new();
 
-    let json: serde_json::Value = serde_json::from_str(&stdout)?;
     let json = match json.as_object() {
         Some(x) => x,
-        None => return Ok(dependencies),
+        None => return Ok(collections),
     };
 
     let json_error_message =
         "Failed to parse JSON from command: ansible-galaxy collection list --format json";
 
-    for (_collections_directory, packages) in json.into_iter() {
+    for (collections_directory, packages) in json.into_iter() {
         let packages = packages
             .as_object()
             .ok_or(format_err!(json_error_message))?;
@@ -33,11 +247,309 @@ pub fn get_global_dependencies() -> Result<std::collections::HashMap<String, Str
                 Some(x) => x,
                 None => continue,
             };
-            dependencies.insert(package_name.clone(), package_version.to_string());
+            collections.push(super::installed::InstalledCollection {
+                name: package_name.clone(),
+                version: package_version.to_string(),
+                path: super::installed::get_collection_directory(
+                    std::path::Path::new(collections_directory),
+                    package_name,
+                ),
+                is_bundled: false,
+            });
         }
     }
 
-    Ok(dependencies)
+    Ok(collections)
+}
+
+/// Parse human readable `ansible-galaxy collection list` output. Collections are listed
+/// in a `Collection Version` table under each `# <collections path>` heading. Collections
+/// of unknown version, shown as `*`, are skipped.
+fn parse_collection_list_table(stdout: &str) -> Vec<super::installed::InstalledCollection> {
+    let mut collections = Vec::new();
+    let mut collections_directory = std::path::PathBuf::new();
+    for line in stdout.lines() {
+        let line = line.trim();
+        if let Some(path) = line.strip_prefix('#') {
+            collections_directory = std::path::PathBuf::from(path.trim());
+            continue;
+        }
+        let mut columns = line.split_whitespace();
+        let (name, version) = match (columns.next(), columns.next(), columns.next()) {
+            (Some(name), Some(version), None) => (name, version),
+            _ => continue,
+        };
+        if name == "Collection" || name.starts_with('-') || version == "*" {
+            continue;
+        }
+        if super::fqcn::check_package_name(name).is_err() {
+            continue;
+        }
+        collections.push(super::installed::InstalledCollection {
+            name: name.to_string(),
+            version: version.to_string(),
+            path: super::installed::get_collection_directory(&collections_directory, name),
+            is_bundled: false,
+        });
+    }
+    collections
+}
+
+#[test]
+fn test_parse_collection_list_table() {
+    let stdout = "\n# /home/user/.ansible/collections/ansible_collections\n\
+                  Collection        Version\n\
+                  ----------------- -------\n\
+                  community.general 7.0.0  \n\
+                  local.unversioned *      \n";
+    assert_eq!(
+        parse_collection_list_table(stdout),
+        vec![super::installed::InstalledCollection {
+            name: "community.general".to_string(),
+            version: "7.0.0".to_string(),
+            path: std::path::PathBuf::from(
+                "/home/user/.ansible/collections/ansible_collections/community/general"
+            ),
+            is_bundled: false,
+        }]
+    );
+}
+
+/// Wait for a child process and return its output.
+///
+/// The child is killed if it runs longer than `timeout` or writes more than
+/// `max_output_size` bytes to stdout. Stderr, if piped, is truncated to `max_output_size`
+/// bytes.
+pub fn wait_with_limits(
+    mut child: std::process::Child,
+    timeout: std::time::Duration,
+    max_output_size: u64,
+) -> Result<std::process::Output> {
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or(format_err!("Failed to capture process stdout."))?;
+    let reader = std::thread::spawn(move || -> std::io::Result<Vec<u8>> {
+        let mut output = Vec::new();
+        std::io::Read::read_to_end(
+            &mut std::io::Read::take(stdout, max_output_size + 1),
+            &mut output,
+        )?;
+        Ok(output)
+    });
+    let stderr_reader = child.stderr.take().map(|mut stderr| {
+        std::thread::spawn(move || -> std::io::Result<Vec<u8>> {
+            let mut output = Vec::new();
+            std::io::Read::read_to_end(
+                &mut std::io::Read::take(&mut stderr, max_output_size),
+                &mut output,
+            )?;
+            // Drain the rest so that the child is not blocked writing to stderr.
+            std::io::copy(&mut stderr, &mut std::io::sink())?;
+            Ok(output)
+        })
+    });
+    let join_stderr = |stderr_reader: Option<std::thread::JoinHandle<_>>| -> Result<Vec<u8>> {
+        match stderr_reader {
+            Some(stderr_reader) => Ok(stderr_reader
+                .join()
+                .map_err(|_| format_err!("Failed to read process stderr."))??),
+            None => Ok(Vec::new()),
+        }
+    };
+
+    let deadline = std::time::Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if reader.is_finished() {
+            // Either the output cap was reached or stdout was closed before exit.
+            let output = reader
+                .join()
+                .map_err(|_| format_err!("Failed to read process stdout."))??;
+            if output.len() as u64 > max_output_size {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format_err!(
+                    "Process output exceeded {} bytes.",
+                    max_output_size
+                ));
+            }
+            let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+            let status = wait_for_exit(child, remaining)?;
+            return Ok(std::process::Output {
+                status,
+                stdout: output,
+                stderr: join_stderr(stderr_reader)?,
+            });
+        }
+        if std::time::Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format_err!("Process timed out after {:?}.", timeout));
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    };
+
+    let output = reader
+        .join()
+        .map_err(|_| format_err!("Failed to read process stdout."))??;
+    if output.len() as u64 > max_output_size {
+        return Err(format_err!(
+            "Process output exceeded {} bytes.",
+            max_output_size
+        ));
+    }
+    Ok(std::process::Output {
+        status,
+        stdout: output,
+        stderr: join_stderr(stderr_reader)?,
+    })
+}
+
+/// Wait for a child process to exit, killing it after `timeout`.
+fn wait_for_exit(
+    mut child: std::process::Child,
+    timeout: std::time::Duration,
+) -> Result<std::process::ExitStatus> {
+    let deadline = std::time::Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        if std::time::Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format_err!("Process timed out after {:?}.", timeout));
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_wait_with_limits() -> Result<()> {
+    let spawn = |script: &str| {
+        std::process::Command::new("sh")
+            .args(["-c", script])
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+    };
+    let timeout = std::time::Duration::from_secs(5);
+    let output = wait_with_limits(spawn("echo ok")?, timeout, 1024)?;
+    assert_eq!(output.stdout, b"ok\n");
+    assert!(output.status.success());
+
+    let output = wait_with_limits(
+        std::process::Command::new("sh")
+            .args(["-c", "echo failed >&2; exit 3"])
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()?,
+        timeout,
+        1024,
+    )?;
+    assert_eq!(output.stderr, b"failed\n");
+    assert_eq!(output.status.code(), Some(3));
+    assert!(wait_with_limits(spawn("yes")?, timeout, 1024).is_err());
+    assert!(wait_with_limits(
+        spawn("sleep 10")?,
+        std::time::Duration::from_millis(50),
+        1024
+    )
+    .is_err());
+    Ok(())
+}
+
+/// Returns the installed ansible-core version given by `ansible --version`, or None if
+/// ansible is not installed.
+pub fn get_ansible_core_version() -> Result<Option<semver::Version>> {
+    let output = match get_ansible_version_output()? {
+        Some(output) => output,
+        None => return Ok(None),
+    };
+    Ok(Some(parse_ansible_core_version(&output).ok_or(
+        format_err!("Failed to parse ansible-core version from ansible --version output."),
+    )?))
+}
+
+/// Returns `ansible --version` output, or None if ansible is not installed.
+///
+/// With VOUCH_ANSIBLE_GALAXY_PYTHON set, runs `python -m ansible.cli.adhoc --version` using
+/// the same interpreter as ansible-galaxy. Limited as ansible-galaxy commands are, see
+/// `run_ansible_galaxy`.
+pub fn get_ansible_version_output() -> Result<Option<String>> {
+    let mut command = match std::env::var_os("VOUCH_ANSIBLE_GALAXY_PYTHON") {
+        Some(python) if !python.is_empty() => {
+            let mut command = std::process::Command::new(python);
+            command.args(["-m", "ansible.cli.adhoc"]);
+            command
+        }
+        _ => std::process::Command::new("ansible"),
+    };
+    let child = command
+        .args(["--version"])
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn();
+    let child = match child {
+        Ok(child) => child,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error).context("Failed to run ansible --version."),
+    };
+    let (timeout, max_output_size) = get_ansible_command_limits();
+    let output = wait_with_limits(child, timeout, max_output_size)
+        .context("Failed to run ansible --version.")?;
+    if !output.status.success() {
+        return Err(format_err!(
+            "ansible --version failed ({}): {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(Some(String::from_utf8_lossy(&output.stdout).to_string()))
+}
+
+/// Parse the `ansible python module location` line of `ansible --version` output.
+fn parse_ansible_module_location(stdout: &str) -> Option<std::path::PathBuf> {
+    stdout.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        if key.trim() != "ansible python module location" {
+            return None;
+        }
+        Some(std::path::PathBuf::from(value.trim()))
+    })
+}
+
+/// Parse the first line of `ansible --version` output.
+/// For example: `ansible [core 2.15.3]` or, before ansible-core, `ansible 2.9.27`.
+fn parse_ansible_core_version(stdout: &str) -> Option<semver::Version> {
+    let version = stdout.lines().next()?.split_whitespace().last()?;
+    super::resolution::parse_version(version.trim_end_matches(']'))
+}
+
+#[test]
+fn test_parse_ansible_core_version() -> Result<()> {
+    assert_eq!(
+        parse_ansible_core_version("ansible [core 2.15.3]\n  config file = None\n"),
+        Some(semver::Version::parse("2.15.3")?)
+    );
+    assert_eq!(
+        parse_ansible_core_version("ansible 2.9.27\n"),
+        Some(semver::Version::parse("2.9.27")?)
+    );
+    assert_eq!(
+        parse_ansible_module_location(
+            "ansible [core 2.15.3]\n  ansible python module location = \
+             /usr/lib/python3/dist-packages/ansible\n"
+        ),
+        Some(std::path::PathBuf::from(
+            "/usr/lib/python3/dist-packages/ansible"
+        ))
+    );
+    Ok(())
 }
 
 /// Order newest version greater than oldest.
@@ -68,39 +580,123 @@ fn order_version_requirement_comparators(
     std::cmp::Ordering::Equal
 }
 
+/// Select the comparator whose version is used when no installed version is available.
+///
+/// Inclusive lower bounds (`=`, `>=`, `~`, `^` and wildcards) are preferred, highest first.
+/// Otherwise inclusive upper bounds (`<=`) are used, lowest first. The first candidate which
+/// satisfies every comparator is selected. If the comparators are unsatisfiable, the first
+/// candidate is selected. Exclusive bounds are never selected.
 fn select_latest_equal_comparator(
-    comparators: &Vec<semver::Comparator>,
+    comparators: &[semver::Comparator],
 ) -> Option<semver::Comparator> {
-    let mut comparators = comparators.clone();
-    comparators.sort_by(|a, b| order_version_requirement_comparators(&a, &b));
-    let mut selected_comparator = None;
-    for comparator in comparators {
-        if comparator.op == semver::Op::Exact
-            || comparator.op == semver::Op::GreaterEq
-            || comparator.op == semver::Op::LessEq
-            || comparator.op == semver::Op::Tilde
-            || comparator.op == semver::Op::Caret
-        {
-            selected_comparator = Some(comparator);
-        }
-    }
-    selected_comparator
+    let order = |a: &semver::Comparator, b: &semver::Comparator| {
+        order_version_requirement_comparators(a, b).then_with(|| a.to_string().cmp(&b.to_string()))
+    };
+
+    let mut lower_bounds: Vec<_> = comparators
+        .iter()
+        .filter(|comparator| {
+            matches!(
+                comparator.op,
+                semver::Op::Exact
+                    | semver::Op::GreaterEq
+                    | semver::Op::Tilde
+                    | semver::Op::Caret
+                    | semver::Op::Wildcard
+            )
+        })
+        .collect();
+    lower_bounds.sort_by(|a, b| order(b, a));
+
+    let mut upper_bounds: Vec<_> = comparators
+        .iter()
+        .filter(|comparator| comparator.op == semver::Op::LessEq)
+        .collect();
+    upper_bounds.sort_by(|a, b| order(a, b));
+
+    let candidates = if lower_bounds.is_empty() {
+        upper_bounds
+    } else {
+        lower_bounds
+    };
+    let is_satisfying = |candidate: &semver::Comparator| {
+        let version = comparator_to_version(candidate);
+        comparators
+            .iter()
+            .all(|comparator| comparator.matches(&version))
+    };
+    candidates
+        .iter()
+        .find(|candidate| is_satisfying(candidate))
+        .or(candidates.first())
+        .map(|comparator| (*comparator).clone())
 }
 
 #[test]
 fn test_select_latest_equal_comparator() -> Result<()> {
-    let comparators = vec![
-        semver::Comparator::parse("=1.3.2")?,
-        semver::Comparator::parse(">=2.3.2")?,
-        semver::Comparator::parse(">3.3.2")?,
+    let select = |comparators: &[&str]| -> Result<Option<String>> {
+        let comparators = comparators
+            .iter()
+            .map(|comparator| semver::Comparator::parse(comparator))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(select_latest_equal_comparator(&comparators).map(|comparator| comparator.to_string()))
+    };
+
+    let cases: Vec<(Vec<&str>, Option<&str>)> = vec![
+        (vec![], None),
+        (vec![">1.0.0"], None),
+        (vec!["<2.0.0"], None),
+        (vec![">1.0.0", "<2.0.0"], None),
+        (vec!["=1.2.0"], Some("=1.2.0")),
+        (vec![">=1.0"], Some(">=1.0")),
+        (vec!["~1.2"], Some("~1.2")),
+        (vec!["^1.2.3"], Some("^1.2.3")),
+        (vec!["1.*"], Some("1.*")),
+        (vec!["<=2.0"], Some("<=2.0")),
+        (vec![">=1.0", "<2.0"], Some(">=1.0")),
+        (vec!["<2.0", ">=1.0"], Some(">=1.0")),
+        (vec![">=1.0", "<=2.0"], Some(">=1.0")),
+        (vec!["<=2.0", ">=1.0"], Some(">=1.0")),
+        (vec![">=1.0", ">=1.5", "<2.0"], Some(">=1.5")),
+        (vec![">=1.5", ">=1.0", "<2.0"], Some(">=1.5")),
+        (vec![">=1.0", ">=2.5", "<2.0"], Some(">=2.5")),
+        (vec![">=1.0", "=1.2.0"], Some("=1.2.0")),
+        (vec!["=1.2.0", ">=1.0"], Some("=1.2.0")),
+        (vec!["^1.2", ">=1.5"], Some(">=1.5")),
+        (vec!["<=3.0", "<=2.0"], Some("<=2.0")),
+        (vec!["<=2.0", "<=3.0"], Some("<=2.0")),
+        (vec![">1.0", "<=2.0"], Some("<=2.0")),
+        (vec![">=2.0", "<1.0"], Some(">=2.0")),
+        (vec!["=1.3.2", ">=2.3.2", ">3.3.2"], Some(">=2.3.2")),
     ];
-    let result = select_latest_equal_comparator(&comparators);
-    let expected = Some(semver::Comparator::parse(">=2.3.2")?);
-    assert_eq!(result, expected);
+    for (comparators, expected) in cases {
+        assert_eq!(
+            select(&comparators)?,
+            expected.map(String::from),
+            "comparators: {:?}",
+            comparators
+        );
+    }
+
+    // Selection does not depend on comparator order.
+    let comparators = [">=1.0", "<=2.0", ">=1.5", "=1.7.0", "^1.6", "<3.0"];
+    let expected = select(&comparators)?;
+    assert_eq!(expected.as_deref(), Some("=1.7.0"));
+    for rotation in 0..comparators.len() {
+        let mut rotated = comparators.to_vec();
+        rotated.rotate_left(rotation);
+        assert_eq!(select(&rotated)?, expected);
+        rotated.reverse();
+        assert_eq!(select(&rotated)?, expected);
+    }
     Ok(())
 }
 
-fn normalize_version(version: &str) -> Result<String> {
+/// Pad version with missing minor and patch numbers. For example: `1.2` becomes `1.2.0`.
+///
+/// Some private registries publish two component versions such as `3.0`, so registry and
+/// installed versions are normalized before semver parsing.
+pub fn normalize_version(version: &str) -> Result<String> {
     let mut split = version.split("-");
     let prefix = split
         .next()
@@ -113,7 +709,6 @@ fn normalize_version(version: &str) -> Result<String> {
         prefix += ".0.0";
     } else if count_periods == 1 {
         prefix += ".0";
-    } else {
     }
 
     for part in split {
@@ -135,119 +730,774 @@ fn test_normalize_version() -> Result<()> {
     Ok(())
 }
 
-/// Convert a version comparator to a plain version by stripping the operator prefix.
-fn comparator_to_version(comparator: &semver::Comparator) -> Result<semver::Version> {
-    let comparator_str = comparator.to_string();
-
-    let op_str = match comparator.op {
-        semver::Op::Exact => "=",
-        semver::Op::Greater => ">",
-        semver::Op::GreaterEq => ">=",
-        semver::Op::Less => "<",
-        semver::Op::LessEq => "<=",
-        semver::Op::Tilde => "~",
-        semver::Op::Caret => "^",
-        semver::Op::Wildcard => "*",
-        _ => "",
-    };
-
-    let version_str = comparator_str.trim_start_matches(&op_str);
-    let version = normalize_version(version_str)?;
-    let version = semver::Version::parse(&version);
-    Ok(version?)
+/// Returns the lowest version described by a comparator. Missing minor and patch numbers
+/// are zero.
+fn comparator_to_version(comparator: &semver::Comparator) -> semver::Version {
+    semver::Version {
+        major: comparator.major,
+        minor: comparator.minor.unwrap_or(0),
+        patch: comparator.patch.unwrap_or(0),
+        pre: comparator.pre.clone(),
+        build: semver::BuildMetadata::EMPTY,
+    }
 }
 
 /// Given version requirement and installed global package version,
 /// return the version which is probabilistically most relevant.
 fn package_specific_version_from_requirement(
-    version_requirement: &semver::VersionReq,
+    version_requirement: &super::resolution::VersionRequirement,
     global_version: Option<semver::Version>,
 ) -> std::result::Result<semver::Version, vouch_lib::extension::common::VersionError> {
     if let Some(global_version) = global_version {
-        if version_requirement.matches(&global_version) {
+        if version_requirement.matches(&global_version, false) {
             return Ok(global_version);
         }
     } else {
-        let comparator = select_latest_equal_comparator(&version_requirement.comparators)
-            .ok_or(vouch_lib::extension::common::VersionError::from_missing_version())?;
-        let version = comparator_to_version(&comparator).or(Err(
-            vouch_lib::extension::common::VersionError::from_missing_version(),
-        ))?;
-        return Ok(version);
+        let comparator =
+            select_latest_equal_comparator(&version_requirement.requirement.comparators)
+                .ok_or(vouch_lib::extension::common::VersionError::from_missing_version())?;
+        let version = comparator_to_version(&comparator);
+        if !version_requirement.excluded_versions.contains(&version) {
+            return Ok(version);
+        }
     }
     Err(vouch_lib::extension::common::VersionError::from_missing_version())
 }
 
+/// Returns the version of a package which best satisfies a requirement, typically by querying
+/// registries. Returns None if no version could be found.
+pub type VersionResolver<'a> =
+    &'a dyn Fn(&str, &super::resolution::VersionRequirement) -> Option<String>;
+
+/// How collection version requirements are turned into dependency versions.
+#[derive(Clone, Copy)]
+pub struct VersionContext<'a> {
+    /// Installed global collection versions, by name.
+    pub global_dependencies: &'a super::installed::InstalledVersions<'a>,
+
+    pub resolver: Option<VersionResolver<'a>>,
+
+    /// Prefer a matching installed global version over the resolver's version.
+    pub prefer_installed: bool,
+
+    /// Installed standalone role versions, by role name. Used for roles without a version.
+    pub installed_roles: Option<&'a super::installed::InstalledVersions<'a>>,
+}
+
+impl<'a> VersionContext<'a> {
+    /// Returns context which uses installed versions only.
+    pub fn installed(global_dependencies: &'a super::installed::InstalledVersions<'a>) -> Self {
+        Self {
+            global_dependencies,
+            resolver: None,
+            prefer_installed: true,
+            installed_roles: None,
+        }
+    }
+}
+
+/// Returns dependency version given a collection version requirement.
+///
+/// Uses a matching installed global version or the resolver's version, in the order given by
+/// the context. Otherwise, uses the version named by the requirement. Installed versions are
+/// not looked up for exact requirements.
+fn get_collection_dependency_version(
+    package_name: &str,
+    version_requirement: &super::resolution::VersionRequirement,
+    context: &VersionContext,
+) -> Result<std::result::Result<String, vouch_lib::extension::common::VersionError>> {
+    let global_version = if version_requirement.exact_version().is_some() {
+        None
+    } else {
+        context
+            .global_dependencies
+            .get(package_name)?
+            .and_then(super::resolution::parse_version)
+    };
+    let is_global_match = global_version
+        .as_ref()
+        .is_some_and(|version| version_requirement.matches(version, false));
+    if !is_global_match || !context.prefer_installed {
+        if let Some(version) = context
+            .resolver
+            .and_then(|resolve| resolve(package_name, version_requirement))
+        {
+            return Ok(Ok(version));
+        }
+    }
+    let version = package_specific_version_from_requirement(version_requirement, global_version);
+    Ok(version.map(|v| v.to_string()))
+}
+
+#[test]
+fn test_get_collection_dependency_version() -> Result<()> {
+    let parse_requirement = super::resolution::VersionRequirement::parse;
+    let global_dependencies = super::installed::InstalledVersions::from(maplit::hashmap! {
+        "community.general".to_string() => "1.2.0".to_string(),
+        "community.docker".to_string() => "3.0".to_string(),
+    });
+    let resolver: VersionResolver = &|_, requirement| {
+        assert!(requirement.is_any());
+        Some("3.0.0".to_string())
+    };
+    assert_eq!(
+        get_collection_dependency_version(
+            "community.general",
+            &parse_requirement("")?,
+            &VersionContext::installed(&global_dependencies)
+        )?,
+        Ok("1.2.0".to_string())
+    );
+    assert_eq!(
+        get_collection_dependency_version(
+            "ansible.posix",
+            &parse_requirement("*")?,
+            &VersionContext {
+                resolver: Some(resolver),
+                ..VersionContext::installed(&global_dependencies)
+            }
+        )?,
+        Ok("3.0.0".to_string())
+    );
+    assert_eq!(
+        get_collection_dependency_version(
+            "community.general",
+            &parse_requirement("*")?,
+            &VersionContext {
+                resolver: Some(resolver),
+                prefer_installed: false,
+                ..VersionContext::installed(&global_dependencies)
+            }
+        )?,
+        Ok("3.0.0".to_string())
+    );
+    assert_eq!(
+        get_collection_dependency_version(
+            "ansible.posix",
+            &parse_requirement("")?,
+            &VersionContext::installed(&global_dependencies)
+        )?,
+        Err(vouch_lib::extension::common::VersionError::from_missing_version())
+    );
+    assert_eq!(
+        get_collection_dependency_version(
+            "community.docker",
+            &parse_requirement(">=3.0.0")?,
+            &VersionContext::installed(&global_dependencies)
+        )?,
+        Ok("3.0.0".to_string())
+    );
+
+    // Exact requirements do not need installed versions.
+    let global_dependencies =
+        super::installed::InstalledVersions::new(|| Err(format_err!("Not loaded lazily.")));
+    assert_eq!(
+        get_collection_dependency_version(
+            "community.general",
+            &parse_requirement("==2.1.0")?,
+            &VersionContext::installed(&global_dependencies)
+        )?,
+        Ok("2.1.0".to_string())
+    );
+    assert!(!global_dependencies.is_loaded());
+    Ok(())
+}
+
+/// Dependencies parsed from a dependency file.
+#[derive(Debug, Default)]
+pub struct FileDependencies {
+    pub dependencies: std::collections::HashSet<vouch_lib::extension::Dependency>,
+
+    /// Entries which were skipped.
+    pub diagnostics: Vec<super::diagnostic::DependencyDiagnostic>,
+}
+
+impl FileDependencies {
+    fn push_diagnostic(&mut self, file_path: &std::path::Path, entry: String, message: String) {
+        self.diagnostics
+            .push(super::diagnostic::DependencyDiagnostic {
+                path: file_path.to_path_buf(),
+                entry,
+                message,
+            });
+    }
+}
+
+/// Parse dependencies from a MANIFEST.json or galaxy.yml `dependencies` mapping.
+fn get_mapping_dependencies(
+    file_path: &std::path::Path,
+    raw_dependencies: &serde_json::Map<String, serde_json::Value>,
+    context: &VersionContext,
+) -> Result<FileDependencies> {
+    let mut file_dependencies = FileDependencies::default();
+    for (package_name, version_requirement) in raw_dependencies.iter() {
+        match get_mapping_dependency(package_name, version_requirement, context)? {
+            Ok(dependency) => {
+                file_dependencies.dependencies.insert(dependency);
+            }
+            Err(message) => {
+                file_dependencies.push_diagnostic(file_path, package_name.clone(), message)
+            }
+        }
+    }
+    Ok(file_dependencies)
+}
+
+/// Parse a single `dependencies` mapping entry. Returns Ok(Err) with a message if the entry
+/// is invalid.
+fn get_mapping_dependency(
+    package_name: &str,
+    version_requirement: &serde_json::Value,
+    context: &VersionContext,
+) -> Result<std::result::Result<vouch_lib::extension::Dependency, String>> {
+    let version_requirement = match version_requirement.as_str() {
+        Some(version_requirement) => version_requirement,
+        None => {
+            return Ok(Err(
+                "Failed to parse version requirement as string.".to_string()
+            ))
+        }
+    };
+
+    // Git sources are versioned by tag or branch rather than semver requirement.
+    if super::git::is_git_source(package_name) {
+        return Ok(Ok(vouch_lib::extension::Dependency {
+            name: package_name.to_string(),
+            version: get_git_dependency_version(version_requirement),
+        }));
+    }
+
+    let version_requirement =
+        match super::resolution::VersionRequirement::parse(version_requirement) {
+            Ok(version_requirement) => version_requirement,
+            Err(error) => return Ok(Err(format!("{:#}", error))),
+        };
+    Ok(Ok(vouch_lib::extension::Dependency {
+        name: package_name.to_string(),
+        version: get_collection_dependency_version(package_name, &version_requirement, context)?,
+    }))
+}
+
+#[test]
+fn test_get_mapping_dependencies() -> Result<()> {
+    let global_dependencies = super::installed::InstalledVersions::from(maplit::hashmap! {});
+    let raw_dependencies = serde_json::json!({
+        "community.general": ">=3.0.0",
+        "community.docker": 3,
+        "community.crypto": "!=latest",
+    });
+    let file_dependencies = get_mapping_dependencies(
+        std::path::Path::new("galaxy.yml"),
+        raw_dependencies.as_object().unwrap(),
+        &VersionContext::installed(&global_dependencies),
+    )?;
+    assert_eq!(
+        file_dependencies.dependencies,
+        maplit::hashset! {vouch_lib::extension::Dependency {
+            name: "community.general".to_string(),
+            version: Ok("3.0.0".to_string()),
+        }}
+    );
+    let entries: Vec<&str> = file_dependencies
+        .diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.entry.as_str())
+        .collect();
+    assert_eq!(entries, vec!["community.crypto", "community.docker"]);
+    Ok(())
+}
+
 /// Parse dependencies from project MANIFEST.json file.
 pub fn get_manifest_dependencies(
     file_path: &std::path::PathBuf,
-    global_dependencies: &std::collections::HashMap<String, String>,
-) -> Result<std::collections::HashSet<vouch_lib::extension::Dependency>> {
+    context: &VersionContext,
+) -> Result<FileDependencies> {
     let file = std::fs::File::open(file_path)?;
     let reader = std::io::BufReader::new(file);
     let package_meta: serde_json::Value = serde_json::from_reader(reader)
-        .context(format!("Failed to parse json: {}", file_path.display()))?;
+        .map_err(|error| AnsibleExtensionError::from_manifest_error(file_path, &error))?;
     let raw_dependencies = &package_meta["collection_info"]["dependencies"]
         .as_object()
-        .ok_or(format_err!(
-            "Failed to parse dependencies section as object."
-        ))?;
-
-    let mut dependencies = std::collections::HashSet::<vouch_lib::extension::Dependency>::new();
-    for (package_name, version_requirement) in raw_dependencies.iter() {
-        let version_requirement = version_requirement.as_str().ok_or(format_err!(
-            "Failed to parse version requirement as string."
-        ))?;
-
-        let version_requirement = semver::VersionReq::parse(version_requirement)?;
-        let global_version = global_dependencies
-            .get(package_name.as_str())
-            .and_then(|f| semver::Version::parse(f.as_str()).ok());
-        let version =
-            package_specific_version_from_requirement(&version_requirement, global_version);
-
-        dependencies.insert(vouch_lib::extension::Dependency {
-            name: package_name.clone(),
-            version: version.map(|v| v.to_string()),
-        });
-    }
+        .ok_or_else(|| ParseManifest {
+            path: file_path.clone(),
+            location: None,
+            message: "Failed to parse dependencies section as object.".to_string(),
+        })?;
 
-    Ok(dependencies)
+    get_mapping_dependencies(file_path, raw_dependencies, context)
 }
 
 /// Parse dependencies from project galaxy.yml file.
+///
+/// A list shaped dependencies section is accepted with a warning.
 pub fn get_galaxy_yml_dependencies(
     file_path: &std::path::PathBuf,
-    global_dependencies: &std::collections::HashMap<String, String>,
-) -> Result<std::collections::HashSet<vouch_lib::extension::Dependency>> {
+    context: &VersionContext,
+) -> Result<FileDependencies> {
     let file = std::fs::File::open(file_path)?;
     let reader = std::io::BufReader::new(file);
     let package_meta: serde_json::Value = serde_yaml::from_reader(reader)
-        .context(format!("Failed to parse json: {}", file_path.display()))?;
-    let raw_dependencies = &package_meta["dependencies"].as_object().ok_or(format_err!(
-        "Failed to parse dependencies section as object."
-    ))?;
+        .map_err(|error| AnsibleExtensionError::from_galaxy_yml_error(file_path, &error))?;
+    let raw_dependencies = &package_meta["dependencies"];
+    let (mapping, invalid_entries) =
+        get_dependencies_mapping(raw_dependencies).ok_or_else(|| ParseGalaxyYml {
+            path: file_path.clone(),
+            location: None,
+            message: "Failed to parse dependencies section as object.".to_string(),
+        })?;
 
-    let mut dependencies = std::collections::HashSet::<vouch_lib::extension::Dependency>::new();
-    for (package_name, version_requirement) in raw_dependencies.iter() {
-        let version_requirement = version_requirement.as_str().ok_or(format_err!(
-            "Failed to parse version requirement as string."
-        ))?;
-
-        let version_requirement = semver::VersionReq::parse(version_requirement)?;
-        let global_version = global_dependencies
-            .get(package_name.as_str())
-            .and_then(|f| semver::Version::parse(f.as_str()).ok());
-        let version =
-            package_specific_version_from_requirement(&version_requirement, global_version);
-
-        dependencies.insert(vouch_lib::extension::Dependency {
-            name: package_name.clone(),
-            version: version.map(|v| v.to_string()),
-        });
+    let mut file_dependencies = get_mapping_dependencies(file_path, &mapping, context)?;
+    if raw_dependencies.is_array() {
+        file_dependencies.push_diagnostic(
+            file_path,
+            "dependencies".to_string(),
+            "Dependencies section is a list, expected a mapping of collection names to \
+            version requirements."
+                .to_string(),
+        );
     }
+    for entry in invalid_entries {
+        file_dependencies.push_diagnostic(
+            file_path,
+            entry.to_string(),
+            "Failed to parse dependency list entry.".to_string(),
+        );
+    }
+    Ok(file_dependencies)
+}
 
-    Ok(dependencies)
+#[test]
+fn test_get_galaxy_yml_dependencies_list() -> Result<()> {
+    let directory = tempfile::tempdir()?;
+    let file_path = directory.path().join("galaxy.yml");
+    std::fs::write(
+        &file_path,
+        "dependencies:\n  - name: community.docker\n    version: 3.0.0\n",
+    )?;
+    let global_dependencies =
+        super::installed::InstalledVersions::from(std::collections::HashMap::new());
+    let file_dependencies =
+        get_galaxy_yml_dependencies(&file_path, &VersionContext::installed(&global_dependencies))?;
+    assert_eq!(
+        file_dependencies
+            .dependencies
+            .iter()
+            .map(|dependency| dependency.name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["community.docker"]
+    );
+    assert_eq!(
+        file_dependencies
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.entry.as_str())
+            .collect::<Vec<_>>(),
+        vec!["dependencies"]
+    );
+    Ok(())
+}
+
+/// Returns a galaxy.yml `dependencies` section as a mapping, with any list entries which
+/// could not be read.
+///
+/// Hand-written files sometimes give the section as a list of single-entry `name: version`
+/// mappings, `name` and `version` mappings, or plain names. Entries without a version require
+/// any version. Returns None for other shapes.
+fn get_dependencies_mapping(
+    dependencies: &serde_json::Value,
+) -> Option<(
+    serde_json::Map<String, serde_json::Value>,
+    Vec<&serde_json::Value>,
+)> {
+    let entries = match dependencies {
+        serde_json::Value::Object(dependencies) => return Some((dependencies.clone(), Vec::new())),
+        serde_json::Value::Array(entries) => entries,
+        _ => return None,
+    };
+    let mut mapping = serde_json::Map::new();
+    let mut invalid_entries = Vec::new();
+    for entry in entries {
+        match entry {
+            serde_json::Value::String(name) => {
+                mapping.insert(name.clone(), serde_json::Value::from("*"));
+            }
+            serde_json::Value::Object(fields) if fields.contains_key("name") => {
+                match (&fields["name"], fields.get("version")) {
+                    (serde_json::Value::String(name), version)
+                        if fields.keys().all(|key| key == "name" || key == "version") =>
+                    {
+                        let version = version.cloned().unwrap_or_else(|| "*".into());
+                        mapping.insert(name.clone(), version);
+                    }
+                    _ => invalid_entries.push(entry),
+                }
+            }
+            serde_json::Value::Object(entry) if entry.len() == 1 => mapping.extend(entry.clone()),
+            _ => invalid_entries.push(entry),
+        }
+    }
+    Some((mapping, invalid_entries))
+}
+
+#[test]
+fn test_get_dependencies_mapping() -> Result<()> {
+    let dependencies: serde_json::Value = serde_yaml::from_str(
+        r#"
+- community.general: ">=3.0.0"
+- ansible.posix
+- name: community.docker
+  version: 3.0.0
+- name: community.crypto
+- name: community.mysql
+  src: https://example.com/
+- [community.aws]
+"#,
+    )?;
+    let (mapping, invalid_entries) = get_dependencies_mapping(&dependencies).unwrap();
+    assert_eq!(
+        serde_json::Value::Object(mapping),
+        serde_json::json!({
+            "community.general": ">=3.0.0",
+            "ansible.posix": "*",
+            "community.docker": "3.0.0",
+            "community.crypto": "*",
+        })
+    );
+    assert_eq!(invalid_entries, vec![&dependencies[4], &dependencies[5]]);
+
+    let dependencies = serde_json::json!({"community.general": ">=3.0.0"});
+    assert_eq!(
+        get_dependencies_mapping(&dependencies),
+        Some((dependencies.as_object().unwrap().clone(), Vec::new()))
+    );
+    assert_eq!(
+        get_dependencies_mapping(&serde_json::json!("invalid")),
+        None
+    );
+    Ok(())
+}
+
+/// Parse dependencies from project requirements.yml file.
+///
+/// Handles both the `collections`/`roles` mapping format and the legacy top-level roles list.
+/// Entries may be plain names or mappings with `name`, `version`, `type`, `src` and `scm` keys.
+pub fn get_requirements_yml_dependencies(
+    file_path: &std::path::PathBuf,
+    context: &VersionContext,
+) -> Result<FileDependencies> {
+    let file = std::fs::File::open(file_path)?;
+    let reader = std::io::BufReader::new(file);
+    let requirements: serde_json::Value = serde_yaml::from_reader(reader)
+        .map_err(|error| AnsibleExtensionError::from_requirements_yml_error(file_path, &error))?;
+
+    let empty = Vec::new();
+    let (collections, roles) = match &requirements {
+        serde_json::Value::Array(roles) => (&empty, roles),
+        _ => (
+            requirements["collections"].as_array().unwrap_or(&empty),
+            requirements["roles"].as_array().unwrap_or(&empty),
+        ),
+    };
+
+    let mut file_dependencies = FileDependencies::default();
+    let entries = collections
+        .iter()
+        .map(|entry| (entry, true))
+        .chain(roles.iter().map(|entry| (entry, false)));
+    for (entry, is_collection) in entries {
+        match get_requirement_dependency(entry, is_collection, context)? {
+            Ok(Some(dependency)) => {
+                file_dependencies.dependencies.insert(dependency);
+            }
+            Ok(None) => {}
+            Err(message) => {
+                let entry = entry
+                    .as_str()
+                    .or_else(|| entry["name"].as_str())
+                    .map(|name| name.to_string())
+                    .unwrap_or_else(|| entry.to_string());
+                file_dependencies.push_diagnostic(file_path, entry, message)
+            }
+        }
+    }
+    Ok(file_dependencies)
+}
+
+/// Parse a single requirements.yml entry. Returns None for local file and directory entries,
+/// or Ok(Err) with a message if the entry is invalid.
+fn get_requirement_dependency(
+    entry: &serde_json::Value,
+    is_collection: bool,
+    context: &VersionContext,
+) -> Result<std::result::Result<Option<vouch_lib::extension::Dependency>, String>> {
+    let (name, version, source_type) = match entry {
+        serde_json::Value::String(name) => (name.clone(), None, None),
+        serde_json::Value::Object(_) => {
+            let name = match entry["src"]
+                .as_str()
+                .filter(|_| !is_collection)
+                .or_else(|| entry["name"].as_str())
+            {
+                Some(name) => name,
+                None => return Ok(Err("Failed to parse requirement name.".to_string())),
+            };
+            let version = match &entry["version"] {
+                serde_json::Value::String(version) => Some(version.clone()),
+                serde_json::Value::Number(version) => Some(version.to_string()),
+                _ => None,
+            };
+            let source_type = entry["type"].as_str().or_else(|| entry["scm"].as_str());
+            (
+                name.to_string(),
+                version,
+                source_type.map(|t| t.to_string()),
+            )
+        }
+        _ => return Ok(Err("Failed to parse requirement.".to_string())),
+    };
+
+    let dependency = match source_type.as_deref() {
+        Some("file") | Some("dir") | Some("subdirs") => return Ok(Ok(None)),
+        Some("git") if name.trim_start_matches("git+").starts_with('-') => {
+            return Ok(Err(format!("Invalid git repository: {}", name)))
+        }
+        Some("git") => {
+            let name = if super::git::is_git_source(&name) {
+                name
+            } else {
+                format!("git+{}", name)
+            };
+            vouch_lib::extension::Dependency {
+                name,
+                version: get_git_dependency_version(version.as_deref().unwrap_or("")),
+            }
+        }
+        _ if super::git::is_git_source(&name) => vouch_lib::extension::Dependency {
+            name,
+            version: get_git_dependency_version(version.as_deref().unwrap_or("")),
+        },
+        _ if super::oci::is_oci_source(&name) => vouch_lib::extension::Dependency {
+            name,
+            version: version
+                .ok_or(vouch_lib::extension::common::VersionError::from_missing_version()),
+        },
+        // Tarball versions are recovered from the artifact MANIFEST.json during review.
+        _ if super::tarball::is_tarball_url(&name) => vouch_lib::extension::Dependency {
+            name,
+            version: Err(vouch_lib::extension::common::VersionError::from_missing_version()),
+        },
+        _ if is_collection => {
+            let version_requirement = match super::resolution::VersionRequirement::parse(
+                version.as_deref().unwrap_or("*"),
+            ) {
+                Ok(version_requirement) => version_requirement,
+                Err(error) => return Ok(Err(format!("{:#}", error))),
+            };
+            let version = get_collection_dependency_version(&name, &version_requirement, context)?;
+            vouch_lib::extension::Dependency { name, version }
+        }
+        _ => {
+            let version = match (version, context.installed_roles) {
+                (None, Some(installed_roles)) => installed_roles
+                    .get(&name)?
+                    .map(|version| version.to_string()),
+                (version, _) => version,
+            };
+            vouch_lib::extension::Dependency {
+                name,
+                version: version
+                    .ok_or(vouch_lib::extension::common::VersionError::from_missing_version()),
+            }
+        }
+    };
+    Ok(Ok(Some(dependency)))
+}
+
+#[test]
+fn test_get_requirement_dependency() -> Result<()> {
+    let global_dependencies = super::installed::InstalledVersions::from(maplit::hashmap! {});
+    let entries: serde_json::Value = serde_yaml::from_str(
+        r#"
+- name: community.general
+  version: ">=3.0.0"
+- name: https://github.com/org/repo.git
+  type: git
+  version: v1.0.0
+- https://example.com/org-collection-1.0.0.tar.gz
+- name: ./local
+  type: dir
+- version: 1.0.0
+- name: --upload-pack=touch /tmp/x
+  type: git
+"#,
+    )?;
+    let dependencies = entries
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| {
+            get_requirement_dependency(
+                entry,
+                true,
+                &VersionContext::installed(&global_dependencies),
+            )
+        })
+        .collect::<Result<Vec<_>>>()?;
+    assert_eq!(
+        dependencies,
+        vec![
+            Ok(Some(vouch_lib::extension::Dependency {
+                name: "community.general".to_string(),
+                version: Ok("3.0.0".to_string()),
+            })),
+            Ok(Some(vouch_lib::extension::Dependency {
+                name: "https://github.com/org/repo.git".to_string(),
+                version: Ok("v1.0.0".to_string()),
+            })),
+            Ok(Some(vouch_lib::extension::Dependency {
+                name: "https://example.com/org-collection-1.0.0.tar.gz".to_string(),
+                version: Err(vouch_lib::extension::common::VersionError::from_missing_version()),
+            })),
+            Ok(None),
+            Err("Failed to parse requirement name.".to_string()),
+            Err("Invalid git repository: --upload-pack=touch /tmp/x".to_string()),
+        ]
+    );
+
+    let installed_roles = super::installed::InstalledVersions::from(maplit::hashmap! {
+        "geerlingguy.docker".to_string() => "6.1.0".to_string(),
+    });
+    assert_eq!(
+        get_requirement_dependency(
+            &serde_json::json!({"name": "geerlingguy.docker"}),
+            false,
+            &VersionContext {
+                installed_roles: Some(&installed_roles),
+                ..VersionContext::installed(&global_dependencies)
+            },
+        )?,
+        Ok(Some(vouch_lib::extension::Dependency {
+            name: "geerlingguy.docker".to_string(),
+            version: Ok("6.1.0".to_string()),
+        }))
+    );
+    Ok(())
+}
+
+/// Returns collection version requirements from a MANIFEST.json or galaxy.yml
+/// `dependencies` mapping. Git sources are skipped.
+fn get_requirements_mapping(dependencies: &serde_json::Value) -> Vec<(String, String)> {
+    dependencies
+        .as_object()
+        .map(|dependencies| {
+            dependencies
+                .iter()
+                .filter(|(name, _)| !super::git::is_git_source(name))
+                .map(|(name, requirement)| {
+                    (
+                        name.clone(),
+                        requirement.as_str().unwrap_or("*").to_string(),
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns collection version requirements, as written, declared by a MANIFEST.json file.
+pub fn get_manifest_requirements(file_path: &std::path::Path) -> Result<Vec<(String, String)>> {
+    let file = std::fs::File::open(file_path)?;
+    let package_meta: serde_json::Value = serde_json::from_reader(std::io::BufReader::new(file))
+        .map_err(|error| AnsibleExtensionError::from_manifest_error(file_path, &error))?;
+    Ok(get_requirements_mapping(
+        &package_meta["collection_info"]["dependencies"],
+    ))
+}
+
+/// Returns collection version requirements, as written, declared by a galaxy.yml file.
+pub fn get_galaxy_yml_requirements(file_path: &std::path::Path) -> Result<Vec<(String, String)>> {
+    let file = std::fs::File::open(file_path)?;
+    let package_meta: serde_json::Value = serde_yaml::from_reader(std::io::BufReader::new(file))
+        .map_err(|error| AnsibleExtensionError::from_galaxy_yml_error(file_path, &error))?;
+    let dependencies = get_dependencies_mapping(&package_meta["dependencies"])
+        .map(|(mapping, _)| serde_json::Value::Object(mapping))
+        .unwrap_or_default();
+    Ok(get_requirements_mapping(&dependencies))
+}
+
+/// Returns collection version requirements, as written, declared by a requirements.yml file.
+/// Roles and git, OCI, tarball and local sources are skipped.
+pub fn get_requirements_yml_requirements(
+    file_path: &std::path::Path,
+) -> Result<Vec<(String, String)>> {
+    let file = std::fs::File::open(file_path)?;
+    let requirements: serde_json::Value = serde_yaml::from_reader(std::io::BufReader::new(file))
+        .map_err(|error| AnsibleExtensionError::from_requirements_yml_error(file_path, &error))?;
+
+    let mut collection_requirements = Vec::new();
+    for entry in requirements["collections"].as_array().into_iter().flatten() {
+        let (name, version) = match entry {
+            serde_json::Value::String(name) => (name.as_str(), "*".to_string()),
+            serde_json::Value::Object(_)
+                if entry["type"].as_str().unwrap_or("galaxy") == "galaxy" =>
+            {
+                let name = match entry["name"].as_str() {
+                    Some(name) => name,
+                    None => continue,
+                };
+                let version = match &entry["version"] {
+                    serde_json::Value::String(version) => version.clone(),
+                    serde_json::Value::Number(version) => version.to_string(),
+                    _ => "*".to_string(),
+                };
+                (name, version)
+            }
+            _ => continue,
+        };
+        if super::git::is_git_source(name)
+            || super::oci::is_oci_source(name)
+            || super::tarball::is_tarball_url(name)
+        {
+            continue;
+        }
+        collection_requirements.push((name.to_string(), version));
+    }
+    Ok(collection_requirements)
+}
+
+/// Returns the names of the standalone roles declared by a requirements.yml file.
+pub fn get_requirements_yml_role_names(
+    file_path: &std::path::Path,
+) -> Result<std::collections::BTreeSet<String>> {
+    let file = std::fs::File::open(file_path)?;
+    let requirements: serde_json::Value = serde_yaml::from_reader(std::io::BufReader::new(file))
+        .map_err(|error| AnsibleExtensionError::from_requirements_yml_error(file_path, &error))?;
+
+    let roles = match &requirements {
+        serde_json::Value::Array(roles) => Some(roles),
+        _ => requirements["roles"].as_array(),
+    };
+    Ok(roles
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            entry
+                .as_str()
+                .or_else(|| entry["src"].as_str())
+                .or_else(|| entry["name"].as_str())
+        })
+        .map(|name| name.to_string())
+        .collect())
+}
+
+/// Returns git dependency version requirement, a missing version if unconstrained.
+fn get_git_dependency_version(
+    version_requirement: &str,
+) -> std::result::Result<String, vouch_lib::extension::common::VersionError> {
+    match version_requirement.trim() {
+        "" | "*" => Err(vouch_lib::extension::common::VersionError::from_missing_version()),
+        version_requirement => Ok(version_requirement.to_string()),
+    }
 }
 
 pub fn get_registry_host_name() -> String {
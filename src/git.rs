@@ -0,0 +1,287 @@
+use super::error;
+use super::registry;
+use anyhow::{format_err, Context, Result};
+
+/// Git hosted package source, as given in a requirements entry.
+///
+/// Accepted forms include `git+https://github.com/org/repo.git,v1.0.0`,
+/// `https://github.com/org/repo.git` and `git@github.com:org/repo.git`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitSource {
+    /// Repository URL passed to git.
+    pub repository_url: String,
+
+    /// Repository web URL. For example: https://github.com/org/repo
+    pub web_url: url::Url,
+
+    /// Version given after the comma in the source string.
+    pub version: Option<String>,
+}
+
+/// Returns true if the package name refers to a git repository.
+pub fn is_git_source(package_name: &str) -> bool {
+    let (repository_url, _) = split_version(package_name.trim_start_matches("git+"));
+    package_name.starts_with("git+")
+        || package_name.starts_with("git@")
+        || (repository_url.contains("://") && repository_url.ends_with(".git"))
+}
+
+/// Split `<repository>,<version>` source string.
+fn split_version(source: &str) -> (&str, Option<&str>) {
+    match source.rfind(',') {
+        Some(index) => (&source[..index], Some(&source[index + 1..])),
+        None => (source, None),
+    }
+}
+
+impl GitSource {
+    pub fn parse(package_name: &str) -> Result<Self> {
+        let (repository_url, version) = split_version(package_name.trim_start_matches("git+"));
+        // Would otherwise be parsed as a git option.
+        if repository_url.starts_with('-') {
+            return Err(format_err!("Invalid git repository: {}", repository_url));
+        }
+
+        // Convert scp-like and ssh URLs into web URLs.
+        let web_url = if let Some(scp_path) = repository_url.strip_prefix("git@") {
+            let (host, path) = scp_path.split_once(':').ok_or(format_err!(
+                "Failed to parse git repository: {}",
+                repository_url
+            ))?;
+            format!("https://{}/{}", host, path)
+        } else {
+            let url = url::Url::parse(repository_url).context(format!(
+                "Failed to parse git repository: {}",
+                repository_url
+            ))?;
+            let host = url.host_str().ok_or(format_err!(
+                "Git repository URL is missing a host: {}",
+                repository_url
+            ))?;
+            format!("https://{}{}", host, url.path())
+        };
+        let web_url = url::Url::parse(web_url.trim_end_matches('/').trim_end_matches(".git"))?;
+
+        Ok(Self {
+            repository_url: repository_url.to_string(),
+            web_url,
+            version: version
+                .filter(|version| !version.is_empty())
+                .map(|version| version.to_string()),
+        })
+    }
+
+    /// Returns repository archive URL for the given git reference.
+    ///
+    /// The URL is checked by `registry::check_remote_url`, as the repository may be taken from
+    /// registry metadata.
+    pub fn archive_url(&self, reference: &str) -> Result<url::Url> {
+        let host = self.web_url.host_str().unwrap_or("");
+        let base = self.web_url.as_str();
+        let url = if host == "github.com" {
+            format!("{}/archive/{}.tar.gz", base, reference)
+        } else if host.contains("gitlab") {
+            let repository_name = self
+                .web_url
+                .path_segments()
+                .and_then(|mut segments| segments.next_back())
+                .unwrap_or("");
+            format!(
+                "{}/-/archive/{}/{}-{}.tar.gz",
+                base, reference, repository_name, reference
+            )
+        } else if host == "bitbucket.org" {
+            format!("{}/get/{}.tar.gz", base, reference)
+        } else {
+            return Err(format_err!(
+                "Unsupported git host for archive URLs: {}",
+                host
+            ));
+        };
+        let url = url::Url::parse(&url)?;
+        registry::check_remote_url(&url)?;
+        Ok(url)
+    }
+
+    /// Returns repository web URL for the given git reference.
+    pub fn human_url(&self, reference: &str) -> Result<url::Url> {
+        let host = self.web_url.host_str().unwrap_or("");
+        let base = self.web_url.as_str();
+        let url = if host.contains("gitlab") {
+            format!("{}/-/tree/{}", base, reference)
+        } else if host == "bitbucket.org" {
+            format!("{}/src/{}", base, reference)
+        } else {
+            format!("{}/tree/{}", base, reference)
+        };
+        Ok(url::Url::parse(&url)?)
+    }
+}
+
+/// Git hosted collection release.
+#[derive(Debug, Clone)]
+pub struct GitRelease {
+    pub host_name: String,
+
+    /// Selected git reference, usually a tag.
+    pub version: String,
+
+    pub human_url: url::Url,
+    pub artifact_url: url::Url,
+}
+
+/// Resolve a git hosted package into a release pointing at the matching tag archive.
+///
+/// The version may be a tag, a branch, or a version requirement matched against tags.
+/// Without a version, the highest version tag is selected.
+pub fn get_git_release(
+    package_name: &str,
+    package_version: &Option<&str>,
+    include_prereleases: bool,
+) -> Result<GitRelease> {
+    let source = GitSource::parse(package_name)?;
+    let requested_version = package_version
+        .map(|version| version.to_string())
+        .or_else(|| source.version.clone());
+
+    let tags = get_remote_tags(&source.repository_url)?;
+    let reference = select_reference(&tags, requested_version.as_deref(), include_prereleases)?;
+
+    Ok(GitRelease {
+        host_name: source.web_url.host_str().unwrap_or("").to_string(),
+        human_url: source.human_url(&reference)?,
+        artifact_url: source.archive_url(&reference)?,
+        version: reference,
+    })
+}
+
+/// Returns repository tag names using `git ls-remote`.
+fn get_remote_tags(repository_url: &str) -> Result<Vec<String>> {
+    let handle = std::process::Command::new("git")
+        .args(["ls-remote", "--tags", "--refs", "--", repository_url])
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .output()
+        .context("Failed to run git.")?;
+    if !handle.status.success() {
+        return Err(error::AnsibleExtensionError::SubprocessFailed {
+            command: format!("git ls-remote {}", repository_url),
+            status: handle.status.code(),
+            stderr: String::from_utf8_lossy(&handle.stderr).to_string(),
+        }
+        .into());
+    }
+    Ok(parse_ls_remote_tags(&String::from_utf8_lossy(
+        &handle.stdout,
+    )))
+}
+
+fn parse_ls_remote_tags(stdout: &str) -> Vec<String> {
+    stdout
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .filter_map(|reference| reference.strip_prefix("refs/tags/"))
+        .map(|tag| tag.trim_end_matches("^{}").to_string())
+        .collect()
+}
+
+/// Select the git reference matching the requested version.
+///
+/// Without a requested version, selects the highest version tag. Prereleases are skipped
+/// unless `include_prereleases` is set.
+fn select_reference(
+    tags: &[String],
+    requested_version: Option<&str>,
+    include_prereleases: bool,
+) -> Result<String> {
+    let requested_version = match requested_version {
+        Some(requested_version) => requested_version,
+        None => {
+            return tags
+                .iter()
+                .filter_map(|tag| {
+                    super::resolution::parse_version(tag).map(|version| (version, tag))
+                })
+                .filter(|(version, _)| {
+                    super::resolution::is_selectable(version, include_prereleases)
+                })
+                .max_by(|(a, _), (b, _)| super::resolution::compare_versions(a, b))
+                .map(|(_, tag)| tag.clone())
+                .ok_or(format_err!("Failed to find any stable version tags."));
+        }
+    };
+
+    if tags.iter().any(|tag| tag == requested_version) {
+        return Ok(requested_version.to_string());
+    }
+
+    // A plain version number selects the tag with that version, e.g. `1.0` matches `v1.0.0`.
+    if let Some(version) = super::resolution::parse_version(requested_version) {
+        if let Some(tag) = tags
+            .iter()
+            .find(|tag| super::resolution::parse_version(tag).as_ref() == Some(&version))
+        {
+            return Ok(tag.clone());
+        }
+    }
+
+    if let Ok(version_requirement) = super::resolution::VersionRequirement::parse(requested_version)
+    {
+        return tags
+            .iter()
+            .filter_map(|tag| super::resolution::parse_version(tag).map(|version| (version, tag)))
+            .filter(|(version, _)| version_requirement.matches(version, include_prereleases))
+            .max_by(|(a, _), (b, _)| super::resolution::compare_versions(a, b))
+            .map(|(_, tag)| tag.clone())
+            .ok_or(format_err!(
+                "Failed to find tag matching version requirement: {}",
+                requested_version
+            ));
+    }
+
+    // Otherwise assume a branch name or commit.
+    Ok(requested_version.to_string())
+}
+
+#[test]
+fn test_git_source_parse() -> Result<()> {
+    let source = GitSource::parse("git+https://github.com/org/repo.git,v1.0.0")?;
+    assert_eq!(source.repository_url, "https://github.com/org/repo.git");
+    assert_eq!(source.web_url.as_str(), "https://github.com/org/repo");
+    assert_eq!(source.version, Some("v1.0.0".to_string()));
+    assert_eq!(
+        source.archive_url("v1.0.0")?.as_str(),
+        "https://github.com/org/repo/archive/v1.0.0.tar.gz"
+    );
+
+    let source = GitSource::parse("git@gitlab.com:group/repo.git")?;
+    assert_eq!(source.web_url.as_str(), "https://gitlab.com/group/repo");
+    assert_eq!(
+        source.archive_url("2.0.0")?.as_str(),
+        "https://gitlab.com/group/repo/-/archive/2.0.0/repo-2.0.0.tar.gz"
+    );
+
+    assert!(GitSource::parse("git+--upload-pack=touch /tmp/x.git").is_err());
+
+    assert!(is_git_source("https://github.com/org/repo.git"));
+    assert!(!is_git_source("community.general"));
+    Ok(())
+}
+
+#[test]
+fn test_select_reference() -> Result<()> {
+    let tags = parse_ls_remote_tags(
+        "a1\trefs/tags/v1.0.0\nb2\trefs/tags/v1.2.0\nc3\trefs/tags/2.0.0-rc1\nd4\trefs/tags/nightly\n",
+    );
+    assert_eq!(select_reference(&tags, None, false)?, "v1.2.0");
+    assert_eq!(select_reference(&tags, None, true)?, "2.0.0-rc1");
+    assert_eq!(select_reference(&tags, Some("1.0"), false)?, "v1.0.0");
+    assert_eq!(
+        select_reference(&tags, Some(">=1.0.0, <2.0.0"), false)?,
+        "v1.2.0"
+    );
+    assert_eq!(select_reference(&tags, Some("nightly"), false)?, "nightly");
+    assert_eq!(select_reference(&tags, Some("devel"), false)?, "devel");
+    Ok(())
+}
@@ -0,0 +1,195 @@
+use anyhow::{format_err, Result};
+
+/// Collection at a specific version. Displayed as `<name>@<version>`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize)]
+pub struct DependencyNode {
+    /// Fully qualified collection name. For example: `community.general`
+    pub name: String,
+    pub version: String,
+}
+
+impl std::fmt::Display for DependencyNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}@{}", self.name, self.version)
+    }
+}
+
+/// Dependency requirement which could not be resolved to a registry version.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct UnresolvedDependency {
+    /// Dependent collection. None for a requirement of the project dependency file.
+    pub dependent: Option<DependencyNode>,
+
+    pub name: String,
+    pub version_requirement: String,
+}
+
+/// Collection dependency graph. Edges point from a collection to its requirements.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DependencyGraph {
+    /// Collections required directly by the project dependency file.
+    pub roots: std::collections::BTreeSet<DependencyNode>,
+
+    /// Requirements which were skipped while building the graph.
+    pub unresolved: Vec<UnresolvedDependency>,
+
+    edges_: std::collections::BTreeMap<DependencyNode, std::collections::BTreeSet<DependencyNode>>,
+}
+
+impl DependencyGraph {
+    /// Add a node without dependencies. Does nothing if the node exists.
+    pub fn add_node(&mut self, node: DependencyNode) {
+        self.edges_.entry(node).or_default();
+    }
+
+    /// Add a requirement of `dependent` on `dependency`, adding either node if missing.
+    pub fn add_edge(&mut self, dependent: DependencyNode, dependency: DependencyNode) {
+        self.add_node(dependency.clone());
+        self.edges_.entry(dependent).or_default().insert(dependency);
+    }
+
+    pub fn contains(&self, node: &DependencyNode) -> bool {
+        self.edges_.contains_key(node)
+    }
+
+    /// Returns all nodes, ordered by name then version.
+    pub fn nodes(&self) -> impl Iterator<Item = &DependencyNode> {
+        self.edges_.keys()
+    }
+
+    /// Returns the direct dependencies of a node.
+    pub fn dependencies(&self, node: &DependencyNode) -> impl Iterator<Item = &DependencyNode> {
+        self.edges_.get(node).into_iter().flatten()
+    }
+
+    /// Returns a dependency cycle, if any. The first node is repeated at the end.
+    pub fn find_cycle(&self) -> Option<Vec<DependencyNode>> {
+        self.visit_all().err()
+    }
+
+    /// Returns nodes ordered so that every collection follows its dependencies.
+    ///
+    /// Reviewing in this order means dependencies are reviewed before their dependents.
+    pub fn topological_order(&self) -> Result<Vec<DependencyNode>> {
+        self.visit_all().map_err(|cycle| {
+            format_err!(
+                "Dependency cycle found: {}",
+                cycle
+                    .iter()
+                    .map(|node| node.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" -> ")
+            )
+        })
+    }
+
+    /// Depth first post-order traversal. Returns the cycle path if a cycle is found.
+    fn visit_all(&self) -> std::result::Result<Vec<DependencyNode>, Vec<DependencyNode>> {
+        let mut order = Vec::new();
+        let mut visited = std::collections::BTreeSet::new();
+        for node in self.nodes() {
+            let mut path = Vec::new();
+            self.visit(node, &mut path, &mut visited, &mut order)?;
+        }
+        Ok(order)
+    }
+
+    fn visit<'a>(
+        &'a self,
+        node: &'a DependencyNode,
+        path: &mut Vec<&'a DependencyNode>,
+        visited: &mut std::collections::BTreeSet<&'a DependencyNode>,
+        order: &mut Vec<DependencyNode>,
+    ) -> std::result::Result<(), Vec<DependencyNode>> {
+        if let Some(index) = path.iter().position(|path_node| *path_node == node) {
+            let mut cycle: Vec<DependencyNode> =
+                path[index..].iter().map(|node| (*node).clone()).collect();
+            cycle.push(node.clone());
+            return Err(cycle);
+        }
+        if visited.contains(node) {
+            return Ok(());
+        }
+
+        path.push(node);
+        for dependency in self.dependencies(node) {
+            self.visit(dependency, path, visited, order)?;
+        }
+        path.pop();
+
+        visited.insert(node);
+        order.push(node.clone());
+        Ok(())
+    }
+}
+
+/// Returns collection requirements from a registry version entry's `metadata.dependencies`.
+pub fn get_entry_dependencies(entry_json: &serde_json::Value) -> Vec<(String, String)> {
+    entry_json["metadata"]["dependencies"]
+        .as_object()
+        .map(|dependencies| {
+            dependencies
+                .iter()
+                .map(|(name, requirement)| {
+                    (
+                        name.clone(),
+                        requirement.as_str().unwrap_or("*").to_string(),
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[test]
+fn test_dependency_graph() -> Result<()> {
+    let node = |name: &str| DependencyNode {
+        name: name.to_string(),
+        version: "1.0.0".to_string(),
+    };
+    let mut graph = DependencyGraph::default();
+    graph.add_edge(
+        node("community.docker"),
+        node("community.library_inventory_filtering_v1"),
+    );
+    graph.add_edge(node("community.general"), node("community.docker"));
+    graph.add_node(node("ansible.posix"));
+
+    assert_eq!(
+        graph.topological_order()?,
+        vec![
+            node("ansible.posix"),
+            node("community.library_inventory_filtering_v1"),
+            node("community.docker"),
+            node("community.general"),
+        ]
+    );
+    assert_eq!(graph.find_cycle(), None);
+
+    graph.add_edge(
+        node("community.library_inventory_filtering_v1"),
+        node("community.general"),
+    );
+    assert_eq!(
+        graph.find_cycle(),
+        Some(vec![
+            node("community.docker"),
+            node("community.library_inventory_filtering_v1"),
+            node("community.general"),
+            node("community.docker"),
+        ])
+    );
+    assert!(graph.topological_order().is_err());
+    Ok(())
+}
+
+#[test]
+fn test_get_entry_dependencies() {
+    let entry_json = serde_json::json!({
+        "metadata": {"dependencies": {"ansible.utils": ">=2.0.0"}}
+    });
+    assert_eq!(
+        get_entry_dependencies(&entry_json),
+        vec![("ansible.utils".to_string(), ">=2.0.0".to_string())]
+    );
+}
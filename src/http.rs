@@ -0,0 +1,1574 @@
+use super::cache;
+use super::circuit;
+use super::error;
+use super::registry;
+use super::scheduler;
+use anyhow::{format_err, Context, Result};
+use std::io::Read;
+
+/// HTTP client settings.
+#[derive(Debug, Clone)]
+pub struct HttpConfig {
+    /// PEM file of CA certificates trusted in addition to the system trust store.
+    pub ca_file: Option<std::path::PathBuf>,
+
+    /// Client certificate and key used for mutual TLS.
+    ///
+    /// A PKCS#12 archive with the native-tls backend, or a PEM file containing both the
+    /// certificate and the private key with the rustls backend.
+    pub client_identity_file: Option<std::path::PathBuf>,
+
+    /// Password protecting the client identity archive.
+    pub client_identity_password: Option<String>,
+
+    /// Proxy URL used for all requests. Credentials may be given as URL user info.
+    ///
+    /// Falls back to the HTTPS_PROXY, HTTP_PROXY and ALL_PROXY environment variables.
+    pub proxy: Option<String>,
+
+    /// Hosts which are reached without a proxy. Falls back to NO_PROXY.
+    pub no_proxy: Option<Vec<String>>,
+
+    /// Maximum time to establish a connection.
+    pub connect_timeout: std::time::Duration,
+
+    /// Maximum time to wait for a response, and for each read of its body.
+    pub read_timeout: std::time::Duration,
+
+    /// Maximum size of a registry JSON response body in bytes.
+    pub max_response_size: u64,
+
+    /// Maximum redirects followed per request. Zero disables following redirects.
+    pub max_redirects: usize,
+
+    /// Maximum idle connections kept open per host. Zero disables connection reuse,
+    /// for proxies which do not support keep-alive connections.
+    pub pool_max_idle_per_host: usize,
+
+    /// Time after which an idle pooled connection is closed.
+    pub pool_idle_timeout: std::time::Duration,
+
+    /// Interval of TCP keep-alive probes on open connections. None disables probes.
+    pub tcp_keepalive: Option<std::time::Duration>,
+
+    pub retry_policy: RetryPolicy,
+    pub request_budget: RequestBudget,
+    pub scheduler: scheduler::RequestScheduler,
+    pub circuit_breaker: circuit::CircuitBreaker,
+
+    /// Directory of stored responses. None disables the response cache.
+    pub cache_directory: Option<std::path::PathBuf>,
+    pub cache_policy: cache::CachePolicy,
+
+    /// Write a line to stderr for each registry request and cache hit.
+    pub trace: bool,
+}
+
+static DEFAULT_CONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+static DEFAULT_READ_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+static DEFAULT_MAX_RESPONSE_SIZE: u64 = 32 * 1024 * 1024;
+static DEFAULT_MAX_REDIRECTS: usize = 10;
+static DEFAULT_POOL_MAX_IDLE_PER_HOST: usize = 8;
+static DEFAULT_POOL_IDLE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(90);
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            ca_file: None,
+            client_identity_file: None,
+            client_identity_password: None,
+            proxy: None,
+            no_proxy: None,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            read_timeout: DEFAULT_READ_TIMEOUT,
+            max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            pool_max_idle_per_host: DEFAULT_POOL_MAX_IDLE_PER_HOST,
+            pool_idle_timeout: DEFAULT_POOL_IDLE_TIMEOUT,
+            tcp_keepalive: None,
+            retry_policy: RetryPolicy::default(),
+            request_budget: RequestBudget::default(),
+            scheduler: scheduler::RequestScheduler::default(),
+            circuit_breaker: circuit::CircuitBreaker::default(),
+            cache_directory: None,
+            cache_policy: cache::CachePolicy::default(),
+            trace: false,
+        }
+    }
+}
+
+impl HttpConfig {
+    /// Returns settings read from environment variables.
+    ///
+    /// VOUCH_ANSIBLE_CA_FILE, VOUCH_ANSIBLE_CLIENT_IDENTITY_FILE,
+    /// VOUCH_ANSIBLE_CLIENT_IDENTITY_PASSWORD, VOUCH_ANSIBLE_PROXY, VOUCH_ANSIBLE_NO_PROXY,
+    /// VOUCH_ANSIBLE_CONNECT_TIMEOUT_SECS, VOUCH_ANSIBLE_READ_TIMEOUT_SECS,
+    /// VOUCH_ANSIBLE_MAX_RESPONSE_BYTES, VOUCH_ANSIBLE_MAX_REDIRECTS,
+    /// VOUCH_ANSIBLE_POOL_MAX_IDLE_PER_HOST, VOUCH_ANSIBLE_POOL_IDLE_TIMEOUT_SECS,
+    /// VOUCH_ANSIBLE_TCP_KEEPALIVE_SECS and VOUCH_ANSIBLE_TRACE.
+    /// See `RetryPolicy::from_env`, `RequestBudget::from_env`, `RequestScheduler::from_env` and
+    /// `CircuitBreaker::from_env` for rate and failure settings,
+    /// and `cache::get_default_directory` and `CachePolicy::from_env` for the response cache.
+    pub fn from_env() -> Self {
+        Self {
+            ca_file: std::env::var_os("VOUCH_ANSIBLE_CA_FILE").map(std::path::PathBuf::from),
+            client_identity_file: std::env::var_os("VOUCH_ANSIBLE_CLIENT_IDENTITY_FILE")
+                .map(std::path::PathBuf::from),
+            client_identity_password: std::env::var("VOUCH_ANSIBLE_CLIENT_IDENTITY_PASSWORD").ok(),
+            proxy: std::env::var("VOUCH_ANSIBLE_PROXY").ok(),
+            no_proxy: std::env::var("VOUCH_ANSIBLE_NO_PROXY")
+                .ok()
+                .map(|value| parse_no_proxy(&value)),
+            connect_timeout: get_env_seconds("VOUCH_ANSIBLE_CONNECT_TIMEOUT_SECS")
+                .unwrap_or(DEFAULT_CONNECT_TIMEOUT),
+            read_timeout: get_env_seconds("VOUCH_ANSIBLE_READ_TIMEOUT_SECS")
+                .unwrap_or(DEFAULT_READ_TIMEOUT),
+            max_response_size: std::env::var("VOUCH_ANSIBLE_MAX_RESPONSE_BYTES")
+                .ok()
+                .and_then(|value| value.parse::<u64>().ok())
+                .unwrap_or(DEFAULT_MAX_RESPONSE_SIZE),
+            max_redirects: get_env_count("VOUCH_ANSIBLE_MAX_REDIRECTS")
+                .unwrap_or(DEFAULT_MAX_REDIRECTS),
+            pool_max_idle_per_host: get_env_count("VOUCH_ANSIBLE_POOL_MAX_IDLE_PER_HOST")
+                .unwrap_or(DEFAULT_POOL_MAX_IDLE_PER_HOST),
+            pool_idle_timeout: get_env_seconds("VOUCH_ANSIBLE_POOL_IDLE_TIMEOUT_SECS")
+                .unwrap_or(DEFAULT_POOL_IDLE_TIMEOUT),
+            tcp_keepalive: get_env_seconds("VOUCH_ANSIBLE_TCP_KEEPALIVE_SECS")
+                .filter(|interval| interval.as_secs() > 0),
+            retry_policy: RetryPolicy::from_env(),
+            request_budget: RequestBudget::from_env(),
+            scheduler: scheduler::RequestScheduler::from_env(),
+            circuit_breaker: circuit::CircuitBreaker::from_env(),
+            cache_directory: cache::get_default_directory(),
+            cache_policy: cache::CachePolicy::from_env(),
+            trace: std::env::var("VOUCH_ANSIBLE_TRACE")
+                .ok()
+                .and_then(|value| super::config::parse_bool(&value))
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// Returns true if the `--trace` extension argument is given. See `HttpConfig::trace`.
+pub fn get_trace_arg(extension_args: &[String]) -> bool {
+    extension_args.iter().any(|arg| arg == "--trace")
+}
+
+/// Retry policy for transient request failures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Total attempts per URL, including the first.
+    pub attempts: u32,
+
+    /// Delay before the first retry. Doubles with each further retry.
+    pub initial_backoff: std::time::Duration,
+    pub max_backoff: std::time::Duration,
+
+    /// Randomise each delay between half and all of its nominal value.
+    pub jitter: bool,
+
+    /// Longest Retry-After delay honoured for 429 responses. Longer delays fail immediately.
+    pub max_retry_after: std::time::Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            initial_backoff: std::time::Duration::from_millis(500),
+            max_backoff: std::time::Duration::from_secs(30),
+            jitter: true,
+            max_retry_after: std::time::Duration::from_secs(120),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns policy read from the VOUCH_ANSIBLE_RETRY_ATTEMPTS, VOUCH_ANSIBLE_RETRY_BACKOFF_MS,
+    /// VOUCH_ANSIBLE_RETRY_MAX_BACKOFF_MS, VOUCH_ANSIBLE_RETRY_JITTER and
+    /// VOUCH_ANSIBLE_MAX_RETRY_AFTER_MS environment variables.
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let get_millis = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .and_then(|value| value.parse::<u64>().ok())
+                .map(std::time::Duration::from_millis)
+        };
+        Self {
+            attempts: std::env::var("VOUCH_ANSIBLE_RETRY_ATTEMPTS")
+                .ok()
+                .and_then(|value| value.parse::<u32>().ok())
+                .map(|attempts| attempts.max(1))
+                .unwrap_or(default.attempts),
+            initial_backoff: get_millis("VOUCH_ANSIBLE_RETRY_BACKOFF_MS")
+                .unwrap_or(default.initial_backoff),
+            max_backoff: get_millis("VOUCH_ANSIBLE_RETRY_MAX_BACKOFF_MS")
+                .unwrap_or(default.max_backoff),
+            jitter: std::env::var("VOUCH_ANSIBLE_RETRY_JITTER")
+                .ok()
+                .and_then(|value| super::config::parse_bool(&value))
+                .unwrap_or(default.jitter),
+            max_retry_after: get_millis("VOUCH_ANSIBLE_MAX_RETRY_AFTER_MS")
+                .unwrap_or(default.max_retry_after),
+        }
+    }
+
+    /// Returns nominal delay before the given retry, starting from zero.
+    fn nominal_backoff(&self, retry: u32) -> std::time::Duration {
+        let factor = 2u32.saturating_pow(retry);
+        self.initial_backoff
+            .checked_mul(factor)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff)
+    }
+
+    /// Returns delay before the given retry, with jitter applied if enabled.
+    fn backoff(&self, retry: u32) -> std::time::Duration {
+        let backoff = self.nominal_backoff(retry);
+        if !self.jitter {
+            return backoff;
+        }
+        // Seed from the clock; the spread only needs to decorrelate concurrent clients.
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.subsec_nanos())
+            .unwrap_or(0);
+        let fraction = 0.5 + (seed % 1000) as f64 / 2000.0;
+        backoff.mul_f64(fraction)
+    }
+}
+
+#[test]
+fn test_retry_policy_backoff() {
+    let policy = RetryPolicy {
+        attempts: 5,
+        initial_backoff: std::time::Duration::from_millis(500),
+        max_backoff: std::time::Duration::from_secs(2),
+        jitter: false,
+        max_retry_after: std::time::Duration::from_secs(120),
+    };
+    assert_eq!(policy.backoff(0), std::time::Duration::from_millis(500));
+    assert_eq!(policy.backoff(1), std::time::Duration::from_secs(1));
+    assert_eq!(policy.backoff(3), std::time::Duration::from_secs(2));
+
+    let policy = RetryPolicy {
+        jitter: true,
+        ..policy
+    };
+    let backoff = policy.backoff(1);
+    assert!(backoff >= std::time::Duration::from_millis(500));
+    assert!(backoff <= std::time::Duration::from_secs(1));
+}
+
+/// Default client-side request budget. Keeps batch scans below galaxy.ansible.com rate limits.
+static DEFAULT_MAX_REQUESTS_PER_MINUTE: u32 = 300;
+
+/// Client-side request budget shared by all clones.
+#[derive(Debug, Clone)]
+pub struct RequestBudget {
+    /// Maximum requests sent within any one minute window. Zero disables the budget.
+    pub max_requests_per_minute: u32,
+
+    sent_: std::sync::Arc<std::sync::Mutex<std::collections::VecDeque<std::time::Instant>>>,
+}
+
+impl Default for RequestBudget {
+    fn default() -> Self {
+        Self {
+            max_requests_per_minute: DEFAULT_MAX_REQUESTS_PER_MINUTE,
+            sent_: Default::default(),
+        }
+    }
+}
+
+impl RequestBudget {
+    /// Returns budget read from the VOUCH_ANSIBLE_MAX_REQUESTS_PER_MINUTE environment variable.
+    pub fn from_env() -> Self {
+        Self {
+            max_requests_per_minute: std::env::var("VOUCH_ANSIBLE_MAX_REQUESTS_PER_MINUTE")
+                .ok()
+                .and_then(|value| value.parse::<u32>().ok())
+                .unwrap_or(DEFAULT_MAX_REQUESTS_PER_MINUTE),
+            sent_: Default::default(),
+        }
+    }
+
+    /// Block until a request may be sent within budget, then record it.
+    fn acquire(&self) {
+        while let Some(wait) = self.reserve() {
+            std::thread::sleep(wait);
+        }
+    }
+
+    /// Record a request if within budget. Otherwise, returns the time until budget frees up.
+    fn reserve(&self) -> Option<std::time::Duration> {
+        if self.max_requests_per_minute == 0 {
+            return None;
+        }
+        let window = std::time::Duration::from_secs(60);
+        let mut sent = match self.sent_.lock() {
+            Ok(sent) => sent,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let now = std::time::Instant::now();
+        while sent
+            .front()
+            .is_some_and(|sent_at| now.duration_since(*sent_at) >= window)
+        {
+            sent.pop_front();
+        }
+        if sent.len() < self.max_requests_per_minute as usize {
+            sent.push_back(now);
+            return None;
+        }
+        Some(window - now.duration_since(sent[0]))
+    }
+}
+
+/// Parse Retry-After header value given as delay seconds or HTTP date.
+fn parse_retry_after(value: &str) -> Option<std::time::Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(std::time::Duration::from_secs(seconds));
+    }
+    let retry_at = httpdate::parse_http_date(value).ok()?;
+    Some(
+        retry_at
+            .duration_since(std::time::SystemTime::now())
+            .unwrap_or_default(),
+    )
+}
+
+#[test]
+fn test_parse_retry_after() {
+    assert_eq!(
+        parse_retry_after("120"),
+        Some(std::time::Duration::from_secs(120))
+    );
+    assert_eq!(
+        parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+        Some(std::time::Duration::default())
+    );
+    assert_eq!(parse_retry_after("soon"), None);
+}
+
+/// HTTP client with the retry policy of its settings.
+///
+/// Dereferences to the underlying reqwest client for requests which are not retried.
+#[derive(Debug, Clone)]
+pub struct Client {
+    client: reqwest::blocking::Client,
+    retry_policy: RetryPolicy,
+    request_budget: RequestBudget,
+    scheduler: scheduler::RequestScheduler,
+    circuit_breaker: circuit::CircuitBreaker,
+    max_response_size: u64,
+    cache: Option<cache::ResponseCache>,
+    trace: bool,
+}
+
+impl std::ops::Deref for Client {
+    type Target = reqwest::blocking::Client;
+
+    fn deref(&self) -> &Self::Target {
+        &self.client
+    }
+}
+
+impl Client {
+    /// Returns the response body size limit, see `BoundedReader`.
+    pub fn max_response_size(&self) -> u64 {
+        self.max_response_size
+    }
+
+    /// Trace requests to stderr, whatever the client settings.
+    pub fn enable_trace(&mut self) {
+        self.trace = true;
+    }
+}
+
+/// Returns count given by an environment variable.
+fn get_env_count(name: &str) -> Option<usize> {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse::<usize>().ok())
+}
+
+/// Returns duration given in seconds by an environment variable.
+pub fn get_env_seconds(name: &str) -> Option<std::time::Duration> {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.trim().parse::<f64>().ok())
+        .filter(|seconds| seconds.is_finite() && *seconds > 0.0)
+        .map(std::time::Duration::from_secs_f64)
+}
+
+/// Returns first set environment variable, checking upper and lower case names.
+fn get_env_var(names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .flat_map(|name| vec![name.to_uppercase(), name.to_lowercase()])
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+}
+
+/// Parse comma separated NO_PROXY host list.
+fn parse_no_proxy(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|host| host.trim().to_lowercase())
+        .filter(|host| !host.is_empty())
+        .collect()
+}
+
+/// Returns true if given host matches a NO_PROXY entry.
+///
+/// Entries match the host itself and its subdomains. `*` matches all hosts.
+fn is_no_proxy_host(host: &str, no_proxy: &[String]) -> bool {
+    let host = host.to_lowercase();
+    no_proxy.iter().any(|entry| {
+        let entry = entry.split(':').next().unwrap_or(entry);
+        let entry = entry.trim_start_matches("*.").trim_start_matches('.');
+        entry == "*" || host == entry || host.ends_with(&format!(".{}", entry))
+    })
+}
+
+#[test]
+fn test_is_no_proxy_host() {
+    let no_proxy = parse_no_proxy("localhost, .internal.example.com,10.0.0.1:8080");
+    assert!(is_no_proxy_host("localhost", &no_proxy));
+    assert!(is_no_proxy_host("hub.internal.example.com", &no_proxy));
+    assert!(is_no_proxy_host("internal.example.com", &no_proxy));
+    assert!(is_no_proxy_host("10.0.0.1", &no_proxy));
+    assert!(!is_no_proxy_host("galaxy.ansible.com", &no_proxy));
+    assert!(!is_no_proxy_host("notinternal.example.com", &no_proxy));
+    assert!(is_no_proxy_host("galaxy.ansible.com", &parse_no_proxy("*")));
+}
+
+/// Returns proxy for requests, honouring explicit settings and standard environment variables.
+fn get_proxy(config: &HttpConfig) -> reqwest::Proxy {
+    let explicit_proxy = config.proxy.clone();
+    let no_proxy = config
+        .no_proxy
+        .clone()
+        .or_else(|| get_env_var(&["no_proxy"]).map(|value| parse_no_proxy(&value)))
+        .unwrap_or_default();
+
+    reqwest::Proxy::custom(move |url| {
+        if let Some(host) = url.host_str() {
+            if is_no_proxy_host(host, &no_proxy) {
+                return None;
+            }
+        }
+        let proxy = explicit_proxy.clone().or_else(|| match url.scheme() {
+            "https" => get_env_var(&["https_proxy", "all_proxy"]),
+            _ => get_env_var(&["http_proxy", "all_proxy"]),
+        })?;
+        url::Url::parse(&proxy).ok()
+    })
+}
+
+/// Returns HTTP client built from given settings.
+pub fn build_client(config: &HttpConfig) -> Result<Client> {
+    let mut builder = reqwest::blocking::Client::builder()
+        .no_proxy()
+        .proxy(get_proxy(config))
+        .connect_timeout(config.connect_timeout)
+        .timeout(config.read_timeout)
+        .redirect(get_redirect_policy(config))
+        .pool_max_idle_per_host(config.pool_max_idle_per_host)
+        .pool_idle_timeout(config.pool_idle_timeout)
+        .tcp_keepalive(config.tcp_keepalive);
+    for certificate in load_root_certificates(config)? {
+        builder = builder.add_root_certificate(certificate);
+    }
+    if let Some(identity) = load_identity(config)? {
+        builder = builder.identity(identity);
+    }
+
+    Ok(Client {
+        client: builder.build()?,
+        retry_policy: config.retry_policy.clone(),
+        request_budget: config.request_budget.clone(),
+        scheduler: config.scheduler.clone(),
+        circuit_breaker: config.circuit_breaker.clone(),
+        max_response_size: config.max_response_size,
+        cache: config
+            .cache_directory
+            .as_ref()
+            .map(|directory| cache::ResponseCache::new(directory, &config.cache_policy)),
+        trace: config.trace,
+    })
+}
+
+/// Returns redirect policy following up to the configured number of redirects.
+///
+/// Every hop is checked by `registry::check_redirect_url`.
+fn get_redirect_policy(config: &HttpConfig) -> reqwest::redirect::Policy {
+    if config.max_redirects == 0 {
+        return reqwest::redirect::Policy::none();
+    }
+    let max_redirects = config.max_redirects;
+    reqwest::redirect::Policy::custom(move |attempt| {
+        if attempt.previous().len() > max_redirects {
+            return attempt.error(format!("Exceeded {} redirects.", max_redirects));
+        }
+        match registry::check_redirect_url(attempt.previous().last(), attempt.url()) {
+            Ok(()) => attempt.follow(),
+            Err(error) => attempt.error(error),
+        }
+    })
+}
+
+/// Returns additional trusted CA certificates from the configured CA file.
+fn load_root_certificates(config: &HttpConfig) -> Result<Vec<reqwest::Certificate>> {
+    let ca_file = match &config.ca_file {
+        Some(ca_file) => ca_file,
+        None => return Ok(Vec::new()),
+    };
+    let pem = std::fs::read_to_string(ca_file)
+        .context(format!("Failed to read CA file: {}", ca_file.display()))?;
+    let certificates = split_pem_certificates(&pem);
+    if certificates.is_empty() {
+        return Err(format_err!(
+            "Failed to find any certificates in CA file: {}",
+            ca_file.display()
+        ));
+    }
+    certificates
+        .iter()
+        .map(|certificate| {
+            reqwest::Certificate::from_pem(certificate.as_bytes())
+                .context(format!("Failed to parse CA file: {}", ca_file.display()))
+        })
+        .collect()
+}
+
+/// Returns configured mutual TLS client identity.
+fn load_identity(config: &HttpConfig) -> Result<Option<reqwest::Identity>> {
+    let identity_file = match &config.client_identity_file {
+        Some(identity_file) => identity_file,
+        None => return Ok(None),
+    };
+    let content = std::fs::read(identity_file).context(format!(
+        "Failed to read client identity file: {}",
+        identity_file.display()
+    ))?;
+    let identity = parse_identity(&content, config).context(format!(
+        "Failed to parse client identity file: {}",
+        identity_file.display()
+    ))?;
+    Ok(Some(identity))
+}
+
+#[cfg(feature = "native-tls")]
+fn parse_identity(content: &[u8], config: &HttpConfig) -> Result<reqwest::Identity> {
+    let password = config.client_identity_password.as_deref().unwrap_or("");
+    Ok(reqwest::Identity::from_pkcs12_der(content, password)?)
+}
+
+#[cfg(all(feature = "rustls", not(feature = "native-tls")))]
+fn parse_identity(content: &[u8], config: &HttpConfig) -> Result<reqwest::Identity> {
+    if config.client_identity_password.is_some() {
+        return Err(format_err!(
+            "Encrypted client identities require the native-tls feature."
+        ));
+    }
+    Ok(reqwest::Identity::from_pem(content)?)
+}
+
+#[cfg(not(any(feature = "rustls", feature = "native-tls")))]
+compile_error!("Either the rustls or the native-tls feature must be enabled.");
+
+/// Async HTTP client with the retry policy of its settings.
+#[cfg(feature = "async")]
+#[derive(Debug, Clone)]
+pub struct AsyncClient {
+    client: reqwest::Client,
+    retry_policy: RetryPolicy,
+    request_budget: RequestBudget,
+    scheduler: scheduler::RequestScheduler,
+    circuit_breaker: circuit::CircuitBreaker,
+    max_response_size: u64,
+    cache: Option<cache::ResponseCache>,
+    trace: bool,
+}
+
+#[cfg(feature = "async")]
+impl std::ops::Deref for AsyncClient {
+    type Target = reqwest::Client;
+
+    fn deref(&self) -> &Self::Target {
+        &self.client
+    }
+}
+
+/// Returns async HTTP client built from given settings.
+#[cfg(feature = "async")]
+pub fn build_async_client(config: &HttpConfig) -> Result<AsyncClient> {
+    let mut builder = reqwest::Client::builder()
+        .no_proxy()
+        .proxy(get_proxy(config))
+        .connect_timeout(config.connect_timeout)
+        .timeout(config.read_timeout)
+        .redirect(get_redirect_policy(config))
+        .pool_max_idle_per_host(config.pool_max_idle_per_host)
+        .pool_idle_timeout(config.pool_idle_timeout)
+        .tcp_keepalive(config.tcp_keepalive);
+    for certificate in load_root_certificates(config)? {
+        builder = builder.add_root_certificate(certificate);
+    }
+    if let Some(identity) = load_identity(config)? {
+        builder = builder.identity(identity);
+    }
+
+    Ok(AsyncClient {
+        client: builder.build()?,
+        retry_policy: config.retry_policy.clone(),
+        request_budget: config.request_budget.clone(),
+        scheduler: config.scheduler.clone(),
+        circuit_breaker: config.circuit_breaker.clone(),
+        max_response_size: config.max_response_size,
+        cache: config
+            .cache_directory
+            .as_ref()
+            .map(|directory| cache::ResponseCache::new(directory, &config.cache_policy)),
+        trace: config.trace,
+    })
+}
+
+/// Split a PEM bundle into individual certificates.
+fn split_pem_certificates(pem: &str) -> Vec<String> {
+    static BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+    static END: &str = "-----END CERTIFICATE-----";
+
+    let mut certificates = Vec::new();
+    let mut remaining = pem;
+    while let Some(begin_index) = remaining.find(BEGIN) {
+        let end_index = match remaining[begin_index..].find(END) {
+            Some(end_index) => begin_index + end_index + END.len(),
+            None => break,
+        };
+        certificates.push(remaining[begin_index..end_index].to_string());
+        remaining = &remaining[end_index..];
+    }
+    certificates
+}
+
+#[test]
+fn test_split_pem_certificates() {
+    let pem = "# Root CA\n-----BEGIN CERTIFICATE-----\nAAAA\n-----END CERTIFICATE-----\n\
+               # Intermediate CA\n-----BEGIN CERTIFICATE-----\nBBBB\n-----END CERTIFICATE-----\n";
+    assert_eq!(
+        split_pem_certificates(pem),
+        vec![
+            "-----BEGIN CERTIFICATE-----\nAAAA\n-----END CERTIFICATE-----".to_string(),
+            "-----BEGIN CERTIFICATE-----\nBBBB\n-----END CERTIFICATE-----".to_string(),
+        ]
+    );
+}
+
+/// Request failure, classified by whether a mirror should be tried.
+enum RequestError {
+    /// Connection failure or server error.
+    Unavailable(anyhow::Error),
+
+    /// 429 response, with the Retry-After delay if given.
+    RateLimited(anyhow::Error, Option<std::time::Duration>),
+
+    Other(anyhow::Error),
+}
+
+impl RequestError {
+    fn context(self, context: String) -> Self {
+        match self {
+            Self::Unavailable(error) => Self::Unavailable(error.context(context)),
+            Self::RateLimited(error, retry_after) => {
+                Self::RateLimited(error.context(context), retry_after)
+            }
+            Self::Other(error) => Self::Other(error.context(context)),
+        }
+    }
+}
+
+/// Maximum number of response body bytes quoted in error messages.
+static MAX_ERROR_BODY_SIZE: usize = 512;
+
+/// Returns error context describing a failed response: the status, the URL after redirects
+/// and the start of the body.
+fn get_response_context(status: reqwest::StatusCode, final_url: &str, body: &[u8]) -> String {
+    let snippet = String::from_utf8_lossy(&body[..body.len().min(MAX_ERROR_BODY_SIZE)])
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut context = format!("HTTP {}: {}", status, final_url);
+    if !snippet.is_empty() {
+        context.push_str("\nResponse body: ");
+        context.push_str(&snippet);
+        if body.len() > MAX_ERROR_BODY_SIZE {
+            context.push_str("...");
+        }
+    }
+    context
+}
+
+#[test]
+fn test_get_response_context() {
+    let body = format!("<html>\n  <body>{}</body>\n</html>", "x".repeat(600));
+    let context = get_response_context(
+        reqwest::StatusCode::BAD_GATEWAY,
+        "https://galaxy.ansible.com/api/",
+        body.as_bytes(),
+    );
+    assert!(context.starts_with(
+        "HTTP 502 Bad Gateway: https://galaxy.ansible.com/api/\nResponse body: <html> <body>xxx"
+    ));
+    assert!(context.ends_with("x..."));
+    assert_eq!(
+        get_response_context(reqwest::StatusCode::NOT_FOUND, "https://example.com/", b""),
+        "HTTP 404 Not Found: https://example.com/"
+    );
+}
+
+/// Returns the start of a response body, for error messages. Read failures are ignored.
+fn read_error_body(response: reqwest::blocking::Response) -> Vec<u8> {
+    let mut body = Vec::new();
+    let _ = response
+        .take(MAX_ERROR_BODY_SIZE as u64 + 1)
+        .read_to_end(&mut body);
+    body
+}
+
+/// Reader which keeps a copy of the first bytes read, for error messages.
+struct HeadReader<R> {
+    reader: R,
+    head: Vec<u8>,
+}
+
+impl<R: Read> Read for HeadReader<R> {
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        let count = self.reader.read(buffer)?;
+        let head_count = count.min((MAX_ERROR_BODY_SIZE + 1).saturating_sub(self.head.len()));
+        self.head.extend_from_slice(&buffer[..head_count]);
+        Ok(count)
+    }
+}
+
+/// Returns JSON response body from given registry API URL.
+///
+/// Sends the registry token, if configured, to the registry host only. Requests are retried
+/// and fail over to the registry mirrors as by `send_with_retries`.
+///
+/// Fresh stored responses are returned without a request. Stale ones are revalidated.
+/// Responses to requests sent with the registry token are not stored.
+pub fn get_json(
+    client: &Client,
+    registry: &registry::Registry,
+    url: &str,
+) -> Result<serde_json::Value> {
+    if let Some(json) = get_fresh_json(get_request_cache(&client.cache, registry, url), url) {
+        trace_request(client.trace, url, "-", "fresh", None);
+        return json;
+    }
+    send_with_retries(client, url, registry.mirrored_urls(url), |candidate_url| {
+        get_json_once(client, registry, candidate_url)
+    })
+}
+
+/// Returns streamed response body of an artifact or other file download.
+///
+/// Sends the registry token, if a registry is given, to the registry host only. Requests are
+/// retried and fail over to the registry mirrors as by `send_with_retries`. The body is not
+/// size limited, see `BoundedReader`.
+pub fn get_download(
+    client: &Client,
+    registry: Option<&registry::Registry>,
+    url: &url::Url,
+) -> Result<reqwest::blocking::Response> {
+    let candidate_urls = match registry {
+        Some(registry) => registry.mirrored_urls(url.as_str()),
+        None => vec![url.to_string()],
+    };
+    send_with_retries(client, url.as_str(), candidate_urls, |candidate_url| {
+        get_download_once(client, registry, candidate_url)
+    })
+}
+
+/// Send a request to each candidate URL in turn until one succeeds.
+///
+/// Connection errors, timeouts and 5xx responses are retried with exponential backoff before
+/// the next candidate URL is tried. 429 responses are retried after the Retry-After delay.
+/// Other failures are returned immediately.
+fn send_with_retries<T, F>(
+    client: &Client,
+    url: &str,
+    candidate_urls: Vec<String>,
+    mut send: F,
+) -> Result<T>
+where
+    F: FnMut(&str) -> std::result::Result<(T, reqwest::StatusCode), RequestError>,
+{
+    let mut retry_loop =
+        RetryLoop::new(&client.retry_policy, &client.circuit_breaker, client.trace);
+    for candidate_url in candidate_urls {
+        let mut delay = None;
+        for attempt in 0..client.retry_policy.attempts {
+            if let Some(delay) = delay.take() {
+                std::thread::sleep(delay);
+            }
+            if !retry_loop.check(&candidate_url) {
+                break;
+            }
+            let started_at = std::time::Instant::now();
+            let result = send(&candidate_url);
+            match retry_loop.record(&candidate_url, attempt, result, started_at.elapsed()) {
+                RetryStep::Done(result) => return result,
+                RetryStep::Retry(retry_delay) => delay = Some(retry_delay),
+            }
+        }
+    }
+    Err(retry_loop.into_error(url))
+}
+
+/// Next step of a request loop after an attempt, see `RetryLoop::record`.
+enum RetryStep<T> {
+    Done(Result<T>),
+
+    /// Retry after the delay. The next candidate URL is tried once attempts are exhausted.
+    Retry(std::time::Duration),
+}
+
+/// Retry and failover bookkeeping shared by the blocking and async request loops, see
+/// `send_with_retries`.
+struct RetryLoop<'a> {
+    retry_policy: &'a RetryPolicy,
+    circuit_breaker: &'a circuit::CircuitBreaker,
+    trace: bool,
+    last_error: Option<anyhow::Error>,
+}
+
+impl<'a> RetryLoop<'a> {
+    fn new(
+        retry_policy: &'a RetryPolicy,
+        circuit_breaker: &'a circuit::CircuitBreaker,
+        trace: bool,
+    ) -> Self {
+        Self {
+            retry_policy,
+            circuit_breaker,
+            trace,
+            last_error: None,
+        }
+    }
+
+    /// Returns false if the candidate URL circuit is open, in which case the next candidate
+    /// URL should be tried.
+    fn check(&mut self, candidate_url: &str) -> bool {
+        match self.circuit_breaker.check(candidate_url) {
+            Ok(()) => true,
+            Err(error) => {
+                self.last_error = Some(error);
+                false
+            }
+        }
+    }
+
+    /// Trace and classify the result of an attempt.
+    fn record<T>(
+        &mut self,
+        candidate_url: &str,
+        attempt: u32,
+        result: std::result::Result<(T, reqwest::StatusCode), RequestError>,
+        duration: std::time::Duration,
+    ) -> RetryStep<T> {
+        trace_response(self.trace, candidate_url, &result, duration);
+        match &result {
+            Err(RequestError::Unavailable(error)) => {
+                self.circuit_breaker.record_failure(candidate_url, error)
+            }
+            _ => self.circuit_breaker.record_success(candidate_url),
+        }
+        let retry_policy = self.retry_policy;
+        match result {
+            Ok((value, _)) => RetryStep::Done(Ok(value)),
+            Err(RequestError::Unavailable(error)) => {
+                self.last_error = Some(error);
+                RetryStep::Retry(retry_policy.backoff(attempt))
+            }
+            Err(RequestError::RateLimited(error, retry_after)) => {
+                let retry_after = retry_after.unwrap_or_else(|| retry_policy.backoff(attempt));
+                if retry_after > retry_policy.max_retry_after {
+                    return RetryStep::Done(Err(error.context(format!(
+                        "Rate limited, Retry-After exceeds {} seconds.",
+                        retry_policy.max_retry_after.as_secs()
+                    ))));
+                }
+                self.last_error = Some(error);
+                RetryStep::Retry(retry_after)
+            }
+            Err(RequestError::Other(error)) => RetryStep::Done(Err(error)),
+        }
+    }
+
+    /// Returns the last failure, once all candidate URLs are exhausted.
+    fn into_error(self, url: &str) -> anyhow::Error {
+        self.last_error
+            .unwrap_or_else(|| format_err!("Failed to request URL: {}", url))
+    }
+}
+
+#[test]
+fn test_send_with_retries() -> Result<()> {
+    let client = build_client(&HttpConfig {
+        retry_policy: RetryPolicy {
+            attempts: 2,
+            initial_backoff: std::time::Duration::from_millis(1),
+            max_backoff: std::time::Duration::from_millis(1),
+            jitter: false,
+            max_retry_after: std::time::Duration::from_secs(1),
+        },
+        ..Default::default()
+    })?;
+    let candidate_urls = vec![
+        "https://hub.example.com/a.tar.gz".to_string(),
+        "https://mirror.example.com/a.tar.gz".to_string(),
+    ];
+    let mut requested_urls = Vec::new();
+    let value = send_with_retries(
+        &client,
+        "https://hub.example.com/a.tar.gz",
+        candidate_urls,
+        |url| {
+            requested_urls.push(url.to_string());
+            if url.starts_with("https://mirror.") {
+                Ok((1, reqwest::StatusCode::OK))
+            } else {
+                Err(RequestError::Unavailable(format_err!("Connection reset.")))
+            }
+        },
+    )?;
+    assert_eq!(value, 1);
+    assert_eq!(
+        requested_urls,
+        vec![
+            "https://hub.example.com/a.tar.gz",
+            "https://hub.example.com/a.tar.gz",
+            "https://mirror.example.com/a.tar.gz",
+        ]
+    );
+
+    let result: Result<()> = send_with_retries(&client, "https://hub.example.com/", vec![], |_| {
+        Err(RequestError::Other(format_err!("Unreachable.")))
+    });
+    assert!(result.is_err());
+    Ok(())
+}
+
+fn get_download_once(
+    client: &Client,
+    registry: Option<&registry::Registry>,
+    url: &str,
+) -> std::result::Result<(reqwest::blocking::Response, reqwest::StatusCode), RequestError> {
+    let mut request = client.get(url);
+    if let Some(registry) = registry.filter(|registry| registry.is_token_url(url)) {
+        if let Some(authorization) = registry
+            .authorization_header(client)
+            .map_err(RequestError::Other)?
+        {
+            request = request.header(reqwest::header::AUTHORIZATION, authorization);
+        }
+    }
+
+    client.request_budget.acquire();
+    // Held until the response headers are received.
+    let _permit = client.scheduler.acquire(url);
+    let result = request
+        .send()
+        .map_err(|error| classify_send_error(error, url))?;
+    let status = result.status();
+    let final_url = result.url().to_string();
+    // Downloads are not cached.
+    let status_error = match check_response_status(None, status, result.headers(), url, &None) {
+        Ok(_) => get_unexpected_status_error(status),
+        Err(error) => Some(error),
+    };
+    if let Some(error) = status_error {
+        let body = read_error_body(result);
+        return Err(error.context(get_response_context(status, &final_url, &body)));
+    }
+    Ok((result, status))
+}
+
+/// Registry JSON request settings shared by the blocking and async clients.
+struct JsonRequest<'a> {
+    /// Response cache, unless the request is sent with the registry token.
+    cache: Option<&'a cache::ResponseCache>,
+
+    /// Stored response to revalidate.
+    cached_response: Option<cache::CachedResponse>,
+
+    headers: Vec<(reqwest::header::HeaderName, String)>,
+}
+
+/// Returns the cache and headers of a registry JSON request: the authorization, if given, and
+/// the validators of a stored response.
+fn prepare_json_request<'a>(
+    cache: &'a Option<cache::ResponseCache>,
+    registry: &registry::Registry,
+    url: &str,
+    authorization: Option<String>,
+) -> JsonRequest<'a> {
+    let cache = get_request_cache(cache, registry, url);
+    let cached_response = cache
+        .and_then(|cache| cache.get(url))
+        .filter(|response| !response.not_found);
+    let mut headers = get_conditional_headers(&cached_response);
+    if let Some(authorization) = authorization {
+        headers.push((reqwest::header::AUTHORIZATION, authorization));
+    }
+    JsonRequest {
+        cache,
+        cached_response,
+        headers,
+    }
+}
+
+#[test]
+fn test_prepare_json_request() {
+    let registry = registry::Registry::galaxy();
+    let url = "https://galaxy.ansible.com/api/v2/collections/community/general/";
+    let json_request =
+        prepare_json_request(&None, &registry, url, Some("Token secret".to_string()));
+    assert!(json_request.cache.is_none());
+    assert_eq!(
+        json_request.headers,
+        vec![(reqwest::header::AUTHORIZATION, "Token secret".to_string())]
+    );
+    assert!(prepare_json_request(&None, &registry, url, None)
+        .headers
+        .is_empty());
+}
+
+/// Classify a registry JSON response by status.
+///
+/// Returns the revalidated stored JSON for a 304 response, None if the body should be read,
+/// or the status error.
+fn classify_json_status(
+    cache: Option<&cache::ResponseCache>,
+    status: reqwest::StatusCode,
+    headers: &reqwest::header::HeaderMap,
+    url: &str,
+    cached_response: &Option<cache::CachedResponse>,
+) -> std::result::Result<Option<serde_json::Value>, RequestError> {
+    match check_response_status(cache, status, headers, url, cached_response)? {
+        Some(json) => Ok(Some(json)),
+        None => match get_unexpected_status_error(status) {
+            Some(error) => Err(error),
+            None => Ok(None),
+        },
+    }
+}
+
+fn get_json_once(
+    client: &Client,
+    registry: &registry::Registry,
+    url: &str,
+) -> std::result::Result<(serde_json::Value, reqwest::StatusCode), RequestError> {
+    let authorization = if registry.is_token_url(url) {
+        registry
+            .authorization_header(client)
+            .map_err(RequestError::Other)?
+    } else {
+        None
+    };
+    let json_request = prepare_json_request(&client.cache, registry, url, authorization);
+    let (cache, cached_response) = (json_request.cache, &json_request.cached_response);
+    let mut request = client.get(url);
+    for (name, value) in json_request.headers {
+        request = request.header(name, value);
+    }
+
+    client.request_budget.acquire();
+    // Held until the body is read.
+    let _permit = client.scheduler.acquire(url);
+    let result = request
+        .send()
+        .map_err(|error| classify_send_error(error, url))?;
+    let status = result.status();
+    let final_url = result.url().to_string();
+    let response_context = |body: &[u8]| get_response_context(status, &final_url, body);
+    let status_error =
+        match classify_json_status(cache, status, result.headers(), url, cached_response) {
+            Ok(Some(json)) => return Ok((json, reqwest::StatusCode::NOT_MODIFIED)),
+            Ok(None) => None,
+            Err(error) => Some(error),
+        };
+    if let Some(error) = status_error {
+        return Err(error.context(response_context(&read_error_body(result))));
+    }
+
+    check_content_length(result.content_length(), client.max_response_size, url)
+        .map_err(|error| error.context(response_context(&[])))?;
+    let headers = result.headers().clone();
+    // Deserialize while reading, without first copying the whole body.
+    let mut reader = BoundedReader::new(
+        HeadReader {
+            reader: result,
+            head: Vec::new(),
+        },
+        client.max_response_size,
+    );
+    let json = match serde_json::from_reader(&mut reader) {
+        Ok(json) => json,
+        Err(_) if reader.exceeded => {
+            return Err(get_size_exceeded_error(client.max_response_size, url)
+                .context(response_context(&[])))
+        }
+        Err(error) if error.is_io() => {
+            return Err(RequestError::Unavailable(
+                anyhow::Error::new(error)
+                    .context(format!("Failed to read response: {}", url))
+                    .context(response_context(&[])),
+            ))
+        }
+        Err(error) => {
+            return Err(RequestError::Other(
+                anyhow::Error::new(error)
+                    .context(format!("JSON was not well-formatted: {}", url))
+                    .context(response_context(&reader.reader.head)),
+            ))
+        }
+    };
+    store_response(cache, url, status, &headers, &json);
+    Ok((json, status))
+}
+
+/// Reader which fails once more than a given number of bytes are read.
+pub struct BoundedReader<R> {
+    reader: R,
+    remaining: u64,
+    exceeded: bool,
+}
+
+impl<R> BoundedReader<R> {
+    pub fn new(reader: R, max_size: u64) -> Self {
+        Self {
+            reader,
+            remaining: max_size,
+            exceeded: false,
+        }
+    }
+}
+
+impl<R: Read> Read for BoundedReader<R> {
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        // Read one byte past the limit so that a body of exactly the limit is accepted.
+        let max_read = (self.remaining + 1).min(buffer.len() as u64) as usize;
+        let count = self.reader.read(&mut buffer[..max_read])?;
+        if count as u64 > self.remaining {
+            self.exceeded = true;
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Response body size limit exceeded.",
+            ));
+        }
+        self.remaining -= count as u64;
+        Ok(count)
+    }
+}
+
+#[test]
+fn test_bounded_reader() -> Result<()> {
+    let mut reader = BoundedReader::new(&b"[1, 2]"[..], 6);
+    let json: serde_json::Value = serde_json::from_reader(&mut reader)?;
+    assert_eq!(json, serde_json::json!([1, 2]));
+
+    let mut reader = BoundedReader::new(&b"[1, 2, 3]"[..], 6);
+    assert!(serde_json::from_reader::<_, serde_json::Value>(&mut reader).is_err());
+    assert!(reader.exceeded);
+    Ok(())
+}
+
+/// Fail early when the declared body size exceeds the limit.
+fn check_content_length(
+    content_length: Option<u64>,
+    max_response_size: u64,
+    url: &str,
+) -> std::result::Result<(), RequestError> {
+    match content_length {
+        Some(content_length) if content_length > max_response_size => {
+            Err(get_size_exceeded_error(max_response_size, url))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn get_size_exceeded_error(max_response_size: u64, url: &str) -> RequestError {
+    RequestError::Other(format_err!(
+        "Response body exceeds {} bytes: {}",
+        max_response_size,
+        url
+    ))
+}
+
+/// Write a registry request trace line to stderr, if enabled.
+///
+/// Stdout is reserved for the extension interface.
+pub fn trace_request(
+    trace: bool,
+    url: &str,
+    status: &str,
+    cache: &str,
+    duration: Option<std::time::Duration>,
+) {
+    if !trace {
+        return;
+    }
+    let duration = duration
+        .map(|duration| format!(" duration_ms={}", duration.as_millis()))
+        .unwrap_or_default();
+    eprintln!(
+        "vouch-ansible: method=GET url={} status={} cache={}{}",
+        url, status, cache, duration
+    );
+}
+
+/// Trace the outcome of a single request attempt.
+fn trace_response<T>(
+    trace: bool,
+    url: &str,
+    result: &std::result::Result<(T, reqwest::StatusCode), RequestError>,
+    duration: std::time::Duration,
+) {
+    if !trace {
+        return;
+    }
+    let (status, cache) = match result {
+        Ok((_, status)) if *status == reqwest::StatusCode::NOT_MODIFIED => {
+            (status.as_u16().to_string(), "revalidated")
+        }
+        Ok((_, status)) => (status.as_u16().to_string(), "miss"),
+        Err(RequestError::RateLimited(..)) => ("429".to_string(), "miss"),
+        Err(RequestError::Unavailable(error)) | Err(RequestError::Other(error)) => {
+            (format!("error error={:?}", format!("{:#}", error)), "miss")
+        }
+    };
+    trace_request(trace, url, &status, cache, Some(duration));
+}
+
+/// Returns the response cache for a request to the given URL.
+///
+/// Requests sent with the registry token are not cached, so that private registry responses
+/// are not stored on disk or served to a process using other credentials. Responses stored for
+/// such a request by earlier versions are removed rather than revalidated.
+fn get_request_cache<'a>(
+    cache: &'a Option<cache::ResponseCache>,
+    registry: &registry::Registry,
+    url: &str,
+) -> Option<&'a cache::ResponseCache> {
+    let cache = cache.as_ref()?;
+    if registry.token.is_some() && registry.is_token_url(url) {
+        cache.remove(url);
+        return None;
+    }
+    Some(cache)
+}
+
+#[test]
+fn test_get_request_cache() {
+    let cache = Some(cache::ResponseCache::new(
+        std::path::Path::new("/nonexistent"),
+        &cache::CachePolicy::default(),
+    ));
+    let mut registry = registry::Registry::galaxy();
+    let url = "https://galaxy.ansible.com/api/v2/collections/community/general/";
+    assert!(get_request_cache(&cache, &registry, url).is_some());
+
+    registry.token = Some("secret".to_string());
+    assert!(get_request_cache(&cache, &registry, url).is_none());
+    assert!(get_request_cache(&cache, &registry, "https://mirror.example.com/api/").is_some());
+}
+
+/// Returns fresh stored JSON response or not found error for URL.
+fn get_fresh_json(
+    cache: Option<&cache::ResponseCache>,
+    url: &str,
+) -> Option<Result<serde_json::Value>> {
+    let cached_response = cache?.get_fresh(url)?;
+    if cached_response.not_found {
+        return Some(Err(get_not_found_error(url)));
+    }
+    serde_json::from_str(&cached_response.body).ok().map(Ok)
+}
+
+fn get_not_found_error(url: &str) -> anyhow::Error {
+    anyhow::Error::new(error::AnsibleExtensionError::NotFound {
+        url: url.to_string(),
+    })
+}
+
+/// Classify request send failure.
+fn classify_send_error(error: reqwest::Error, url: &str) -> RequestError {
+    // Request errors include connections reset before a response is received.
+    let is_unavailable = error.is_connect() || error.is_timeout() || error.is_request();
+    // The error URL is the last one requested, after any redirects.
+    let final_url = error
+        .url()
+        .map(|url| url.to_string())
+        .unwrap_or_else(|| url.to_string());
+    let error = anyhow::Error::new(error).context(error::AnsibleExtensionError::Network {
+        url: final_url,
+        message: "Failed to request URL".to_string(),
+    });
+    if is_unavailable {
+        RequestError::Unavailable(error)
+    } else {
+        RequestError::Other(error)
+    }
+}
+
+/// Returns conditional request headers revalidating a cached response.
+fn get_conditional_headers(
+    cached_response: &Option<cache::CachedResponse>,
+) -> Vec<(reqwest::header::HeaderName, String)> {
+    let mut headers = Vec::new();
+    if let Some(cached_response) = cached_response {
+        if let Some(etag) = &cached_response.etag {
+            headers.push((reqwest::header::IF_NONE_MATCH, etag.clone()));
+        }
+        if let Some(last_modified) = &cached_response.last_modified {
+            headers.push((reqwest::header::IF_MODIFIED_SINCE, last_modified.clone()));
+        }
+    }
+    headers
+}
+
+/// Check response status for retryable failures.
+///
+/// Returns the cached JSON body for 304 Not Modified responses and renews its freshness.
+/// 404 Not Found responses are stored so that repeated lookups fail without a request.
+/// 401 and 403 responses fail without retries, as retrying cannot supply credentials.
+fn check_response_status(
+    cache: Option<&cache::ResponseCache>,
+    status: reqwest::StatusCode,
+    headers: &reqwest::header::HeaderMap,
+    url: &str,
+    cached_response: &Option<cache::CachedResponse>,
+) -> std::result::Result<Option<serde_json::Value>, RequestError> {
+    if status == reqwest::StatusCode::NOT_MODIFIED {
+        if let Some(cached_response) = cached_response {
+            if let Some(cache) = cache {
+                let _ = cache.insert(&cache::CachedResponse {
+                    stored_at: cache::get_unix_time(),
+                    ..cached_response.clone()
+                });
+            }
+            return parse_json_body(&cached_response.body).map(Some);
+        }
+    }
+    if status == reqwest::StatusCode::NOT_FOUND {
+        if let Some(cache) = cache {
+            let _ = cache.insert(&cache::CachedResponse {
+                url: url.to_string(),
+                etag: None,
+                last_modified: None,
+                body: String::new(),
+                stored_at: cache::get_unix_time(),
+                not_found: true,
+            });
+        }
+        return Err(RequestError::Other(get_not_found_error(url)));
+    }
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        let retry_after = headers
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_retry_after);
+        return Err(RequestError::RateLimited(
+            anyhow::Error::new(error::AnsibleExtensionError::RateLimited {
+                url: url.to_string(),
+                retry_after,
+            }),
+            retry_after,
+        ));
+    }
+    if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+        return Err(RequestError::Other(anyhow::Error::new(
+            error::AnsibleExtensionError::AuthRequired {
+                url: url.to_string(),
+                status: status.as_u16(),
+            },
+        )));
+    }
+    if status.is_server_error() {
+        return Err(RequestError::Unavailable(anyhow::Error::new(
+            error::AnsibleExtensionError::Network {
+                url: url.to_string(),
+                message: format!("Registry server error ({})", status),
+            },
+        )));
+    }
+    Ok(None)
+}
+
+/// Returns an error for responses which are neither successful nor otherwise handled,
+/// so that error bodies are not mistaken for registry data.
+fn get_unexpected_status_error(status: reqwest::StatusCode) -> Option<RequestError> {
+    if status.is_success() {
+        return None;
+    }
+    Some(RequestError::Other(format_err!(
+        "Unexpected registry response status: {}",
+        status
+    )))
+}
+
+/// Store successful response with its validators. Failures to store are ignored.
+fn store_response(
+    cache: Option<&cache::ResponseCache>,
+    url: &str,
+    status: reqwest::StatusCode,
+    headers: &reqwest::header::HeaderMap,
+    json: &serde_json::Value,
+) {
+    let cache = match cache {
+        Some(cache) if status.is_success() => cache,
+        _ => return,
+    };
+    let body = match serde_json::to_string(json) {
+        Ok(body) => body,
+        Err(_) => return,
+    };
+    let get_header = |name: reqwest::header::HeaderName| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string())
+    };
+    let _ = cache.insert(&cache::CachedResponse {
+        url: url.to_string(),
+        etag: get_header(reqwest::header::ETAG),
+        last_modified: get_header(reqwest::header::LAST_MODIFIED),
+        body,
+        stored_at: cache::get_unix_time(),
+        not_found: false,
+    });
+}
+
+fn parse_json_body(body: &str) -> std::result::Result<serde_json::Value, RequestError> {
+    serde_json::from_str(body)
+        .context(format!("JSON was not well-formatted:\n{}", body))
+        .map_err(RequestError::Other)
+}
+
+/// Returns JSON response body from given registry API URL using an async client.
+///
+/// Caching, retries, rate limiting and mirror failover behave as in `get_json`.
+#[cfg(feature = "async")]
+pub async fn get_json_async(
+    client: &AsyncClient,
+    registry: &registry::Registry,
+    url: &str,
+) -> Result<serde_json::Value> {
+    if let Some(json) = get_fresh_json(get_request_cache(&client.cache, registry, url), url) {
+        trace_request(client.trace, url, "-", "fresh", None);
+        return json;
+    }
+    let mut retry_loop =
+        RetryLoop::new(&client.retry_policy, &client.circuit_breaker, client.trace);
+    for candidate_url in registry.mirrored_urls(url) {
+        let mut delay = None;
+        for attempt in 0..client.retry_policy.attempts {
+            if let Some(delay) = delay.take() {
+                tokio::time::delay_for(delay).await;
+            }
+            if !retry_loop.check(&candidate_url) {
+                break;
+            }
+            let started_at = std::time::Instant::now();
+            let result = get_json_once_async(client, registry, &candidate_url).await;
+            match retry_loop.record(&candidate_url, attempt, result, started_at.elapsed()) {
+                RetryStep::Done(result) => return result,
+                RetryStep::Retry(retry_delay) => delay = Some(retry_delay),
+            }
+        }
+    }
+    Err(retry_loop.into_error(url))
+}
+
+#[cfg(feature = "async")]
+async fn get_json_once_async(
+    client: &AsyncClient,
+    registry: &registry::Registry,
+    url: &str,
+) -> std::result::Result<(serde_json::Value, reqwest::StatusCode), RequestError> {
+    let authorization = if registry.is_token_url(url) {
+        registry
+            .authorization_header_async(client)
+            .await
+            .map_err(RequestError::Other)?
+    } else {
+        None
+    };
+    let json_request = prepare_json_request(&client.cache, registry, url, authorization);
+    let (cache, cached_response) = (json_request.cache, &json_request.cached_response);
+    let mut request = client.get(url);
+    for (name, value) in json_request.headers {
+        request = request.header(name, value);
+    }
+
+    while let Some(wait) = client.request_budget.reserve() {
+        tokio::time::delay_for(wait).await;
+    }
+    let _permit = loop {
+        match client.scheduler.try_acquire(url) {
+            Ok(permit) => break permit,
+            Err(wait) => tokio::time::delay_for(wait).await,
+        }
+    };
+    let mut result = request
+        .send()
+        .await
+        .map_err(|error| classify_send_error(error, url))?;
+    let status = result.status();
+    let final_url = result.url().to_string();
+    let response_context = |body: &[u8]| get_response_context(status, &final_url, body);
+    let status_error =
+        match classify_json_status(cache, status, result.headers(), url, cached_response) {
+            Ok(Some(json)) => return Ok((json, reqwest::StatusCode::NOT_MODIFIED)),
+            Ok(None) => None,
+            Err(error) => Some(error),
+        };
+    if let Some(error) = status_error {
+        // Read failures are ignored, as for the blocking client.
+        let mut body = Vec::new();
+        while let Ok(Some(chunk)) = result.chunk().await {
+            body.extend_from_slice(&chunk);
+            if body.len() > MAX_ERROR_BODY_SIZE {
+                break;
+            }
+        }
+        return Err(error.context(response_context(&body)));
+    }
+
+    check_content_length(result.content_length(), client.max_response_size, url)
+        .map_err(|error| error.context(response_context(&[])))?;
+    let headers = result.headers().clone();
+    let mut body = Vec::new();
+    while let Some(chunk) = result.chunk().await.map_err(|error| {
+        RequestError::Unavailable(anyhow::Error::new(error).context(response_context(&[])))
+    })? {
+        if (body.len() + chunk.len()) as u64 > client.max_response_size {
+            return Err(get_size_exceeded_error(client.max_response_size, url)
+                .context(response_context(&[])));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    let json = serde_json::from_slice(&body)
+        .context(format!("JSON was not well-formatted: {}", url))
+        .context(response_context(&body))
+        .map_err(RequestError::Other)?;
+    store_response(cache, url, status, &headers, &json);
+    Ok((json, status))
+}
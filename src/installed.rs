@@ -0,0 +1,752 @@
+use super::error;
+use anyhow::{format_err, Result};
+
+/// Search path setting, such as the collections path.
+struct SearchPathSetting {
+    /// Environment variables, highest precedence first.
+    env_vars: &'static [&'static str],
+
+    /// `[defaults]` section keys in ansible.cfg, highest precedence first.
+    config_keys: &'static [&'static str],
+
+    /// Default paths, as in ansible-core.
+    defaults: &'static [&'static str],
+}
+
+static COLLECTIONS_PATH_SETTING: SearchPathSetting = SearchPathSetting {
+    env_vars: &["ANSIBLE_COLLECTIONS_PATH", "ANSIBLE_COLLECTIONS_PATHS"],
+    config_keys: &["collections_path", "collections_paths"],
+    defaults: &["~/.ansible/collections", "/usr/share/ansible/collections"],
+};
+
+static ROLES_PATH_SETTING: SearchPathSetting = SearchPathSetting {
+    env_vars: &["ANSIBLE_ROLES_PATH"],
+    config_keys: &["roles_path"],
+    defaults: &[
+        "~/.ansible/roles",
+        "/usr/share/ansible/roles",
+        "/etc/ansible/roles",
+    ],
+};
+
+/// Returns the paths searched for installed collections, highest precedence first.
+///
+/// The project `collections` directory comes first, as ansible-playbook searches the
+/// directory adjacent to the playbook before the configured paths.
+pub fn get_collections_paths(project_directory: &std::path::Path) -> Vec<std::path::PathBuf> {
+    let mut paths = vec![project_directory.join("collections")];
+    paths.extend(get_configured_paths(
+        project_directory,
+        &COLLECTIONS_PATH_SETTING,
+    ));
+    paths
+}
+
+/// Returns the paths searched for installed standalone roles, highest precedence first.
+///
+/// The project `roles` directory comes first, as for collections.
+pub fn get_roles_paths(project_directory: &std::path::Path) -> Vec<std::path::PathBuf> {
+    let mut paths = vec![project_directory.join("roles")];
+    paths.extend(get_configured_paths(project_directory, &ROLES_PATH_SETTING));
+    paths
+}
+
+/// Returns the configured search paths.
+///
+/// Given by an environment variable as a colon separated list, then by the ansible.cfg file
+/// which applies to the project, otherwise the ansible-core defaults. Relative config file
+/// paths are relative to the config file's directory, as in ansible itself.
+fn get_configured_paths(
+    project_directory: &std::path::Path,
+    setting: &SearchPathSetting,
+) -> Vec<std::path::PathBuf> {
+    if let Some(paths) = setting
+        .env_vars
+        .iter()
+        .find_map(|env_var| std::env::var(env_var).ok())
+    {
+        return parse_search_paths(&paths);
+    }
+
+    if let Some(config_file_path) = super::config::find_project_config_file(project_directory) {
+        if let Ok(config) = super::config::AnsibleConfig::from_file(&config_file_path) {
+            let config_directory = config_file_path
+                .parent()
+                .unwrap_or(std::path::Path::new("."));
+            if let Some(paths) = get_config_paths(&config, config_directory, setting) {
+                return paths;
+            }
+        }
+    }
+
+    setting
+        .defaults
+        .iter()
+        .map(|path| super::config::expand_home_directory(path))
+        .collect()
+}
+
+/// Returns search paths given by an ansible.cfg file, if set.
+fn get_config_paths(
+    config: &super::config::AnsibleConfig,
+    config_directory: &std::path::Path,
+    setting: &SearchPathSetting,
+) -> Option<Vec<std::path::PathBuf>> {
+    let paths = setting
+        .config_keys
+        .iter()
+        .find_map(|key| config.get("defaults", key))?;
+    Some(
+        parse_search_paths(paths)
+            .into_iter()
+            .map(|path| config_directory.join(path))
+            .collect(),
+    )
+}
+
+/// Search path list separator, as Python `os.pathsep`.
+static SEARCH_PATH_SEPARATOR: char = if cfg!(windows) { ';' } else { ':' };
+
+/// Parse search paths separated by the platform path list separator, expanding a leading `~`.
+fn parse_search_paths(paths: &str) -> Vec<std::path::PathBuf> {
+    parse_separated_search_paths(paths, SEARCH_PATH_SEPARATOR)
+}
+
+fn parse_separated_search_paths(paths: &str, separator: char) -> Vec<std::path::PathBuf> {
+    paths
+        .split(separator)
+        .map(|path| path.trim())
+        .filter(|path| !path.is_empty())
+        .map(super::config::expand_home_directory)
+        .collect()
+}
+
+/// Returns the directory of a collection within an `ansible_collections` directory.
+pub fn get_collection_directory(
+    collections_root: &std::path::Path,
+    name: &str,
+) -> std::path::PathBuf {
+    match name.split_once('.') {
+        Some((namespace, collection)) => collections_root.join(namespace).join(collection),
+        None => collections_root.join(name),
+    }
+}
+
+/// Installed collection.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct InstalledCollection {
+    /// Fully qualified collection name. For example: `community.general`
+    pub name: String,
+    pub version: String,
+
+    /// Collection directory.
+    pub path: std::path::PathBuf,
+
+    /// True for collections bundled with ansible or ansible-core, such as `ansible.builtin`.
+    pub is_bundled: bool,
+}
+
+/// Returns installed collections in collections path order.
+///
+/// Collections are found at `ansible_collections/<namespace>/<name>/MANIFEST.json` within
+/// each collections path. A path may also name the `ansible_collections` directory itself.
+pub fn find_installed_collections(
+    collections_paths: &[std::path::PathBuf],
+) -> Vec<InstalledCollection> {
+    let mut collections = Vec::new();
+    for collections_path in collections_paths {
+        let collections_root = if collections_path.ends_with("ansible_collections") {
+            collections_path.clone()
+        } else {
+            collections_path.join("ansible_collections")
+        };
+        for manifest_path in find_manifest_files(&collections_root) {
+            if let Ok((name, version)) = read_manifest_version(&manifest_path) {
+                collections.push(InstalledCollection {
+                    name,
+                    version,
+                    path: manifest_path
+                        .parent()
+                        .map(|path| path.to_path_buf())
+                        .unwrap_or_default(),
+                    is_bundled: false,
+                });
+            }
+        }
+    }
+    collections
+}
+
+/// Returns collections bundled with an ansible installation.
+///
+/// `ansible.builtin` has the ansible-core version. The `ansible` package bundles further
+/// collections in the `ansible_collections` directory next to the ansible python module.
+pub fn find_bundled_collections(
+    ansible_module_location: &std::path::Path,
+    ansible_core_version: Option<&semver::Version>,
+) -> Vec<InstalledCollection> {
+    let mut collections = Vec::new();
+    if let Some(ansible_core_version) = ansible_core_version {
+        collections.push(InstalledCollection {
+            name: "ansible.builtin".to_string(),
+            version: ansible_core_version.to_string(),
+            path: ansible_module_location.to_path_buf(),
+            is_bundled: true,
+        });
+    }
+    if let Some(site_packages) = ansible_module_location.parent() {
+        collections.extend(
+            find_installed_collections(&[site_packages.join("ansible_collections")])
+                .into_iter()
+                .map(|collection| InstalledCollection {
+                    is_bundled: true,
+                    ..collection
+                }),
+        );
+    }
+    collections
+}
+
+/// Order collections by the precedence of the collections path they are installed in, as
+/// ansible does. Collections outside the collections paths follow, in their existing order.
+pub fn sort_by_precedence(
+    collections: &mut [InstalledCollection],
+    collections_paths: &[std::path::PathBuf],
+) {
+    let collections_roots: Vec<_> = collections_paths
+        .iter()
+        .map(|collections_path| {
+            if collections_path.ends_with("ansible_collections") {
+                collections_path.clone()
+            } else {
+                collections_path.join("ansible_collections")
+            }
+        })
+        .collect();
+    collections.sort_by_key(|collection| {
+        collections_roots
+            .iter()
+            .position(|collections_root| collection.path.starts_with(collections_root))
+            .unwrap_or(collections_roots.len())
+    });
+}
+
+/// Returns the installation of a collection which takes precedence, if installed.
+pub fn find_active_collection<'a>(
+    collections: &'a [InstalledCollection],
+    name: &str,
+) -> Option<&'a InstalledCollection> {
+    collections
+        .iter()
+        .find(|collection| collection.name == name)
+}
+
+/// Returns installed collection versions, by fully qualified collection name.
+///
+/// Where a collection is installed several times, the first installation takes precedence.
+pub fn get_versions(
+    collections: &[InstalledCollection],
+) -> std::collections::HashMap<String, String> {
+    let mut versions = std::collections::HashMap::new();
+    for collection in collections {
+        versions
+            .entry(collection.name.clone())
+            .or_insert(collection.version.clone());
+    }
+    versions
+}
+
+/// Returns `<namespace>/<name>/MANIFEST.json` paths, skipping unreadable directories.
+fn find_manifest_files(collections_root: &std::path::Path) -> Vec<std::path::PathBuf> {
+    let read_directories = |path: &std::path::Path| -> Vec<std::path::PathBuf> {
+        let mut paths: Vec<_> = std::fs::read_dir(path)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect();
+        paths.sort();
+        paths
+    };
+    read_directories(collections_root)
+        .iter()
+        .flat_map(|namespace_path| read_directories(namespace_path))
+        .map(|collection_path| collection_path.join("MANIFEST.json"))
+        .filter(|manifest_path| manifest_path.is_file())
+        .collect()
+}
+
+/// Returns collection name and version from an installed MANIFEST.json file.
+fn read_manifest_version(manifest_path: &std::path::Path) -> Result<(String, String)> {
+    let file = std::fs::File::open(manifest_path)?;
+    let reader = std::io::BufReader::new(file);
+    let manifest: serde_json::Value = serde_json::from_reader(reader).map_err(|error| {
+        error::AnsibleExtensionError::from_manifest_error(manifest_path, &error)
+    })?;
+    let collection_info = &manifest["collection_info"];
+    let get_field = |field: &str| {
+        collection_info[field].as_str().ok_or(format_err!(
+            "Failed to find collection_info.{} in file: {}",
+            field,
+            manifest_path.display()
+        ))
+    };
+    Ok((
+        format!("{}.{}", get_field("namespace")?, get_field("name")?),
+        get_field("version")?.to_string(),
+    ))
+}
+
+/// Returns a key which changes when collections are installed or removed in the collections
+/// paths, or when the ansible installation changes.
+///
+/// Derived from `ansible_installation`, which identifies the installed ansible, and the
+/// modification times of each `ansible_collections` directory and its namespace directories.
+pub fn get_inventory_key(
+    collections_paths: &[std::path::PathBuf],
+    ansible_installation: &str,
+) -> String {
+    let mut hasher = <sha2::Sha256 as sha2::Digest>::new();
+    sha2::Digest::update(&mut hasher, ansible_installation.as_bytes());
+    sha2::Digest::update(&mut hasher, b"\0");
+    let mut update = |path: &std::path::Path| {
+        let modified = std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|modified| modified.as_nanos().to_string())
+            .unwrap_or_default();
+        sha2::Digest::update(&mut hasher, path.to_string_lossy().as_bytes());
+        sha2::Digest::update(&mut hasher, b"\0");
+        sha2::Digest::update(&mut hasher, modified.as_bytes());
+        sha2::Digest::update(&mut hasher, b"\0");
+    };
+    for collections_path in collections_paths {
+        let collections_root = if collections_path.ends_with("ansible_collections") {
+            collections_path.clone()
+        } else {
+            collections_path.join("ansible_collections")
+        };
+        update(&collections_root);
+        let mut namespace_paths: Vec<_> = std::fs::read_dir(&collections_root)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .collect();
+        namespace_paths.sort();
+        for namespace_path in namespace_paths {
+            update(&namespace_path);
+        }
+    }
+    hex::encode(sha2::Digest::finalize(hasher))
+}
+
+/// Installed collections stored on disk.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct CachedInventory {
+    key: String,
+
+    /// Unix time in seconds at which the inventory was stored.
+    stored_at: u64,
+    collections: Vec<InstalledCollection>,
+}
+
+/// Returns installed collections stored under `key` less than `ttl` ago, if any.
+///
+/// Unreadable entries are treated as missing.
+pub fn read_cached_inventory(
+    directory: &std::path::Path,
+    key: &str,
+    ttl: std::time::Duration,
+) -> Option<Vec<InstalledCollection>> {
+    let content = std::fs::read_to_string(directory.join(format!("{}.json", key))).ok()?;
+    let inventory: CachedInventory = serde_json::from_str(&content).ok()?;
+    if inventory.key != key
+        || super::cache::get_unix_time().saturating_sub(inventory.stored_at) >= ttl.as_secs()
+    {
+        return None;
+    }
+    Some(inventory.collections)
+}
+
+/// Store installed collections under `key`.
+pub fn write_cached_inventory(
+    directory: &std::path::Path,
+    key: &str,
+    collections: &[InstalledCollection],
+) -> Result<()> {
+    std::fs::create_dir_all(directory)?;
+    let inventory = CachedInventory {
+        key: key.to_string(),
+        stored_at: super::cache::get_unix_time(),
+        collections: collections.to_vec(),
+    };
+    // Write then rename so concurrent readers never see a partial entry.
+    let path = directory.join(format!("{}.json", key));
+    let temporary_path = path.with_extension(format!("{}.tmp", std::process::id()));
+    std::fs::write(&temporary_path, serde_json::to_string(&inventory)?)?;
+    std::fs::rename(&temporary_path, &path)?;
+    Ok(())
+}
+
+/// Installed versions, by name, loaded on first use.
+///
+/// Finding installed collections can take several seconds, so projects whose requirements do
+/// not need installed versions, such as exactly pinned projects, skip it.
+pub struct InstalledVersions<'a> {
+    versions:
+        std::cell::OnceCell<std::result::Result<std::collections::HashMap<String, String>, String>>,
+    load: Box<dyn Fn() -> Result<std::collections::HashMap<String, String>> + 'a>,
+}
+
+impl<'a> InstalledVersions<'a> {
+    pub fn new<F: Fn() -> Result<std::collections::HashMap<String, String>> + 'a>(load: F) -> Self {
+        Self {
+            versions: std::cell::OnceCell::new(),
+            load: Box::new(load),
+        }
+    }
+
+    /// Returns the installed version, loading installed versions if not yet loaded.
+    pub fn get(&self, name: &str) -> Result<Option<&str>> {
+        let versions = self
+            .versions
+            .get_or_init(|| (self.load)().map_err(|error| format!("{:#}", error)));
+        match versions {
+            Ok(versions) => Ok(versions.get(name).map(|version| version.as_str())),
+            Err(error) => Err(format_err!("Failed to find installed versions: {}", error)),
+        }
+    }
+
+    /// Returns true if installed versions have been loaded.
+    pub fn is_loaded(&self) -> bool {
+        self.versions.get().is_some()
+    }
+}
+
+impl From<std::collections::HashMap<String, String>> for InstalledVersions<'_> {
+    fn from(versions: std::collections::HashMap<String, String>) -> Self {
+        Self {
+            versions: std::cell::OnceCell::from(Ok(versions)),
+            load: Box::new(|| Ok(std::collections::HashMap::new())),
+        }
+    }
+}
+
+/// Installed standalone role.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct InstalledRole {
+    /// Role name, as installed by ansible-galaxy. For example: `geerlingguy.docker`
+    pub name: String,
+    pub version: String,
+
+    /// Role directory.
+    pub path: std::path::PathBuf,
+}
+
+/// Returns standalone roles installed by ansible-galaxy in roles path order.
+///
+/// Roles are recognised by the `meta/.galaxy_install_info` file which ansible-galaxy writes
+/// on install. Roles without this file, such as project roles, have no version and are skipped.
+pub fn find_installed_roles(roles_paths: &[std::path::PathBuf]) -> Vec<InstalledRole> {
+    let mut roles = Vec::new();
+    for roles_path in roles_paths {
+        let mut role_paths: Vec<_> = std::fs::read_dir(roles_path)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect();
+        role_paths.sort();
+        for role_path in role_paths {
+            let name = match role_path.file_name().and_then(|name| name.to_str()) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+            if let Ok(version) = read_role_install_version(&role_path) {
+                roles.push(InstalledRole {
+                    name,
+                    version,
+                    path: role_path,
+                });
+            }
+        }
+    }
+    roles
+}
+
+/// Returns the installed version from a role's `meta/.galaxy_install_info` file.
+fn read_role_install_version(role_path: &std::path::Path) -> Result<String> {
+    let install_info_path = role_path.join("meta").join(".galaxy_install_info");
+    let file = std::fs::File::open(&install_info_path)?;
+    let install_info: serde_json::Value = serde_yaml::from_reader(file)?;
+    match &install_info["version"] {
+        serde_json::Value::String(version) if !version.is_empty() => Ok(version.clone()),
+        serde_json::Value::Number(version) => Ok(version.to_string()),
+        _ => Err(format_err!(
+            "Failed to find version in file: {}",
+            install_info_path.display()
+        )),
+    }
+}
+
+/// Returns installed role versions, by role name. The first installation takes precedence.
+pub fn get_role_versions(roles: &[InstalledRole]) -> std::collections::HashMap<String, String> {
+    let mut versions = std::collections::HashMap::new();
+    for role in roles {
+        versions
+            .entry(role.name.clone())
+            .or_insert(role.version.clone());
+    }
+    versions
+}
+
+#[test]
+fn test_find_installed_collections() -> Result<()> {
+    let directory = std::env::temp_dir().join(format!(
+        "vouch-ansible-installed-test-{}",
+        std::process::id()
+    ));
+    let write_manifest = |collections_path: &str, name: &str, version: &str| -> Result<()> {
+        let (namespace, collection) = name.split_once('.').unwrap_or((name, ""));
+        let collection_directory = directory
+            .join(collections_path)
+            .join("ansible_collections")
+            .join(namespace)
+            .join(collection);
+        std::fs::create_dir_all(&collection_directory)?;
+        let manifest = serde_json::json!({
+            "collection_info": {"namespace": namespace, "name": collection, "version": version}
+        });
+        std::fs::write(
+            collection_directory.join("MANIFEST.json"),
+            manifest.to_string(),
+        )?;
+        Ok(())
+    };
+    write_manifest("user", "community.general", "7.0.0")?;
+    write_manifest("system", "community.general", "6.0.0")?;
+    write_manifest("system", "ansible.posix", "1.5.4")?;
+
+    let collections = get_versions(&find_installed_collections(&[
+        directory.join("user"),
+        directory.join("system").join("ansible_collections"),
+        directory.join("missing"),
+    ]));
+    assert_eq!(
+        collections,
+        maplit::hashmap! {
+            "community.general".to_string() => "7.0.0".to_string(),
+            "ansible.posix".to_string() => "1.5.4".to_string(),
+        }
+    );
+
+    // Project collections take precedence over installed collections.
+    write_manifest("project/collections", "community.general", "8.0.0")?;
+    let collections = get_versions(&find_installed_collections(&get_collections_paths(
+        &directory.join("project"),
+    )));
+    assert_eq!(
+        collections.get("community.general"),
+        Some(&"8.0.0".to_string())
+    );
+
+    let mut collections = find_installed_collections(&[
+        directory.join("system"),
+        directory.join("project").join("collections"),
+    ]);
+    sort_by_precedence(
+        &mut collections,
+        &get_collections_paths(&directory.join("project")),
+    );
+    assert_eq!(
+        find_active_collection(&collections, "community.general")
+            .map(|collection| &collection.path),
+        Some(&directory.join("project/collections/ansible_collections/community/general"))
+    );
+
+    write_manifest("site-packages", "community.docker", "3.4.0")?;
+    let collections = find_bundled_collections(
+        &directory.join("site-packages").join("ansible"),
+        Some(&semver::Version::new(2, 15, 3)),
+    );
+    assert_eq!(
+        collections
+            .iter()
+            .map(|collection| (
+                collection.name.as_str(),
+                collection.version.as_str(),
+                collection.is_bundled
+            ))
+            .collect::<Vec<_>>(),
+        vec![
+            ("ansible.builtin", "2.15.3", true),
+            ("community.docker", "3.4.0", true),
+        ]
+    );
+
+    std::fs::remove_dir_all(&directory)?;
+    Ok(())
+}
+
+#[test]
+fn test_find_installed_roles() -> Result<()> {
+    let directory = std::env::temp_dir().join(format!(
+        "vouch-ansible-installed-roles-test-{}",
+        std::process::id()
+    ));
+    let write_role = |roles_path: &str, name: &str, install_info: Option<&str>| -> Result<()> {
+        let meta_directory = directory.join(roles_path).join(name).join("meta");
+        std::fs::create_dir_all(&meta_directory)?;
+        if let Some(install_info) = install_info {
+            std::fs::write(meta_directory.join(".galaxy_install_info"), install_info)?;
+        }
+        Ok(())
+    };
+    write_role(
+        "project/roles",
+        "geerlingguy.docker",
+        Some("install_date: 'Mon Oct  2 10:00:00 2023'\nversion: 6.1.0\n"),
+    )?;
+    write_role("project/roles", "webserver", None)?;
+    write_role(
+        "user",
+        "geerlingguy.docker",
+        Some("install_date: 'Mon Oct  2 10:00:00 2023'\nversion: 5.0.0\n"),
+    )?;
+
+    let roles = find_installed_roles(&[directory.join("project/roles"), directory.join("user")]);
+    assert_eq!(
+        get_role_versions(&roles),
+        maplit::hashmap! {
+            "geerlingguy.docker".to_string() => "6.1.0".to_string(),
+        }
+    );
+
+    std::fs::remove_dir_all(&directory)?;
+    Ok(())
+}
+
+#[test]
+fn test_inventory_cache() -> Result<()> {
+    let directory = std::env::temp_dir().join(format!(
+        "vouch-ansible-inventory-test-{}",
+        std::process::id()
+    ));
+    let collections_path = directory.join("collections");
+    let ansible_installation = "ansible [core 2.14.3]";
+    let key = get_inventory_key(
+        std::slice::from_ref(&collections_path),
+        ansible_installation,
+    );
+    assert_eq!(
+        key,
+        get_inventory_key(
+            std::slice::from_ref(&collections_path),
+            ansible_installation
+        )
+    );
+
+    std::fs::create_dir_all(collections_path.join("ansible_collections").join("ansible"))?;
+    let key = get_inventory_key(
+        std::slice::from_ref(&collections_path),
+        ansible_installation,
+    );
+    assert_ne!(key, get_inventory_key(&[], ansible_installation));
+    assert_ne!(
+        key,
+        get_inventory_key(
+            std::slice::from_ref(&collections_path),
+            "ansible [core 2.15.0]"
+        )
+    );
+
+    let collections = vec![InstalledCollection {
+        name: "ansible.posix".to_string(),
+        version: "1.5.4".to_string(),
+        path: collections_path.join("ansible_collections/ansible/posix"),
+        is_bundled: false,
+    }];
+    let cache_directory = directory.join("cache");
+    let ttl = std::time::Duration::from_secs(60);
+    assert_eq!(read_cached_inventory(&cache_directory, &key, ttl), None);
+    write_cached_inventory(&cache_directory, &key, &collections)?;
+    assert_eq!(
+        read_cached_inventory(&cache_directory, &key, ttl),
+        Some(collections)
+    );
+    assert_eq!(
+        read_cached_inventory(&cache_directory, &key, std::time::Duration::ZERO),
+        None
+    );
+
+    std::fs::remove_dir_all(&directory)?;
+    Ok(())
+}
+
+#[test]
+fn test_installed_versions() -> Result<()> {
+    let load_count = std::cell::Cell::new(0);
+    let versions = InstalledVersions::new(|| {
+        load_count.set(load_count.get() + 1);
+        Ok(maplit::hashmap! {
+            "ansible.posix".to_string() => "1.5.4".to_string(),
+        })
+    });
+    assert!(!versions.is_loaded());
+    assert_eq!(versions.get("ansible.posix")?, Some("1.5.4"));
+    assert_eq!(versions.get("community.general")?, None);
+    assert_eq!(load_count.get(), 1);
+
+    let versions = InstalledVersions::new(|| Err(format_err!("ansible-galaxy timed out")));
+    assert!(versions.get("ansible.posix").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_get_config_paths() -> Result<()> {
+    let config = super::config::AnsibleConfig::parse(
+        "[defaults]\ncollections_path = ./collections:/opt/ansible/collections\n",
+    )?;
+    assert_eq!(
+        get_config_paths(
+            &config,
+            std::path::Path::new("/project"),
+            &COLLECTIONS_PATH_SETTING
+        ),
+        Some(vec![
+            std::path::PathBuf::from("/project/./collections"),
+            std::path::PathBuf::from("/opt/ansible/collections"),
+        ])
+    );
+    assert_eq!(
+        get_config_paths(
+            &config,
+            std::path::Path::new("/project"),
+            &ROLES_PATH_SETTING
+        ),
+        None
+    );
+    Ok(())
+}
+
+#[test]
+fn test_parse_search_paths() {
+    assert_eq!(
+        parse_separated_search_paths(r"C:\ansible\collections;D:\collections", ';'),
+        vec![
+            std::path::PathBuf::from(r"C:\ansible\collections"),
+            std::path::PathBuf::from(r"D:\collections"),
+        ]
+    );
+    assert_eq!(
+        parse_separated_search_paths("/opt/collections: /usr/share/ansible/collections:", ':'),
+        vec![
+            std::path::PathBuf::from("/opt/collections"),
+            std::path::PathBuf::from("/usr/share/ansible/collections"),
+        ]
+    );
+}
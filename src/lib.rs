@@ -1,198 +1,2391 @@
 use anyhow::{format_err, Context, Result};
-use std::io::Read;
 use strum::IntoEnumIterator;
 
+pub mod advisory;
+pub mod artifact;
+mod auth;
+mod cache;
+pub mod changelog;
+mod circuit;
+pub mod config;
+pub mod conflict;
+pub mod diagnostic;
+pub mod error;
+pub mod fqcn;
 mod galaxy;
+mod git;
+pub mod graph;
+mod http;
+pub mod installed;
+mod local;
+pub mod lockfile;
+pub mod metadata;
+#[cfg(feature = "async")]
+pub mod nonblocking;
+mod oci;
+mod parallel;
+pub mod registry;
+mod resolution;
+mod roles;
+mod scheduler;
+pub mod search;
+pub mod signature;
+mod tarball;
+
+pub use galaxy::normalize_version;
 
 #[derive(Clone, Debug)]
 pub struct AnsibleExtension {
     name_: String,
-    registry_host_names_: Vec<String>,
-    root_url_: url::Url,
-    registry_human_url_template_: String,
+    /// Configured registries, or the reason they could not be configured.
+    registries_: std::result::Result<Vec<registry::Registry>, String>,
+    max_version_pages_: usize,
+    max_artifact_size_: u64,
+    http_config_: http::HttpConfig,
+    include_prereleases_: bool,
+    check_requires_ansible_: bool,
+    /// Set once a call is given the `--trace` extension argument. Shared by all clones.
+    trace_arg_: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    ansible_core_version_: std::sync::Arc<std::sync::OnceLock<Option<semver::Version>>>,
+    client_: std::sync::Arc<std::sync::Mutex<Option<http::Client>>>,
+    responses_:
+        std::sync::Arc<std::sync::Mutex<std::collections::HashMap<String, serde_json::Value>>>,
+    installed_collections_: std::sync::Arc<
+        std::sync::Mutex<std::collections::HashMap<String, Vec<installed::InstalledCollection>>>,
+    >,
 }
 
 impl vouch_lib::extension::FromLib for AnsibleExtension {
     fn new() -> Self {
+        Self::from_project_directory(&std::env::current_dir().unwrap_or_default())
+    }
+}
+
+impl AnsibleExtension {
+    /// Initialize extension with the registries configured for a project directory, which
+    /// selects the ansible.cfg Galaxy servers. See `get_registries`.
+    pub fn from_project_directory(project_directory: &std::path::Path) -> Self {
         Self {
             name_: "ansible".to_string(),
-            registry_host_names_: vec!["galaxy.ansible.com".to_owned()],
-            root_url_: url::Url::parse("https://galaxy.ansible.com").unwrap(),
-            registry_human_url_template_: "https://galaxy.ansible.com/{{package_name}}".to_string(),
+            registries_: get_registries(project_directory).map_err(|error| format!("{:#}", error)),
+            max_version_pages_: get_max_version_pages(),
+            max_artifact_size_: get_max_artifact_size(),
+            http_config_: http::HttpConfig::from_env(),
+            include_prereleases_: resolution::get_include_prereleases(&[]),
+            check_requires_ansible_: resolution::get_check_requires_ansible(&[]),
+            trace_arg_: Default::default(),
+            ansible_core_version_: Default::default(),
+            client_: Default::default(),
+            responses_: Default::default(),
+            installed_collections_: Default::default(),
+        }
+    }
+
+    /// Initialize extension with the given registries, highest priority first.
+    pub fn from_registries(registries: Vec<registry::Registry>) -> Result<Self> {
+        if registries.is_empty() {
+            return Err(format_err!("At least one registry must be given."));
+        }
+        Ok(Self {
+            name_: "ansible".to_string(),
+            registries_: Ok(registries),
+            max_version_pages_: get_max_version_pages(),
+            max_artifact_size_: get_max_artifact_size(),
+            http_config_: http::HttpConfig::from_env(),
+            include_prereleases_: resolution::get_include_prereleases(&[]),
+            check_requires_ansible_: resolution::get_check_requires_ansible(&[]),
+            trace_arg_: Default::default(),
+            ansible_core_version_: Default::default(),
+            client_: Default::default(),
+            responses_: Default::default(),
+            installed_collections_: Default::default(),
+        })
+    }
+
+    /// Enable or disable tracing of registry requests to stderr.
+    ///
+    /// Overrides the VOUCH_ANSIBLE_TRACE environment variable. The `--trace` extension argument
+    /// also enables tracing, from the call it is given to onwards.
+    pub fn set_trace(&mut self, trace: bool) {
+        self.http_config_.trace = trace;
+        self.client_ = Default::default();
+    }
+
+    /// Consider prereleases when selecting latest versions and resolving requirements.
+    ///
+    /// Overrides the VOUCH_ANSIBLE_INCLUDE_PRERELEASES environment variable.
+    pub fn set_include_prereleases(&mut self, include_prereleases: bool) {
+        self.include_prereleases_ = include_prereleases;
+    }
+
+    /// Set the maximum size in bytes of artifacts fetched by `download_artifact`.
+    ///
+    /// Overrides the VOUCH_ANSIBLE_MAX_ARTIFACT_BYTES environment variable.
+    pub fn set_max_artifact_size(&mut self, max_artifact_size: u64) {
+        self.max_artifact_size_ = max_artifact_size;
+    }
+
+    /// Skip collection versions whose `requires_ansible` metadata excludes the installed
+    /// ansible-core version, as ansible-galaxy does when installing.
+    ///
+    /// Overrides the VOUCH_ANSIBLE_CHECK_REQUIRES_ANSIBLE environment variable.
+    pub fn set_check_requires_ansible(&mut self, check_requires_ansible: bool) {
+        self.check_requires_ansible_ = check_requires_ansible;
+    }
+
+    /// Returns the installed ansible-core version if versions are checked against it.
+    ///
+    /// The version is looked up once and shared by all clones. If it cannot be found, a
+    /// warning is printed and versions are not checked.
+    fn get_ansible_core_version(&self, extension_args: &[String]) -> Option<semver::Version> {
+        if !self.check_requires_ansible_ && !resolution::get_check_requires_ansible(extension_args)
+        {
+            return None;
+        }
+        self.ansible_core_version_
+            .get_or_init(|| match galaxy::get_ansible_core_version() {
+                Ok(Some(ansible_core_version)) => Some(ansible_core_version),
+                Ok(None) => {
+                    eprintln!(
+                        "vouch-ansible: warning: ansible not found, \
+                         continuing without checking requires_ansible"
+                    );
+                    None
+                }
+                Err(error) => {
+                    eprintln!(
+                        "vouch-ansible: warning: failed to find ansible-core version, \
+                         continuing without checking requires_ansible: {:#}",
+                        error
+                    );
+                    None
+                }
+            })
+            .clone()
+    }
+
+    /// Returns collections installed for a project, cached for the lifetime of the extension.
+    ///
+    /// Entries are keyed by the ansible installation, the project and bundled collections
+    /// paths and their modification times, so projects which share collections paths share
+    /// one lookup. With
+    /// VOUCH_ANSIBLE_CACHE_INSTALLED set, entries are also stored in the `installed`
+    /// subdirectory of the response cache directory, for the response cache lifetime.
+    fn get_installed_collections(
+        &self,
+        working_directory: &std::path::Path,
+    ) -> Result<Vec<installed::InstalledCollection>> {
+        // Bundled collections are skipped if ansible cannot be run.
+        let ansible_version_output = galaxy::get_ansible_version_output().ok().flatten();
+        let mut inventory_paths = installed::get_collections_paths(working_directory);
+        inventory_paths.extend(
+            ansible_version_output
+                .as_deref()
+                .and_then(galaxy::get_bundled_collections_path),
+        );
+        let key = installed::get_inventory_key(
+            &inventory_paths,
+            &galaxy::get_ansible_installation_id(ansible_version_output.as_deref()),
+        );
+        let lock_installed_collections = || {
+            self.installed_collections_
+                .lock()
+                .map_err(|_| format_err!("Installed collections lock poisoned."))
+        };
+        if let Some(collections) = lock_installed_collections()?.get(&key) {
+            return Ok(collections.clone());
+        }
+
+        let cache_directory = std::env::var("VOUCH_ANSIBLE_CACHE_INSTALLED")
+            .ok()
+            .and_then(|value| config::parse_bool(&value))
+            .unwrap_or(false)
+            .then(|| self.http_config_.cache_directory.clone())
+            .flatten()
+            .map(|directory| directory.join("installed"));
+        let cached_collections = cache_directory.as_ref().and_then(|directory| {
+            installed::read_cached_inventory(directory, &key, self.http_config_.cache_policy.ttl)
+        });
+        let collections = match cached_collections {
+            Some(collections) => collections,
+            None => {
+                let collections = galaxy::get_installed_collections(
+                    working_directory,
+                    ansible_version_output.as_deref(),
+                )?;
+                if let Some(directory) = &cache_directory {
+                    let _ = installed::write_cached_inventory(directory, &key, &collections);
+                }
+                collections
+            }
+        };
+        lock_installed_collections()?.insert(key, collections.clone());
+        Ok(collections)
+    }
+
+    /// Returns configured registries, highest priority first.
+    ///
+    /// Fails if the registry environment variables are invalid.
+    fn configured_registries(&self) -> Result<&[registry::Registry]> {
+        self.registries_
+            .as_deref()
+            .map_err(|error| format_err!("Invalid registry configuration: {}", error))
+    }
+
+    /// Returns the shared HTTP client, built on first use.
+    ///
+    /// Clones share one connection pool, so repeated lookups reuse connections.
+    fn client(&self) -> Result<http::Client> {
+        let mut shared_client = self
+            .client_
+            .lock()
+            .map_err(|_| format_err!("HTTP client lock poisoned."))?;
+        let mut client = match shared_client.as_ref() {
+            Some(client) => client.clone(),
+            None => {
+                let client = http::build_client(&self.http_config_)?;
+                *shared_client = Some(client.clone());
+                client
+            }
+        };
+        if self.is_trace_enabled() {
+            client.enable_trace();
+        }
+        Ok(client)
+    }
+
+    fn is_trace_enabled(&self) -> bool {
+        self.http_config_.trace || self.trace_arg_.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Returns registry JSON response, memoized by URL for the lifetime of the extension.
+    ///
+    /// Only successful responses are memoized.
+    fn get_memoized_json(
+        &self,
+        registry: &registry::Registry,
+        url: &url::Url,
+    ) -> Result<serde_json::Value> {
+        if let Some(json) = self.get_memoized_response(url)? {
+            return Ok(json);
         }
+        // The lock is not held during the request so concurrent lookups are not serialized.
+        let json = http::get_json(&self.client()?, registry, url.as_str())?;
+        self.memoize_response(url, &json)?;
+        Ok(json)
+    }
+
+    /// Returns memoized registry JSON response, see `get_memoized_json`.
+    fn get_memoized_response(&self, url: &url::Url) -> Result<Option<serde_json::Value>> {
+        let responses = self
+            .responses_
+            .lock()
+            .map_err(|_| format_err!("Response memo lock poisoned."))?;
+        let json = responses.get(url.as_str()).cloned();
+        if json.is_some() {
+            http::trace_request(self.is_trace_enabled(), url.as_str(), "-", "memo", None);
+        }
+        Ok(json)
+    }
+
+    fn memoize_response(&self, url: &url::Url, json: &serde_json::Value) -> Result<()> {
+        self.responses_
+            .lock()
+            .map_err(|_| format_err!("Response memo lock poisoned."))?
+            .insert(url.to_string(), json.clone());
+        Ok(())
     }
 }
 
-impl vouch_lib::extension::Extension for AnsibleExtension {
-    fn name(&self) -> String {
-        self.name_.clone()
+impl AnsibleExtension {
+    /// Search all registries for collections matching the query.
+    pub fn search_collections(&self, query: &str) -> Result<Vec<search::SearchResult>> {
+        let client = self.client()?;
+        let mut results = Vec::new();
+        let mut errors = Vec::new();
+        for registry in self
+            .configured_registries()?
+            .iter()
+            .filter(|r| !r.is_local())
+        {
+            match search::search_registry(&client, registry, query) {
+                Ok(registry_results) => results.extend(registry_results),
+                Err(error) => errors.push(format!("{}: {}", registry.host_name, error)),
+            }
+        }
+        if results.is_empty() && !errors.is_empty() {
+            return Err(format_err!(
+                "Failed to search registries.\n{}",
+                errors.join("\n")
+            ));
+        }
+        Ok(results)
     }
 
-    fn registries(&self) -> Vec<String> {
-        self.registry_host_names_.clone()
+    /// Resolve registry metadata for many `(name, version)` pairs concurrently.
+    ///
+    /// At most VOUCH_ANSIBLE_MAX_PARALLELISM (default 8) lookups run at once.
+    /// Returns one result per package, in the given order.
+    pub fn batch_registries_package_metadata(
+        &self,
+        packages: &[(&str, Option<&str>)],
+    ) -> Vec<Result<Vec<vouch_lib::extension::RegistryPackageMetadata>>> {
+        parallel::map_bounded(
+            packages,
+            parallel::get_max_parallelism(),
+            |(package_name, package_version)| {
+                vouch_lib::extension::Extension::registries_package_metadata(
+                    self,
+                    package_name,
+                    package_version,
+                )
+            },
+        )
     }
 
-    /// Returns a list of dependencies for the given package.
+    /// Returns the highest version satisfying the requirement from the highest priority
+    /// registry which has the package. Git, OCI and tarball sources are not resolved.
+    fn resolve_registry_version(
+        &self,
+        package_name: &str,
+        version_requirement: &resolution::VersionRequirement,
+        include_prereleases: bool,
+        ansible_core_version: Option<&semver::Version>,
+    ) -> Option<String> {
+        if fqcn::check_package_name(package_name).is_err() {
+            return None;
+        }
+        self.configured_registries()
+            .ok()?
+            .iter()
+            .find_map(|registry| {
+                let is_wanted = |version: &semver::Version| {
+                    version_requirement.matches(version, include_prereleases)
+                };
+                let versions = get_registry_versions(
+                    self,
+                    registry,
+                    package_name,
+                    ansible_core_version,
+                    Some(&is_wanted),
+                )
+                .ok()?;
+                let parsed_versions: Vec<semver::Version> = versions
+                    .iter()
+                    .map(|(version, _)| version.clone())
+                    .collect();
+                let version = resolution::select_matching_version(
+                    &parsed_versions,
+                    version_requirement,
+                    include_prereleases,
+                )?;
+                versions
+                    .into_iter()
+                    .find(|(parsed_version, _)| parsed_version == &version)
+                    .map(|(_, published_version)| published_version)
+            })
+    }
+
+    /// Returns the latest stable version of a collection from the highest priority registry
+    /// which has the collection.
+    pub fn latest_stable_version(&self, package_name: &str) -> Result<String> {
+        self.find_latest_version(package_name, false)
+    }
+
+    /// Returns the latest version of a collection, which may be a prerelease, from the highest
+    /// priority registry which has the collection.
+    pub fn latest_version_including_prerelease(&self, package_name: &str) -> Result<String> {
+        self.find_latest_version(package_name, true)
+    }
+
+    fn find_latest_version(&self, package_name: &str, include_prereleases: bool) -> Result<String> {
+        fqcn::check_package_name(package_name)?;
+        let mut errors = Vec::new();
+        for registry in self.configured_registries()?.iter() {
+            let latest_version = if registry.is_local() {
+                get_registry_versions(self, registry, package_name, None, None).map(|versions| {
+                    versions
+                        .into_iter()
+                        .filter(|(version, _)| {
+                            resolution::is_selectable(version, include_prereleases)
+                        })
+                        .max_by(|(a, _), (b, _)| resolution::compare_versions(a, b))
+                        .map(|(_, published_version)| published_version)
+                })
+            } else {
+                get_latest_version(
+                    self,
+                    registry,
+                    package_name,
+                    include_prereleases,
+                    self.get_ansible_core_version(&[]).as_ref(),
+                )
+            };
+            match latest_version {
+                Ok(Some(latest_version)) => return Ok(latest_version),
+                Ok(None) => errors.push((
+                    registry.host_name.clone(),
+                    format_err!("No versions found."),
+                )),
+                Err(error) => errors.push((registry.host_name.clone(), error)),
+            }
+        }
+        Err(get_registries_error(
+            format!("Failed to find package {} in any registry.", package_name),
+            errors,
+        ))
+    }
+
+    /// Returns all versions of a collection, newest first, from the highest priority registry
+    /// which has the collection. Prereleases are included and flagged.
     ///
-    /// Returns one package dependencies structure per registry.
-    fn identify_package_dependencies(
+    /// Versions are read from every versions endpoint page, up to the configured page limit.
+    /// Versions which cannot be coerced into semver are skipped.
+    pub fn get_all_versions(&self, package_name: &str) -> Result<Vec<metadata::CollectionVersion>> {
+        fqcn::check_package_name(package_name)?;
+        let mut errors = Vec::new();
+        for registry in self.configured_registries()?.iter() {
+            match get_registry_versions(self, registry, package_name, None, None) {
+                Ok(mut versions) if !versions.is_empty() => {
+                    versions.sort_by(|(a, _), (b, _)| resolution::compare_versions(b, a));
+                    return Ok(versions
+                        .into_iter()
+                        .map(|(version, published_version)| metadata::CollectionVersion {
+                            version: published_version,
+                            is_prerelease: !version.pre.is_empty(),
+                            registry_host_name: registry.host_name.clone(),
+                        })
+                        .collect());
+                }
+                Ok(_) => errors.push((
+                    registry.host_name.clone(),
+                    format_err!("No versions found."),
+                )),
+                Err(error) => errors.push((registry.host_name.clone(), error)),
+            }
+        }
+        Err(get_registries_error(
+            format!("Failed to find package {} in any registry.", package_name),
+            errors,
+        ))
+    }
+
+    /// Returns supplementary collection metadata, such as deprecation status,
+    /// from the highest priority registry which has the collection.
+    pub fn collection_metadata(&self, package_name: &str) -> Result<metadata::CollectionMetadata> {
+        fqcn::check_package_name(package_name)?;
+        let mut errors = Vec::new();
+        for registry in self
+            .configured_registries()?
+            .iter()
+            .filter(|r| !r.is_local())
+        {
+            match get_registry_collection_json(self, registry, package_name) {
+                Ok(collection_json) => {
+                    return metadata::parse_collection_metadata(
+                        &collection_json,
+                        package_name,
+                        &registry.host_name,
+                    )
+                }
+                Err(error) => errors.push((registry.host_name.clone(), error)),
+            }
+        }
+        Err(get_registries_error(
+            format!("Failed to find package {} in any registry.", package_name),
+            errors,
+        ))
+    }
+
+    /// Returns publish timestamps, the artifact sha256 digest, signatures and licenses for the
+    /// given collection version, or the latest version, from the highest priority registry
+    /// which has the collection.
+    pub fn version_metadata(
         &self,
-        _package_name: &str,
-        _package_version: &Option<&str>,
-        _extension_args: &Vec<String>,
-    ) -> Result<Vec<vouch_lib::extension::PackageDependencies>> {
-        Err(format_err!("Function unimplemented."))
+        package_name: &str,
+        package_version: &Option<&str>,
+    ) -> Result<metadata::VersionMetadata> {
+        fqcn::check_package_name(package_name)?;
+        let mut errors = Vec::new();
+        for registry in self
+            .configured_registries()?
+            .iter()
+            .filter(|r| !r.is_local())
+        {
+            let version_metadata = match package_version {
+                Some(package_version) => Ok(package_version.to_string()),
+                None => get_latest_version(
+                    self,
+                    registry,
+                    package_name,
+                    self.include_prereleases_,
+                    self.get_ansible_core_version(&[]).as_ref(),
+                )
+                .and_then(|version| version.ok_or(format_err!("Failed to find latest version."))),
+            }
+            .and_then(|package_version| {
+                let entry_json =
+                    get_registry_entry_json(self, registry, package_name, &package_version)?;
+                Ok(metadata::parse_version_metadata(
+                    &entry_json,
+                    package_name,
+                    &package_version,
+                    &registry.host_name,
+                ))
+            });
+            match version_metadata {
+                Ok(version_metadata) => return Ok(version_metadata),
+                Err(error) => errors.push((registry.host_name.clone(), error)),
+            }
+        }
+        Err(get_registries_error(
+            format!("Failed to find package {} in any registry.", package_name),
+            errors,
+        ))
     }
 
-    fn identify_file_defined_dependencies(
+    /// Returns documentation links for the given collection version, or the latest version.
+    ///
+    /// The v3 docs-blob, which holds the rendered documentation of every plugin and module,
+    /// is fetched only if `include_docs_blob` is set as it can be several megabytes.
+    pub fn documentation_metadata(
         &self,
-        working_directory: &std::path::PathBuf,
-        _extension_args: &Vec<String>,
+        package_name: &str,
+        package_version: &Option<&str>,
+        include_docs_blob: bool,
+    ) -> Result<metadata::DocumentationMetadata> {
+        fqcn::check_package_name(package_name)?;
+        let mut errors = Vec::new();
+        for registry in self
+            .configured_registries()?
+            .iter()
+            .filter(|r| !r.is_local())
+        {
+            match get_registry_documentation_metadata(
+                self,
+                registry,
+                package_name,
+                package_version,
+                include_docs_blob,
+            ) {
+                Ok(documentation_metadata) => return Ok(documentation_metadata),
+                Err(error) => errors.push((registry.host_name.clone(), error)),
+            }
+        }
+        Err(get_registries_error(
+            format!("Failed to find package {} in any registry.", package_name),
+            errors,
+        ))
+    }
+
+    /// Downloads the given collection version, or the latest version, to `path`.
+    ///
+    /// The artifact is taken from the highest priority registry which has the collection and
+    /// checked against the sha256 digest advertised by that registry. A digest mismatch
+    /// downcasts to `artifact::ChecksumMismatchError`. Returns the verified version metadata.
+    ///
+    /// Artifacts larger than `set_max_artifact_size` are refused, before downloading if the
+    /// registry reports the artifact size.
+    pub fn download_artifact(
+        &self,
+        package_name: &str,
+        package_version: &Option<&str>,
+        path: &std::path::Path,
+    ) -> Result<metadata::VersionMetadata> {
+        fqcn::check_package_name(package_name)?;
+        let mut errors = Vec::new();
+        for registry in self
+            .configured_registries()?
+            .iter()
+            .filter(|r| !r.is_local())
+        {
+            let release = match package_version {
+                Some(package_version) => Ok(package_version.to_string()),
+                None => get_latest_version(
+                    self,
+                    registry,
+                    package_name,
+                    self.include_prereleases_,
+                    self.get_ansible_core_version(&[]).as_ref(),
+                )
+                .and_then(|version| version.ok_or(format_err!("Failed to find latest version."))),
+            }
+            .and_then(|package_version| {
+                let entry_json =
+                    get_registry_entry_json(self, registry, package_name, &package_version)?;
+                let artifact_url =
+                    get_entry_artifact_url(&entry_json, registry, package_name, &package_version)?;
+                let version_metadata = metadata::parse_version_metadata(
+                    &entry_json,
+                    package_name,
+                    &package_version,
+                    &registry.host_name,
+                );
+                Ok((artifact_url, version_metadata))
+            });
+            let (artifact_url, version_metadata) = match release {
+                Ok(release) => release,
+                Err(error) => {
+                    errors.push((registry.host_name.clone(), error));
+                    continue;
+                }
+            };
+            let sha256 = version_metadata.sha256.as_ref().ok_or(format_err!(
+                "Registry {} does not advertise a sha256 digest for {} version {}.",
+                registry.host_name,
+                package_name,
+                version_metadata.package_version
+            ))?;
+            if let Some(artifact_size) = version_metadata
+                .artifact_size
+                .filter(|size| *size > self.max_artifact_size_)
+            {
+                return Err(format_err!(
+                    "Artifact of {} version {} is {} bytes, exceeding the {} byte limit: {}",
+                    package_name,
+                    version_metadata.package_version,
+                    artifact_size,
+                    self.max_artifact_size_,
+                    artifact_url
+                ));
+            }
+            let client = self.client()?;
+            artifact::download_artifact(
+                &client,
+                registry,
+                &artifact_url,
+                sha256,
+                self.max_artifact_size_,
+                path,
+            )?;
+            return Ok(version_metadata);
+        }
+        Err(get_registries_error(
+            format!("Failed to find package {} in any registry.", package_name),
+            errors,
+        ))
+    }
+
+    /// Compares the files of a published collection version, or the latest version, with the
+    /// matching tag of the source repository given in its galaxy.yml.
+    ///
+    /// The artifact is fetched as by `download_artifact`, into a private temporary directory.
+    /// The tag archive is fetched from the git host, see `git::GitSource::archive_url`, and is
+    /// subject to the same size limit.
+    pub fn provenance_check(
+        &self,
+        package_name: &str,
+        package_version: &Option<&str>,
+    ) -> Result<artifact::ProvenanceReport> {
+        let (version_metadata, artifact_digests) =
+            self.read_downloaded_artifact(package_name, package_version, |file| {
+                artifact::read_file_digests(file)
+            })?;
+
+        let repository_url = version_metadata.repository.clone().ok_or(format_err!(
+            "Collection {} version {} does not declare a source repository.",
+            package_name,
+            version_metadata.package_version
+        ))?;
+        let release = git::get_git_release(
+            &repository_url,
+            &Some(version_metadata.package_version.as_str()),
+            self.include_prereleases_,
+        )?;
+        let client = self.client()?;
+        let reader = http::BoundedReader::new(
+            tarball::open_artifact(&client, None, &release.artifact_url)?,
+            self.max_artifact_size_,
+        );
+        let source_digests = artifact::read_file_digests(reader).map_err(|error| {
+            format_err!(
+                "Failed to read source archive: {}: {}",
+                release.artifact_url,
+                error
+            )
+        })?;
+
+        let (differences, matches_source) =
+            artifact::compare_with_source(&source_digests, &artifact_digests)?;
+        Ok(artifact::ProvenanceReport {
+            package_version: version_metadata.package_version,
+            repository_url,
+            reference: release.version,
+            differences,
+            matches_source,
+        })
+    }
+
+    /// Returns an overview of the modules, plugins, roles, playbooks and requirement files
+    /// of a collection version, or the latest version. The artifact is fetched as by
+    /// `download_artifact`.
+    pub fn content_inventory(
+        &self,
+        package_name: &str,
+        package_version: &Option<&str>,
+    ) -> Result<artifact::ContentInventory> {
+        let (_, inventory) =
+            self.read_downloaded_artifact(package_name, package_version, |file| {
+                artifact::read_content_inventory(file)
+            })?;
+        Ok(inventory)
+    }
+
+    /// Returns the files added, removed or modified between two versions of a collection.
+    ///
+    /// Both artifacts are fetched as by `download_artifact`, so are verified against the
+    /// registry digests.
+    pub fn diff_versions(
+        &self,
+        package_name: &str,
+        old_version: &str,
+        new_version: &str,
+    ) -> Result<artifact::VersionDiff> {
+        let (old_metadata, old_digests) =
+            self.read_downloaded_artifact(package_name, &Some(old_version), |file| {
+                artifact::read_file_digests(file)
+            })?;
+        let (new_metadata, new_digests) =
+            self.read_downloaded_artifact(package_name, &Some(new_version), |file| {
+                artifact::read_file_digests(file)
+            })?;
+        Ok(artifact::VersionDiff {
+            name: package_name.to_string(),
+            old_version: old_metadata.package_version,
+            new_version: new_metadata.package_version,
+            differences: artifact::diff_file_digests(&old_digests, &new_digests),
+        })
+    }
+
+    /// Download a verified artifact to a private temporary directory, read it, then remove
+    /// the directory.
+    fn read_downloaded_artifact<T, F: FnOnce(std::fs::File) -> Result<T>>(
+        &self,
+        package_name: &str,
+        package_version: &Option<&str>,
+        read: F,
+    ) -> Result<(metadata::VersionMetadata, T)> {
+        let directory = tempfile::Builder::new()
+            .prefix("vouch-ansible-artifact-")
+            .tempdir()
+            .context("Failed to create temporary directory.")?;
+        let artifact_path = directory.path().join("artifact.tar.gz");
+        let version_metadata =
+            self.download_artifact(package_name, package_version, &artifact_path)?;
+        let file = std::fs::File::open(&artifact_path)?;
+        Ok((version_metadata, read(file)?))
+    }
+
+    /// Verifies the signatures of a downloaded collection artifact, as given by
+    /// `version_metadata`, against the GPG keyring configured for the working directory.
+    /// See `signature::Keyring::from_project_directory`.
+    pub fn verify_artifact_signatures(
+        &self,
+        working_directory: &std::path::Path,
+        artifact_path: &std::path::Path,
+        version_metadata: &metadata::VersionMetadata,
+    ) -> Result<Vec<signature::SignatureVerification>> {
+        let keyring =
+            signature::Keyring::from_project_directory(working_directory).ok_or(format_err!(
+                "No GPG keyring configured. Set VOUCH_ANSIBLE_GPG_KEYS or [galaxy] gpg_keyring."
+            ))?;
+        let file = std::fs::File::open(artifact_path).map_err(|error| {
+            format_err!(
+                "Failed to open artifact: {}: {}",
+                artifact_path.display(),
+                error
+            )
+        })?;
+        let (_, manifest) = tarball::read_file(file, &["MANIFEST.json"])?.ok_or(format_err!(
+            "Failed to find MANIFEST.json in artifact: {}",
+            artifact_path.display()
+        ))?;
+        signature::verify_signatures(&manifest, &version_metadata.signatures, &keyring)
+    }
+
+    /// Returns changelog entries for versions after `from_version` up to `to_version`, newest first.
+    ///
+    /// The changelog is read from the `changelogs/changelog.yaml` or `CHANGELOG.rst` file
+    /// of the `to_version` artifact, which is fetched with the registry token.
+    pub fn changelog_between(
+        &self,
+        package_name: &str,
+        from_version: &str,
+        to_version: &str,
+    ) -> Result<Vec<changelog::ChangelogEntry>> {
+        let registries_metadata = vouch_lib::extension::Extension::registries_package_metadata(
+            self,
+            package_name,
+            &Some(to_version),
+        )?;
+        let primary_metadata = registries_metadata
+            .iter()
+            .find(|metadata| metadata.is_primary)
+            .ok_or(format_err!("Failed to find artifact for {}.", package_name))?;
+        let artifact_url = url::Url::parse(&primary_metadata.artifact_url)?;
+        let registry = self
+            .configured_registries()?
+            .iter()
+            .find(|registry| registry.host_name == primary_metadata.registry_host_name);
+
+        let client = self.client()?;
+        let reader = if oci::is_oci_source(package_name) {
+            oci::open_blob(&client, &artifact_url)?
+        } else {
+            tarball::open_artifact(&client, registry, &artifact_url)?
+        };
+        let (file_path, content) = tarball::read_file(reader, changelog::CHANGELOG_FILE_PATHS)?
+            .ok_or(format_err!(
+                "Failed to find changelog in artifact: {}",
+                artifact_url
+            ))?;
+        let entries = changelog::parse_changelog(&file_path, &content)?;
+        changelog::select_entries_between(entries, from_version, to_version)
+    }
+
+    /// Returns publisher namespace details for a namespace or collection name.
+    pub fn namespace_metadata(&self, name: &str) -> Result<metadata::NamespaceMetadata> {
+        let namespace = name.split('.').next().unwrap_or(name);
+        let client = self.client()?;
+        let mut errors = Vec::new();
+        for registry in self
+            .configured_registries()?
+            .iter()
+            .filter(|r| !r.is_local())
+        {
+            let namespace_json = registry
+                .namespace_url(namespace)
+                .and_then(|url| http::get_json(&client, registry, url.as_str()));
+            match namespace_json {
+                Ok(namespace_json) => {
+                    return metadata::parse_namespace_metadata(
+                        &namespace_json,
+                        namespace,
+                        &registry.host_name,
+                    )
+                }
+                Err(error) => errors.push((registry.host_name.clone(), error)),
+            }
+        }
+        Err(get_registries_error(
+            format!("Failed to find namespace {} in any registry.", namespace),
+            errors,
+        ))
+    }
+
+    /// Returns the dependency graph of the collections required by the project dependency file.
+    ///
+    /// Collection requirements are read from registry version metadata and resolved to the
+    /// highest matching registry version. Git, OCI, tarball and role dependencies are not
+    /// followed. Collections without registry metadata are kept as leaves and also listed
+    /// as unresolved.
+    pub fn dependency_graph(
+        &self,
+        working_directory: &std::path::Path,
+        extension_args: &[String],
+    ) -> Result<graph::DependencyGraph> {
+        self.build_dependency_graph(working_directory, extension_args, true)
+    }
+
+    fn build_dependency_graph(
+        &self,
+        working_directory: &std::path::Path,
+        extension_args: &[String],
+        use_lockfile: bool,
+    ) -> Result<graph::DependencyGraph> {
+        let include_prereleases =
+            self.include_prereleases_ || resolution::get_include_prereleases(extension_args);
+        let ansible_core_version = self.get_ansible_core_version(extension_args);
+        let file_dependencies =
+            self.get_file_defined_dependencies(working_directory, extension_args, use_lockfile)?;
+
+        // Role names may also pass as collection names, but roles have no collection metadata.
+        let role_names =
+            match select_preferred_dependency_file(&identify_dependency_files(working_directory)) {
+                Some(DependencyFile {
+                    r#type: DependencyFileType::RequirementsYml,
+                    path,
+                }) => galaxy::get_requirements_yml_role_names(path)?,
+                _ => std::collections::BTreeSet::new(),
+            };
+
+        let mut graph = graph::DependencyGraph::default();
+        let mut queue = std::collections::VecDeque::new();
+        for dependency in file_dependencies
+            .iter()
+            .flat_map(|file_dependencies| file_dependencies.dependencies.iter())
+            .filter(|dependency| !role_names.contains(&dependency.name))
+            .filter(|dependency| fqcn::check_package_name(&dependency.name).is_ok())
+        {
+            let version = match &dependency.version {
+                Ok(version) => Some(version.clone()),
+                Err(_) => self.resolve_registry_version(
+                    &dependency.name,
+                    &resolution::VersionRequirement::parse("*")?,
+                    include_prereleases,
+                    ansible_core_version.as_ref(),
+                ),
+            };
+            let node = match version {
+                Some(version) => graph::DependencyNode {
+                    name: dependency.name.clone(),
+                    version,
+                },
+                None => {
+                    graph.unresolved.push(graph::UnresolvedDependency {
+                        dependent: None,
+                        name: dependency.name.clone(),
+                        version_requirement: "*".to_string(),
+                    });
+                    continue;
+                }
+            };
+            if !graph.contains(&node) {
+                graph.add_node(node.clone());
+                queue.push_back(node.clone());
+            }
+            graph.roots.insert(node);
+        }
+
+        while let Some(node) = queue.pop_front() {
+            let entry_json = match self
+                .configured_registries()?
+                .iter()
+                .filter(|registry| !registry.is_local())
+                .find_map(|registry| {
+                    get_registry_entry_json(self, registry, &node.name, &node.version).ok()
+                }) {
+                Some(entry_json) => entry_json,
+                // Kept as a leaf, its requirements unknown.
+                None => {
+                    graph.unresolved.push(graph::UnresolvedDependency {
+                        dependent: None,
+                        name: node.name.clone(),
+                        version_requirement: node.version.clone(),
+                    });
+                    continue;
+                }
+            };
+
+            for (name, version_requirement) in graph::get_entry_dependencies(&entry_json) {
+                let version = resolution::VersionRequirement::parse(&version_requirement)
+                    .ok()
+                    .and_then(|requirement| {
+                        self.resolve_registry_version(
+                            &name,
+                            &requirement,
+                            include_prereleases,
+                            ansible_core_version.as_ref(),
+                        )
+                    });
+                let dependency = match version {
+                    Some(version) => graph::DependencyNode { name, version },
+                    None => {
+                        graph.unresolved.push(graph::UnresolvedDependency {
+                            dependent: Some(node.clone()),
+                            name,
+                            version_requirement,
+                        });
+                        continue;
+                    }
+                };
+                if !graph.contains(&dependency) {
+                    queue.push_back(dependency.clone());
+                }
+                graph.add_edge(node.clone(), dependency);
+            }
+        }
+        Ok(graph)
+    }
+
+    /// Resolve all project dependencies, including transitive collection dependencies,
+    /// to exact versions.
+    pub fn generate_lockfile(
+        &self,
+        working_directory: &std::path::Path,
+        extension_args: &[String],
+    ) -> Result<lockfile::Lockfile> {
+        // Existing pins are ignored so that the lockfile picks up new registry versions.
+        let graph = self.build_dependency_graph(working_directory, extension_args, false)?;
+        if let Some(unresolved) = graph
+            .unresolved
+            .iter()
+            .find(|unresolved| unresolved.dependent.is_some())
+        {
+            return Err(format_err!(
+                "Failed to resolve requirement {} {} of {}.",
+                unresolved.name,
+                unresolved.version_requirement,
+                unresolved
+                    .dependent
+                    .as_ref()
+                    .map(|node| node.to_string())
+                    .unwrap_or_default()
+            ));
+        }
+
+        // Dependencies outside of the graph, such as git sources and roles, are resolved
+        // individually.
+        let mut packages: Vec<(String, Option<String>)> = graph
+            .nodes()
+            .map(|node| (node.name.clone(), Some(node.version.clone())))
+            .collect();
+        let file_dependencies =
+            self.get_file_defined_dependencies(working_directory, extension_args, false)?;
+        for dependency in file_dependencies
+            .iter()
+            .flat_map(|file_dependencies| file_dependencies.dependencies.iter())
+        {
+            let is_graph_root = graph.roots.iter().any(|root| root.name == dependency.name);
+            if !is_graph_root {
+                packages.push((dependency.name.clone(), dependency.version.clone().ok()));
+            }
+        }
+
+        let mut dependencies = Vec::new();
+        for (package_name, package_version) in packages {
+            let registries_metadata = vouch_lib::extension::Extension::registries_package_metadata(
+                self,
+                &package_name,
+                &package_version.as_deref(),
+            )?;
+            let metadata = registries_metadata
+                .into_iter()
+                .find(|metadata| metadata.is_primary)
+                .ok_or(format_err!("Failed to resolve {}.", package_name))?;
+            dependencies.push(lockfile::LockedDependency {
+                name: package_name,
+                version: metadata.package_version,
+                registry_host_name: metadata.registry_host_name,
+                artifact_url: metadata.artifact_url,
+            });
+        }
+        Ok(lockfile::Lockfile::new(dependencies))
+    }
+
+    /// Generate a lockfile and write it next to the project dependency file.
+    /// Returns the lockfile path.
+    pub fn write_lockfile(
+        &self,
+        working_directory: &std::path::Path,
+        extension_args: &[String],
+    ) -> Result<std::path::PathBuf> {
+        let dependency_file =
+            select_preferred_dependency_file(&identify_dependency_files(working_directory))
+                .cloned()
+                .ok_or(format_err!(
+                    "Failed to find a dependency file from: {}",
+                    working_directory.display()
+                ))?;
+        let lockfile = self.generate_lockfile(working_directory, extension_args)?;
+        let path = lockfile::Lockfile::get_path(&dependency_file.path);
+        lockfile.write(&path)?;
+        Ok(path)
+    }
+
+    /// Returns known advisories for a collection version: registry deprecation, and entries
+    /// of the advisory feeds given by VOUCH_ANSIBLE_ADVISORY_FEEDS.
+    ///
+    /// Fails if no advisory feeds are configured, or if the deprecation status cannot be
+    /// checked.
+    pub fn advisories(
+        &self,
+        package_name: &str,
+        package_version: &str,
+    ) -> Result<Vec<advisory::Advisory>> {
+        let client = self.client()?;
+        let database = advisory::load_database(&client, &advisory::get_feed_locations())?;
+        let mut advisories: Vec<_> = self
+            .get_deprecation_advisory(package_name)?
+            .into_iter()
+            .collect();
+        advisories.extend(database.find(package_name, package_version));
+        Ok(advisories)
+    }
+
+    /// Returns the collections of the project dependency graph which have known advisories,
+    /// or whose deprecation status could not be checked. See `advisories`.
+    ///
+    /// Deprecation is looked up once per collection, shared by all versions in the graph.
+    pub fn dependency_advisories(
+        &self,
+        working_directory: &std::path::Path,
+        extension_args: &[String],
+    ) -> Result<Vec<advisory::DependencyAdvisories>> {
+        let client = self.client()?;
+        let database = advisory::load_database(&client, &advisory::get_feed_locations())?;
+        let graph = self.dependency_graph(working_directory, extension_args)?;
+        let mut deprecations = std::collections::BTreeMap::new();
+        Ok(graph
+            .nodes()
+            .filter_map(|node| {
+                let deprecation = deprecations.entry(node.name.clone()).or_insert_with(|| {
+                    self.get_deprecation_advisory(&node.name)
+                        .map_err(|error| format!("{:#}", error))
+                });
+                let (mut advisories, deprecation_error) = match deprecation {
+                    Ok(deprecation) => (deprecation.iter().cloned().collect::<Vec<_>>(), None),
+                    Err(error) => (Vec::new(), Some(error.clone())),
+                };
+                advisories.extend(database.find(&node.name, &node.version));
+                if advisories.is_empty() && deprecation_error.is_none() {
+                    return None;
+                }
+                Some(advisory::DependencyAdvisories {
+                    node: node.clone(),
+                    advisories,
+                    deprecation_error,
+                })
+            })
+            .collect())
+    }
+
+    fn get_deprecation_advisory(&self, package_name: &str) -> Result<Option<advisory::Advisory>> {
+        let collection_metadata = self
+            .collection_metadata(package_name)
+            .context(format!("Failed to check deprecation of {}.", package_name))?;
+        Ok(advisory::get_deprecation_advisory(&collection_metadata))
+    }
+
+    /// Returns collections whose requirements conflict across the project MANIFEST.json,
+    /// galaxy.yml and requirements.yml files.
+    pub fn dependency_conflicts(
+        &self,
+        working_directory: &std::path::Path,
+    ) -> Result<Vec<conflict::DependencyConflict>> {
+        let mut file_requirements = Vec::new();
+        for dependency_file in identify_dependency_files(working_directory) {
+            let requirements = get_dependency_file_requirements(&dependency_file)?;
+            file_requirements.push((dependency_file.path, requirements));
+        }
+        Ok(conflict::find_conflicts(&file_requirements))
+    }
+
+    /// Returns the dependency file entries which are skipped because they cannot be parsed,
+    /// and the lockfile pins which are ignored because they no longer satisfy requirements.
+    ///
+    /// Every MANIFEST.json, galaxy.yml and requirements.yml file of the project is checked.
+    pub fn dependency_diagnostics(
+        &self,
+        working_directory: &std::path::Path,
+    ) -> Result<Vec<diagnostic::DependencyDiagnostic>> {
+        // Versions are not needed, so installed versions are never loaded.
+        let global_dependencies =
+            installed::InstalledVersions::from(std::collections::HashMap::new());
+        let context = galaxy::VersionContext::installed(&global_dependencies);
+        let mut diagnostics = Vec::new();
+        for dependency_file in identify_dependency_files(working_directory) {
+            let file_dependencies = match dependency_file.r#type {
+                DependencyFileType::GalaxyManifest => {
+                    galaxy::get_manifest_dependencies(&dependency_file.path, &context)?
+                }
+                DependencyFileType::GalaxyYml => {
+                    galaxy::get_galaxy_yml_dependencies(&dependency_file.path, &context)?
+                }
+                DependencyFileType::RequirementsYml => {
+                    galaxy::get_requirements_yml_dependencies(&dependency_file.path, &context)?
+                }
+            };
+            diagnostics.extend(file_dependencies.diagnostics);
+
+            let lockfile_path = lockfile::Lockfile::get_path(&dependency_file.path);
+            if lockfile_path.is_file() {
+                diagnostics.extend(lockfile::Lockfile::read(&lockfile_path)?.find_stale_pins(
+                    &dependency_file.path,
+                    &get_dependency_file_requirements(&dependency_file)?,
+                ));
+            }
+        }
+        Ok(diagnostics)
+    }
+
+    /// Returns collections installed for a project in precedence order, including collections
+    /// bundled with ansible. The first installation of a collection is used for resolution.
+    pub fn installed_collections(
+        &self,
+        working_directory: &std::path::Path,
+    ) -> Result<Vec<installed::InstalledCollection>> {
+        self.get_installed_collections(working_directory)
+    }
+
+    /// Returns the installation of a collection used for version resolution, if installed.
+    /// Where a collection is installed in several collections paths, the path with the
+    /// highest precedence is used, as in ansible.
+    pub fn installed_collection(
+        &self,
+        working_directory: &std::path::Path,
+        package_name: &str,
+    ) -> Result<Option<installed::InstalledCollection>> {
+        let collections = self.get_installed_collections(working_directory)?;
+        Ok(installed::find_active_collection(&collections, package_name).cloned())
+    }
+
+    /// Returns dependencies of the project dependency file.
+    ///
+    /// With `use_lockfile`, versions pinned by a lockfile next to the dependency file take
+    /// precedence over requirement resolution. Pins which no longer satisfy the requirement
+    /// are ignored and reported by `dependency_diagnostics`.
+    ///
+    /// Only the preferred dependency file is read. Conflicting requirements in the other
+    /// dependency files are returned by `dependency_conflicts`, and skipped entries by
+    /// `dependency_diagnostics`.
+    fn get_file_defined_dependencies(
+        &self,
+        working_directory: &std::path::Path,
+        extension_args: &[String],
+        use_lockfile: bool,
     ) -> Result<Vec<vouch_lib::extension::FileDefinedDependencies>> {
         // Identify dependency definition file.
-        let dependency_files = identify_dependency_files(&working_directory);
+        let dependency_files = identify_dependency_files(working_directory);
         let dependency_file = match select_preferred_dependency_file(&dependency_files) {
             Some(dependency_file) => dependency_file,
             None => return Ok(Vec::new()),
         };
 
-        let global_dependencies = galaxy::get_global_dependencies()?;
+        let global_dependencies = installed::InstalledVersions::new(|| {
+            Ok(installed::get_versions(
+                &self.get_installed_collections(working_directory)?,
+            ))
+        });
+        let include_prereleases =
+            self.include_prereleases_ || resolution::get_include_prereleases(extension_args);
+        let ansible_core_version = self.get_ansible_core_version(extension_args);
+        let lockfile_path = lockfile::Lockfile::get_path(&dependency_file.path);
+        if http::get_trace_arg(extension_args) {
+            self.trace_arg_
+                .store(true, std::sync::atomic::Ordering::Relaxed);
+        }
+        let version_resolution = match resolution::VersionResolution::from_args(extension_args)? {
+            // Lockfile generation resolves without the lockfile it replaces.
+            resolution::VersionResolution::Lockfile if !use_lockfile => {
+                resolution::VersionResolution::Installed
+            }
+            resolution::VersionResolution::Lockfile if !lockfile_path.is_file() => {
+                return Err(format_err!(
+                    "Lockfile version resolution requires a lockfile: {}",
+                    lockfile_path.display()
+                ));
+            }
+            version_resolution => version_resolution,
+        };
+        let resolve_registry_version =
+            |package_name: &str, requirement: &resolution::VersionRequirement| {
+                if version_resolution == resolution::VersionResolution::Installed
+                    && !requirement.is_any()
+                {
+                    return None;
+                }
+                self.resolve_registry_version(
+                    package_name,
+                    requirement,
+                    include_prereleases,
+                    ansible_core_version.as_ref(),
+                )
+            };
+        let installed_roles = installed::InstalledVersions::new(|| {
+            Ok(installed::get_role_versions(&galaxy::get_installed_roles(
+                working_directory,
+            )?))
+        });
+        let context = match version_resolution {
+            // Without a resolver, no registry request is made.
+            resolution::VersionResolution::Offline | resolution::VersionResolution::Lockfile => {
+                galaxy::VersionContext {
+                    installed_roles: Some(&installed_roles),
+                    ..galaxy::VersionContext::installed(&global_dependencies)
+                }
+            }
+            _ => galaxy::VersionContext {
+                resolver: Some(&resolve_registry_version),
+                prefer_installed: version_resolution != resolution::VersionResolution::Registry,
+                installed_roles: Some(&installed_roles),
+                ..galaxy::VersionContext::installed(&global_dependencies)
+            },
+        };
 
         // Read all dependencies definitions files.
         let mut dependency_specs = Vec::new();
-        let (dependencies, registry_host_name) = match dependency_file.r#type {
+        let (file_dependencies, registry_host_name) = match dependency_file.r#type {
             DependencyFileType::GalaxyManifest => (
-                galaxy::get_manifest_dependencies(&dependency_file.path, &global_dependencies)?,
+                galaxy::get_manifest_dependencies(&dependency_file.path, &context)?,
                 galaxy::get_registry_host_name(),
             ),
             DependencyFileType::GalaxyYml => (
-                galaxy::get_galaxy_yml_dependencies(&dependency_file.path, &global_dependencies)?,
+                galaxy::get_galaxy_yml_dependencies(&dependency_file.path, &context)?,
+                galaxy::get_registry_host_name(),
+            ),
+            DependencyFileType::RequirementsYml => (
+                galaxy::get_requirements_yml_dependencies(&dependency_file.path, &context)?,
                 galaxy::get_registry_host_name(),
             ),
         };
+        let dependencies = file_dependencies.dependencies;
+        let dependencies = if use_lockfile && lockfile_path.is_file() {
+            lockfile::Lockfile::read(&lockfile_path)?.pin_dependencies(
+                dependencies,
+                &get_dependency_file_requirements(dependency_file)?,
+                version_resolution == resolution::VersionResolution::Lockfile,
+            )
+        } else {
+            dependencies
+        };
         dependency_specs.push(vouch_lib::extension::FileDefinedDependencies {
             path: dependency_file.path.clone(),
-            registry_host_name: registry_host_name,
+            registry_host_name,
             dependencies: dependencies.into_iter().collect(),
         });
 
         Ok(dependency_specs)
     }
 
+    /// Returns popular collections which the package name closely resembles, closest first.
+    /// Check these before vouching for the package, in case it is a typosquat.
+    ///
+    /// Names published by a configured registry are not reported, as established collections
+    /// may resemble popular ones. See `search::find_typosquat_targets`.
+    pub fn typosquat_targets(&self, package_name: &str) -> Result<Vec<&'static str>> {
+        let typosquat_targets = search::find_typosquat_targets(package_name);
+        if typosquat_targets.is_empty() {
+            return Ok(typosquat_targets);
+        }
+        let is_published = self
+            .configured_registries()?
+            .iter()
+            .filter(|registry| !registry.is_local())
+            .any(|registry| get_registry_collection_json(self, registry, package_name).is_ok());
+        Ok(if is_published {
+            Vec::new()
+        } else {
+            typosquat_targets
+        })
+    }
+
+    /// Returns similarly named collections, closest first.
+    ///
+    /// Intended for "did you mean" suggestions when a package lookup fails. A name without a
+    /// dot is taken as an artifact style `namespace-name`.
+    pub fn suggest_collections(&self, package_name: &str) -> Result<Vec<search::SearchResult>> {
+        let package_name = &if package_name.contains('.') {
+            package_name.to_string()
+        } else {
+            package_name.replacen('-', ".", 1)
+        };
+        let mut results = Vec::new();
+        for query in package_name.split('.').filter(|query| !query.is_empty()) {
+            results.extend(self.search_collections(query)?);
+        }
+        Ok(search::select_suggestions(package_name, results))
+    }
+}
+
+/// Returns configured registries, highest priority first.
+///
+/// Red Hat Automation Hub is included when the VOUCH_ANSIBLE_AUTOMATION_HUB_TOKEN
+/// environment variable gives an offline token. VOUCH_ANSIBLE_AUTOMATION_HUB_REPOSITORY
+/// selects the `certified` (default) or `validated` repository.
+///
+/// The default registry is galaxy.ansible.com unless overridden using
+/// the VOUCH_ANSIBLE_REGISTRY_* environment variables.
+///
+/// Without any of the above environment variables set, the servers listed
+/// in the `[galaxy] server_list` (or ANSIBLE_GALAXY_SERVER_LIST) of the ansible.cfg which
+/// applies to the project directory are used in order, if any.
+fn get_registries(project_directory: &std::path::Path) -> Result<Vec<registry::Registry>> {
+    let is_env_configured = std::env::var("VOUCH_ANSIBLE_AUTOMATION_HUB_TOKEN").is_ok()
+        || std::env::var("VOUCH_ANSIBLE_REGISTRY_URL").is_ok();
+    if !is_env_configured {
+        let registries = get_ansible_config_registries(project_directory);
+        if !registries.is_empty() {
+            return Ok(registries);
+        }
+    }
+
+    let mut registries = Vec::new();
+    if let Ok(offline_token) = std::env::var("VOUCH_ANSIBLE_AUTOMATION_HUB_TOKEN") {
+        let repository = std::env::var("VOUCH_ANSIBLE_AUTOMATION_HUB_REPOSITORY")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(registry::AutomationHubRepository::Certified);
+        registries.push(registry::Registry::automation_hub(
+            repository,
+            &offline_token,
+        ));
+    }
+    registries.push(get_default_registry()?);
+    Ok(registries)
+}
+
+/// Returns registries defined by ansible.cfg Galaxy servers
+/// and ANSIBLE_GALAXY_SERVER_* environment variables.
+///
+/// Server definitions which fail to parse are skipped.
+fn get_ansible_config_registries(project_directory: &std::path::Path) -> Vec<registry::Registry> {
+    config::AnsibleConfig::load(project_directory)
+        .galaxy_servers()
+        .iter()
+        .filter_map(|server| registry::Registry::from_galaxy_server(server).ok())
+        .collect()
+}
+
+/// Returns the default registry.
+///
+/// VOUCH_ANSIBLE_REGISTRY_URL replaces galaxy.ansible.com with a self-hosted server,
+/// or with a `file://` directory of collection artifacts.
+/// VOUCH_ANSIBLE_REGISTRY_API_PREFIX and VOUCH_ANSIBLE_REGISTRY_HUMAN_URL_TEMPLATE
+/// override the API path prefix and the human URL template respectively.
+/// VOUCH_ANSIBLE_REGISTRY_DISTRIBUTION selects a Pulp distribution base path.
+/// VOUCH_ANSIBLE_REGISTRY_PROXY_COMPATIBILITY toggles Artifactory/Nexus compatibility mode.
+/// VOUCH_ANSIBLE_REGISTRY_MIRRORS gives comma separated mirror root URLs.
+fn get_default_registry() -> Result<registry::Registry> {
+    let mut default_registry = match std::env::var("VOUCH_ANSIBLE_REGISTRY_URL") {
+        Ok(value) => {
+            let root_url = url::Url::parse(&value).context(format!(
+                "Failed to parse VOUCH_ANSIBLE_REGISTRY_URL: {}",
+                value
+            ))?;
+            match root_url.to_file_path() {
+                Ok(directory) if root_url.scheme() == "file" => {
+                    registry::Registry::local(&directory)?
+                }
+                _ => registry::Registry::private(&root_url)?,
+            }
+        }
+        Err(_) => registry::Registry::galaxy(),
+    };
+
+    if let Ok(api_prefix) = std::env::var("VOUCH_ANSIBLE_REGISTRY_API_PREFIX") {
+        default_registry.api_prefix = api_prefix;
+    }
+    if let Ok(human_url_template) = std::env::var("VOUCH_ANSIBLE_REGISTRY_HUMAN_URL_TEMPLATE") {
+        default_registry.human_url_template = human_url_template;
+    }
+    if let Some(proxy_compatibility) = std::env::var("VOUCH_ANSIBLE_REGISTRY_PROXY_COMPATIBILITY")
+        .ok()
+        .and_then(|value| config::parse_bool(&value))
+    {
+        default_registry.proxy_compatibility = proxy_compatibility;
+    }
+    if let Ok(distribution) = std::env::var("VOUCH_ANSIBLE_REGISTRY_DISTRIBUTION") {
+        default_registry.distribution = Some(distribution);
+    }
+    if let Ok(mirror_urls) = std::env::var("VOUCH_ANSIBLE_REGISTRY_MIRRORS") {
+        default_registry.mirror_urls = mirror_urls
+            .split(',')
+            .filter_map(|mirror_url| url::Url::parse(mirror_url.trim()).ok())
+            .collect();
+    }
+    Ok(default_registry)
+}
+
+/// Default maximum number of versions endpoint pages to follow.
+static DEFAULT_MAX_VERSION_PAGES: usize = 100;
+
+/// Returns versions endpoint page cap.
+///
+/// Can be overridden using the VOUCH_ANSIBLE_MAX_VERSION_PAGES environment variable.
+fn get_max_version_pages() -> usize {
+    std::env::var("VOUCH_ANSIBLE_MAX_VERSION_PAGES")
+        .ok()
+        .and_then(|value| value.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_MAX_VERSION_PAGES)
+}
+
+/// Default maximum artifact download size in bytes.
+static DEFAULT_MAX_ARTIFACT_SIZE: u64 = 100 * 1024 * 1024;
+
+/// Returns artifact download size cap.
+///
+/// Can be overridden using the VOUCH_ANSIBLE_MAX_ARTIFACT_BYTES environment variable.
+fn get_max_artifact_size() -> u64 {
+    std::env::var("VOUCH_ANSIBLE_MAX_ARTIFACT_BYTES")
+        .ok()
+        .and_then(|value| value.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_MAX_ARTIFACT_SIZE)
+}
+
+impl vouch_lib::extension::Extension for AnsibleExtension {
+    fn name(&self) -> String {
+        self.name_.clone()
+    }
+
+    fn registries(&self) -> Vec<String> {
+        self.configured_registries()
+            .unwrap_or_default()
+            .iter()
+            .map(|registry| registry.host_name.clone())
+            .collect()
+    }
+
+    /// Returns a list of dependencies for the given package.
+    ///
+    /// Returns one package dependencies structure per registry.
+    fn identify_package_dependencies(
+        &self,
+        _package_name: &str,
+        _package_version: &Option<&str>,
+        _extension_args: &Vec<String>,
+    ) -> Result<Vec<vouch_lib::extension::PackageDependencies>> {
+        Err(format_err!("Function unimplemented."))
+    }
+
+    fn identify_file_defined_dependencies(
+        &self,
+        working_directory: &std::path::PathBuf,
+        extension_args: &Vec<String>,
+    ) -> Result<Vec<vouch_lib::extension::FileDefinedDependencies>> {
+        self.get_file_defined_dependencies(working_directory, extension_args, true)
+    }
+
     fn registries_package_metadata(
         &self,
         package_name: &str,
         package_version: &Option<&str>,
     ) -> Result<Vec<vouch_lib::extension::RegistryPackageMetadata>> {
-        let package_version = match package_version {
-            Some(v) => Some(v.to_string()),
-            None => get_latest_version(&package_name)?,
+        // Git hosted packages are resolved against the repository tags rather than a registry.
+        if git::is_git_source(package_name) {
+            let release =
+                git::get_git_release(package_name, package_version, self.include_prereleases_)?;
+            return Ok(vec![vouch_lib::extension::RegistryPackageMetadata {
+                registry_host_name: release.host_name,
+                human_url: release.human_url.to_string(),
+                artifact_url: release.artifact_url.to_string(),
+                is_primary: true,
+                package_version: release.version,
+            }]);
+        }
+
+        // OCI hosted collections are resolved via the OCI distribution API.
+        if oci::is_oci_source(package_name) {
+            let source = oci::OciSource::parse(package_name)?;
+            let client = self.client()?;
+            let release = oci::get_oci_release(
+                &client,
+                package_name,
+                package_version,
+                self.include_prereleases_,
+            )?;
+            return Ok(vec![vouch_lib::extension::RegistryPackageMetadata {
+                registry_host_name: source.host_name,
+                human_url: release.human_url.to_string(),
+                artifact_url: release.artifact_url.to_string(),
+                is_primary: true,
+                package_version: release.version,
+            }]);
         }
-        .ok_or(format_err!("Failed to find package version."))?;
 
-        // Query remote package registry for given package.
-        let human_url = get_registry_human_url(&self, &package_name)?;
+        // Direct tarball URLs are versioned by their embedded MANIFEST.json file.
+        if tarball::is_tarball_url(package_name) {
+            let url = url::Url::parse(package_name)?;
+            let client = self.client()?;
+            let release = tarball::get_tarball_release(&client, &url)?;
+            if let Some(package_version) = package_version {
+                if *package_version != release.version {
+                    return Err(format_err!(
+                        "Tarball {} contains {} version {}, expected version {}.",
+                        url,
+                        release.name,
+                        release.version,
+                        package_version
+                    ));
+                }
+            }
+            return Ok(vec![vouch_lib::extension::RegistryPackageMetadata {
+                registry_host_name: url.host_str().unwrap_or("").to_string(),
+                human_url: url.to_string(),
+                artifact_url: url.to_string(),
+                is_primary: true,
+                package_version: release.version,
+            }]);
+        }
 
-        // Currently, only one registry is supported. Therefore simply extract.
-        let registry_host_name = self
-            .registries()
-            .first()
-            .ok_or(format_err!(
-                "Code error: vector of registry host names is empty."
-            ))?
-            .clone();
+        fqcn::check_package_name(package_name)?;
+
+        // Query registries in priority order. The first registry which has the package is primary.
+        let mut registries_metadata = Vec::new();
+        let mut errors = Vec::new();
+        for registry in self.configured_registries()? {
+            match get_registry_package_metadata(self, registry, package_name, package_version) {
+                Ok(mut metadata) => {
+                    metadata.is_primary = registries_metadata.is_empty();
+                    registries_metadata.push(metadata);
+                }
+                Err(error) => errors.push((registry.host_name.clone(), error)),
+            }
+        }
+
+        if registries_metadata.is_empty() {
+            return Err(get_registries_error(
+                get_package_not_found_message(package_name),
+                errors,
+            ));
+        }
+        Ok(registries_metadata)
+    }
+}
 
-        let entry_json = get_registry_entry_json(&package_name, &package_version)?;
-        let artifact_url = get_archive_url(&entry_json)?;
+/// Returns the error of a lookup which failed in every registry.
+///
+/// If every registry reports the resource as missing, the error chain holds
+/// `error::AnsibleExtensionError::NotFound` so that a missing collection can be told apart
+/// from failing registries.
+fn get_registries_error(message: String, errors: Vec<(String, anyhow::Error)>) -> anyhow::Error {
+    let details = errors
+        .iter()
+        .map(|(host_name, error)| format!("{}: {:#}", host_name, error))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let message = format!("{}\n{}", message, details);
+    let is_not_found =
+        !errors.is_empty() && errors.iter().all(|(_, error)| error::is_not_found(error));
+    match errors.into_iter().next() {
+        Some((_, error)) if is_not_found => error.context(message),
+        _ => format_err!(message),
+    }
+}
+
+#[test]
+fn test_get_registries_error() {
+    let get_not_found_error = |url: &str| {
+        anyhow::Error::new(error::AnsibleExtensionError::NotFound {
+            url: url.to_string(),
+        })
+    };
+    let error = get_registries_error(
+        "Failed to find package community.missing in any registry.".to_string(),
+        vec![
+            (
+                "galaxy.ansible.com".to_string(),
+                get_not_found_error("https://galaxy.ansible.com/"),
+            ),
+            (
+                "hub.example.com".to_string(),
+                get_not_found_error("https://hub.example.com/"),
+            ),
+        ],
+    );
+    assert!(error::is_not_found(&error));
+    assert!(error.to_string().contains("hub.example.com: "));
 
-        Ok(vec![vouch_lib::extension::RegistryPackageMetadata {
-            registry_host_name: registry_host_name,
-            human_url: human_url.to_string(),
-            artifact_url: artifact_url.to_string(),
+    let error = get_registries_error(
+        "Failed to find package community.missing in any registry.".to_string(),
+        vec![
+            (
+                "galaxy.ansible.com".to_string(),
+                get_not_found_error("https://galaxy.ansible.com/"),
+            ),
+            (
+                "hub.example.com".to_string(),
+                format_err!("Registry server error (503)"),
+            ),
+        ],
+    );
+    assert!(!error::is_not_found(&error));
+}
+
+/// Returns the message of a package lookup which failed in every registry, naming the
+/// popular collections which the package name closely resembles, if any.
+fn get_package_not_found_message(package_name: &str) -> String {
+    let message = format!("Failed to find package {} in any registry.", package_name);
+    let typosquat_targets = search::find_typosquat_targets(package_name);
+    if typosquat_targets.is_empty() {
+        return message;
+    }
+    format!(
+        "{} Did you mean {}?",
+        message,
+        typosquat_targets.join(" or ")
+    )
+}
+
+/// Query a single registry for package metadata.
+///
+/// Falls back to the v1 standalone roles API when the registry reports that no collection
+/// exists. Other failures are returned.
+fn get_registry_package_metadata(
+    extension: &AnsibleExtension,
+    registry: &registry::Registry,
+    package_name: &str,
+    package_version: &Option<&str>,
+) -> Result<vouch_lib::extension::RegistryPackageMetadata> {
+    if registry.is_local() {
+        let release = local::get_local_release(
+            registry,
+            package_name,
+            package_version,
+            extension.include_prereleases_,
+        )?;
+        return Ok(vouch_lib::extension::RegistryPackageMetadata {
+            registry_host_name: registry.host_name.clone(),
+            human_url: release.artifact_url.to_string(),
+            artifact_url: release.artifact_url.to_string(),
             is_primary: true,
-            package_version: package_version.to_string(),
-        }])
+            package_version: release.version,
+        });
+    }
+
+    let (package_version, artifact_url) =
+        match get_collection_release(extension, registry, package_name, package_version) {
+            Ok(release) => release,
+            Err(error) if error::is_not_found(&error) => {
+                let client = extension.client()?;
+                let release = roles::get_role_release(
+                    &client,
+                    registry,
+                    package_name,
+                    package_version,
+                    extension.max_version_pages_,
+                )
+                .map_err(|role_error| get_role_fallback_error(error, role_error))?;
+                (release.version, release.artifact_url)
+            }
+            Err(error) => return Err(error),
+        };
+
+    let human_url = get_registry_human_url(registry, package_name)?;
+
+    Ok(vouch_lib::extension::RegistryPackageMetadata {
+        registry_host_name: registry.host_name.clone(),
+        human_url: human_url.to_string(),
+        artifact_url: artifact_url.to_string(),
+        is_primary: true,
+        package_version,
+    })
+}
+
+/// Returns the error of a failed standalone role fallback lookup.
+///
+/// The collection not found error is kept if no role is found either. Other role lookup
+/// failures, such as authentication errors, are returned as they are.
+fn get_role_fallback_error(
+    collection_error: anyhow::Error,
+    role_error: anyhow::Error,
+) -> anyhow::Error {
+    if error::is_not_found(&role_error) {
+        collection_error
+    } else {
+        role_error
+    }
+}
+
+#[test]
+fn test_get_role_fallback_error() {
+    let collection_error = || {
+        anyhow::Error::new(error::AnsibleExtensionError::NotFound {
+            url: "https://galaxy.ansible.com/api/v3/collections/example/missing/".to_string(),
+        })
+    };
+    let role_error = anyhow::Error::new(error::AnsibleExtensionError::NotFound {
+        url: "https://galaxy.ansible.com/api/v1/roles/".to_string(),
+    });
+    assert!(get_role_fallback_error(collection_error(), role_error)
+        .to_string()
+        .contains("/collections/"));
+    assert_eq!(
+        get_role_fallback_error(collection_error(), format_err!("Connection reset.")).to_string(),
+        "Connection reset."
+    );
+}
+
+/// Returns documentation links of a collection version, or the latest version, and
+/// optionally its docs-blob.
+fn get_registry_documentation_metadata(
+    extension: &AnsibleExtension,
+    registry: &registry::Registry,
+    package_name: &str,
+    package_version: &Option<&str>,
+    include_docs_blob: bool,
+) -> Result<metadata::DocumentationMetadata> {
+    let package_version = match package_version {
+        Some(package_version) => package_version.to_string(),
+        None => get_latest_version(
+            extension,
+            registry,
+            package_name,
+            extension.include_prereleases_,
+            extension.get_ansible_core_version(&[]).as_ref(),
+        )?
+        .ok_or(format_err!("Failed to find latest version."))?,
+    };
+    let entry_json = get_registry_entry_json(extension, registry, package_name, &package_version)?;
+    let human_url = get_registry_human_url(registry, package_name)?;
+
+    let docs_blob = if include_docs_blob {
+        let client = extension.client()?;
+        let url = registry.docs_blob_url(package_name, &package_version)?;
+        Some(http::get_json(&client, registry, url.as_str())?["docs_blob"].clone())
+    } else {
+        None
+    };
+
+    Ok(metadata::DocumentationMetadata {
+        docs_url: metadata::get_docs_url(&human_url, &package_version)?.to_string(),
+        documentation_url: entry_json["metadata"]["documentation"]
+            .as_str()
+            .filter(|url| !url.is_empty())
+            .map(|url| url.to_string()),
+        docs_blob,
+        package_version,
+    })
+}
+
+/// Returns collection version and artifact URL.
+fn get_collection_release(
+    extension: &AnsibleExtension,
+    registry: &registry::Registry,
+    package_name: &str,
+    package_version: &Option<&str>,
+) -> Result<(String, url::Url)> {
+    let package_version = match package_version {
+        Some(v) => Some(v.to_string()),
+        None => get_latest_version(
+            extension,
+            registry,
+            package_name,
+            extension.include_prereleases_,
+            extension.get_ansible_core_version(&[]).as_ref(),
+        )?,
+    }
+    .ok_or(format_err!("Failed to find package version."))?;
+
+    let entry_json = get_registry_entry_json(extension, registry, package_name, &package_version)?;
+    let artifact_url =
+        get_entry_artifact_url(&entry_json, registry, package_name, &package_version)?;
+    Ok((package_version, artifact_url))
+}
+
+/// Returns version entry artifact URL.
+///
+/// Falls back to the conventional download URL for package proxies. URLs given by the
+/// registry are checked before use, see `registry::Registry::check_artifact_url`.
+fn get_entry_artifact_url(
+    entry_json: &serde_json::Value,
+    registry: &registry::Registry,
+    package_name: &str,
+    package_version: &str,
+) -> Result<url::Url> {
+    match get_archive_url(entry_json, &registry.root_url) {
+        Ok(artifact_url) => {
+            registry.check_artifact_url(&artifact_url)?;
+            Ok(artifact_url)
+        }
+        Err(_) if registry.proxy_compatibility => {
+            registry.download_url(package_name, package_version)
+        }
+        Err(error) => Err(error),
     }
 }
 
 /// Given package name, return latest version.
-fn get_latest_version(package_name: &str) -> Result<Option<String>> {
-    let json = get_registry_versions_json(&package_name)?;
-    let version_entries = json["results"]
-        .as_array()
-        .ok_or(format_err!("Failed to find results JSON section."))?;
-
-    let mut versions = Vec::<semver::Version>::new();
-    for version_entry in version_entries {
-        let version_entry = version_entry
-            .as_object()
-            .ok_or(format_err!("Failed to parse version entry as JSON object."))?;
-        let version = version_entry["version"]
+///
+/// Prefers the `highest_version` field of the collection detail endpoint.
+/// Falls back to enumerating versions when that field is absent, or when versions are
+/// checked against the installed ansible-core version. See `get_registry_versions`.
+fn get_latest_version(
+    extension: &AnsibleExtension,
+    registry: &registry::Registry,
+    package_name: &str,
+    include_prereleases: bool,
+    ansible_core_version: Option<&semver::Version>,
+) -> Result<Option<String>> {
+    if ansible_core_version.is_none() {
+        let highest_version = get_registry_collection_json(extension, registry, package_name)
+            .ok()
+            .and_then(|json| get_highest_version(&json, include_prereleases));
+        if let Some(highest_version) = highest_version {
+            return Ok(Some(highest_version));
+        }
+    }
+
+    let is_wanted =
+        |version: &semver::Version| resolution::is_selectable(version, include_prereleases);
+    let versions = get_registry_versions(
+        extension,
+        registry,
+        package_name,
+        ansible_core_version,
+        Some(&is_wanted),
+    )?;
+    Ok(Some(select_latest_version(&versions, include_prereleases)?))
+}
+
+/// Returns false for version entries marked as removed or yanked, whose artifacts may no
+/// longer be downloadable.
+fn is_version_entry_available(version_entry: &serde_json::Value) -> bool {
+    let unavailable_states = ["removed", "yanked", "deleted"];
+    let is_unavailable_state = ["state", "status"].iter().any(|key| {
+        version_entry[*key]
             .as_str()
-            .ok_or(format_err!("Failed to parse version as str."))?;
-        let version = match semver::Version::parse(version) {
-            Ok(v) => v,
-            Err(_) => continue,
-        };
-        versions.push(version);
+            .is_some_and(|state| unavailable_states.contains(&state.to_lowercase().as_str()))
+    });
+    let is_marked = version_entry["marks"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|mark| mark.as_str().or_else(|| mark["value"].as_str()))
+        .any(|mark| unavailable_states.contains(&mark.to_lowercase().as_str()));
+    let is_yanked = ["yanked", "removed"]
+        .iter()
+        .any(|key| version_entry[*key].as_bool().unwrap_or(false));
+    !(is_unavailable_state || is_marked || is_yanked)
+}
+
+#[test]
+fn test_is_version_entry_available() {
+    assert!(is_version_entry_available(
+        &serde_json::json!({"version": "1.0.0"})
+    ));
+    assert!(!is_version_entry_available(
+        &serde_json::json!({"version": "1.0.0", "state": "removed"})
+    ));
+    assert!(!is_version_entry_available(
+        &serde_json::json!({"version": "1.0.0", "marks": ["yanked"]})
+    ));
+    assert!(!is_version_entry_available(
+        &serde_json::json!({"version": "1.0.0", "yanked": true})
+    ));
+    assert!(!is_version_entry_available(
+        &serde_json::json!({"version": "1.0.0", "marks": [{"value": "Yanked"}]})
+    ));
+}
+
+/// Returns true if the version entry is installable with the ansible-core version, if given.
+///
+/// Versions list entries of older registries lack `requires_ansible`, which is then read from
+/// the version detail.
+fn is_entry_ansible_compatible(
+    extension: &AnsibleExtension,
+    registry: &registry::Registry,
+    package_name: &str,
+    version_entry: &serde_json::Value,
+    ansible_core_version: Option<&semver::Version>,
+) -> bool {
+    let ansible_core_version = match ansible_core_version {
+        Some(ansible_core_version) => ansible_core_version,
+        None => return true,
+    };
+    let requires_ansible = match get_entry_requires_ansible(version_entry) {
+        Some(requires_ansible) => {
+            requires_ansible.map(|requires_ansible| requires_ansible.to_string())
+        }
+        None => get_version_entry_version(version_entry)
+            .and_then(|version| get_registry_entry_json(extension, registry, package_name, version))
+            .ok()
+            .and_then(|entry_json| get_detail_requires_ansible(&entry_json)),
+    };
+    resolution::is_ansible_compatible(requires_ansible.as_deref(), ansible_core_version)
+}
+
+/// Returns the `requires_ansible` field of a version entry, None if the entry lacks the field.
+fn get_entry_requires_ansible(version_entry: &serde_json::Value) -> Option<Option<&str>> {
+    version_entry
+        .get("requires_ansible")
+        .map(|requires_ansible| requires_ansible.as_str())
+}
+
+/// Returns the `requires_ansible` field of a version detail response.
+fn get_detail_requires_ansible(entry_json: &serde_json::Value) -> Option<String> {
+    entry_json["requires_ansible"]
+        .as_str()
+        .map(|requires_ansible| requires_ansible.to_string())
+}
+
+/// Returns all versions of a package in a registry, both parsed and as published.
+///
+/// Versions which cannot be coerced into semver are skipped, as are removed or yanked versions
+/// and versions incompatible with the ansible-core version, if given.
+///
+/// Given `is_wanted`, stops reading versions endpoint pages once a wanted version is found,
+/// provided the registry has so far listed versions newest first. No later page can then hold
+/// a higher wanted version.
+fn get_registry_versions(
+    extension: &AnsibleExtension,
+    registry: &registry::Registry,
+    package_name: &str,
+    ansible_core_version: Option<&semver::Version>,
+    is_wanted: Option<&(dyn Fn(&semver::Version) -> bool + Sync)>,
+) -> Result<Vec<(semver::Version, String)>> {
+    if registry.is_local() {
+        let versions: Vec<_> = local::get_local_versions(registry, package_name)?
+            .into_iter()
+            .filter_map(|version| Some((resolution::parse_version(&version)?, version)))
+            .collect();
+        if versions.is_empty() {
+            return Err(anyhow::Error::new(error::AnsibleExtensionError::NotFound {
+                url: registry.root_url.to_string(),
+            }));
+        }
+        return Ok(versions);
     }
-    versions.sort();
 
-    let latest_version = versions
-        .last()
-        .ok_or(format_err!("Failed to find latest version."))?;
-    Ok(Some(latest_version.to_string()))
+    let mut pages = VersionPages::new(
+        registry,
+        package_name,
+        extension.max_version_pages_,
+        is_wanted,
+    )?;
+    while let Some(url) = pages.next_url() {
+        let json = extension.get_memoized_json(registry, &url)?;
+        let page_entries = pages.read_page(&json)?;
+        pages.add_versions(page_entries.into_iter().filter(|entry| {
+            is_entry_ansible_compatible(
+                extension,
+                registry,
+                package_name,
+                entry,
+                ansible_core_version,
+            )
+        }));
+    }
+    Ok(pages.into_versions())
 }
 
-fn get_registry_human_url(extension: &AnsibleExtension, package_name: &str) -> Result<url::Url> {
+/// Returns parsed and published versions of version entries. Versions which cannot be coerced
+/// into semver are skipped.
+fn get_entry_versions<'a>(
+    version_entries: impl Iterator<Item = &'a serde_json::Value>,
+) -> Vec<(semver::Version, String)> {
+    version_entries
+        .filter_map(|entry| get_version_entry_version(entry).ok())
+        .filter_map(|version| Some((resolution::parse_version(version)?, version.to_string())))
+        .collect()
+}
+
+/// Returns true if versions read so far are listed newest first and include a wanted version.
+/// No later versions endpoint page can then hold a higher wanted version.
+fn has_newest_wanted_version(
+    versions: &[(semver::Version, String)],
+    is_wanted: &dyn Fn(&semver::Version) -> bool,
+) -> bool {
+    let is_newest_first = versions.windows(2).all(|pair| {
+        resolution::compare_versions(&pair[0].0, &pair[1].0) != std::cmp::Ordering::Less
+    });
+    is_newest_first && versions.iter().any(|(version, _)| is_wanted(version))
+}
+
+/// Returns the highest version, as published.
+///
+/// Prereleases are skipped unless `include_prereleases` is set, as with ansible-galaxy.
+fn select_latest_version(
+    versions: &[(semver::Version, String)],
+    include_prereleases: bool,
+) -> Result<String> {
+    // Return the version as published, which is needed to build its registry URLs.
+    let (_, latest_version) = versions
+        .iter()
+        .filter(|(version, _)| resolution::is_selectable(version, include_prereleases))
+        .max_by(|(a, _), (b, _)| resolution::compare_versions(a, b))
+        .ok_or(format_err!("Failed to find latest stable version."))?;
+    Ok(latest_version.to_string())
+}
+
+#[test]
+fn test_select_latest_version() -> Result<()> {
+    let version_entries = [
+        serde_json::json!({"version": "1.9.0"}),
+        serde_json::json!({"version": "2.0.0-rc1"}),
+        serde_json::json!({"version": "1.10.0"}),
+        serde_json::json!({"version": "v1.11"}),
+        serde_json::json!({"version": "1.2"}),
+        serde_json::json!({"version": "1.12.0", "yanked": true}),
+    ];
+    let versions = get_entry_versions(
+        version_entries
+            .iter()
+            .filter(|entry| is_version_entry_available(entry)),
+    );
+    assert_eq!(select_latest_version(&versions, false)?, "v1.11");
+    assert_eq!(select_latest_version(&versions, true)?, "2.0.0-rc1");
+    Ok(())
+}
+
+#[test]
+fn test_has_newest_wanted_version() -> Result<()> {
+    let is_stable = |version: &semver::Version| version.pre.is_empty();
+    let versions = get_entry_versions(
+        [
+            serde_json::json!({"version": "2.0.0-rc1"}),
+            serde_json::json!({"version": "1.9.0"}),
+        ]
+        .iter(),
+    );
+    assert!(has_newest_wanted_version(&versions, &is_stable));
+    assert!(!has_newest_wanted_version(&versions[..1], &is_stable));
+
+    let versions = get_entry_versions(
+        [
+            serde_json::json!({"version": "1.9.0"}),
+            serde_json::json!({"version": "2.0.0"}),
+        ]
+        .iter(),
+    );
+    assert!(!has_newest_wanted_version(&versions, &is_stable));
+    Ok(())
+}
+
+/// Returns version string from versions endpoint entry.
+fn get_version_entry_version(version_entry: &serde_json::Value) -> Result<&str> {
+    let version_entry = version_entry
+        .as_object()
+        .ok_or(format_err!("Failed to parse version entry as JSON object."))?;
+    version_entry
+        .get("version")
+        .and_then(|version| version.as_str())
+        .ok_or(format_err!("Failed to parse version as str."))
+}
+
+fn get_registry_human_url(registry: &registry::Registry, package_name: &str) -> Result<url::Url> {
     // Example return value: https://galaxy.ansible.com/crivetimihai/development
-    let package_name = package_name.replace(".", "/");
+    let package_name = package_name
+        .split('.')
+        .map(registry::encode_path_segment)
+        .collect::<Result<Vec<_>>>()?
+        .join("/");
+    // The template may be user supplied, so it is rendered rather than formatted.
     let handlebars_registry = handlebars::Handlebars::new();
     let url = handlebars_registry.render_template(
-        &extension.registry_human_url_template_,
-        &maplit::btreemap! {
-            "package_name" => package_name,
-        },
+        &registry.human_url_template,
+        &serde_json::json!({
+            "package_name": package_name,
+            "distribution": registry.human_url_distribution(),
+        }),
     )?;
     Ok(url::Url::parse(url.as_str())?)
 }
 
-fn get_registry_versions_json(package_name: &str) -> Result<serde_json::Value> {
-    let package_name = package_name.replace(".", "/");
-    let handlebars_registry = handlebars::Handlebars::new();
-    let json_url = handlebars_registry.render_template(
-        "https://galaxy.ansible.com/api/v2/collections/{{package_name}}/versions/",
-        &maplit::btreemap! {"package_name" => package_name},
-    )?;
+/// Returns the version given by the `highest_version` field of a collection detail entry.
+///
+/// Returns None for an excluded prerelease, or a removed or yanked version, so that the
+/// highest available version is looked up instead.
+fn get_highest_version(
+    collection_json: &serde_json::Value,
+    include_prereleases: bool,
+) -> Option<String> {
+    let highest_version = &collection_json["highest_version"];
+    if !is_version_entry_available(highest_version) {
+        return None;
+    }
+    let published_version = highest_version["version"].as_str()?;
+    let version = resolution::parse_version(published_version)?;
+    if !resolution::is_selectable(&version, include_prereleases) {
+        return None;
+    }
+    Some(published_version.to_string())
+}
 
-    let mut result = reqwest::blocking::get(&json_url.to_string())?;
-    let mut body = String::new();
-    result.read_to_string(&mut body)?;
+#[test]
+fn test_get_highest_version() {
+    let collection_json = serde_json::json!({
+        "highest_version": {
+            "href": "/api/v3/collections/community/general/versions/3.8.0/",
+            "version": "3.8.0"
+        }
+    });
+    assert_eq!(
+        get_highest_version(&collection_json, false),
+        Some("3.8.0".to_string())
+    );
+    assert_eq!(get_highest_version(&serde_json::json!({}), false), None);
 
-    Ok(serde_json::from_str(&body).context(format!("JSON was not well-formatted:\n{}", body))?)
+    let collection_json = serde_json::json!({"highest_version": {"version": "3.0"}});
+    assert_eq!(
+        get_highest_version(&collection_json, false),
+        Some("3.0".to_string())
+    );
+
+    let collection_json =
+        serde_json::json!({"highest_version": {"version": "3.0.0", "state": "Yanked"}});
+    assert_eq!(get_highest_version(&collection_json, false), None);
 }
 
-fn get_registry_entry_json(package_name: &str, package_version: &str) -> Result<serde_json::Value> {
-    let package_name = package_name.replace(".", "/");
-    let handlebars_registry = handlebars::Handlebars::new();
-    let json_url = handlebars_registry.render_template(
-        "https://galaxy.ansible.com/api/v2/collections/{{package_name}}/versions/{{package_version}}/",
-        &maplit::btreemap! {"package_name" => package_name, "package_version" => package_version.to_string()},
-    )?;
+fn get_registry_collection_json(
+    extension: &AnsibleExtension,
+    registry: &registry::Registry,
+    package_name: &str,
+) -> Result<serde_json::Value> {
+    extension.get_memoized_json(registry, &registry.collection_url(package_name)?)
+}
 
-    let mut result = reqwest::blocking::get(&json_url.to_string())?;
-    let mut body = String::new();
-    result.read_to_string(&mut body)?;
+/// Returns version entries of a versions endpoint page.
+fn get_page_version_entries<'a>(
+    json: &'a serde_json::Value,
+    registry: &registry::Registry,
+) -> Result<&'a Vec<serde_json::Value>> {
+    json["data"]
+        .as_array()
+        .or(json["results"].as_array())
+        .or(if registry.proxy_compatibility {
+            json.as_array()
+        } else {
+            None
+        })
+        .ok_or(format_err!("Failed to find results JSON section."))
+}
 
-    Ok(serde_json::from_str(&body).context(format!("JSON was not well-formatted:\n{}", body))?)
+/// Reader of versions endpoint pages, newest first where supported by the registry.
+///
+/// Shared by the blocking and async paths, which fetch each page given by `next_url`. Follows
+/// `next` (v2) or `links.next` (v3) until exhausted, the page cap is reached, or a wanted
+/// version is found as by `has_newest_wanted_version`.
+struct VersionPages<'a> {
+    registry: &'a registry::Registry,
+    max_pages: usize,
+    page_count: usize,
+    page_url: Option<url::Url>,
+    is_wanted: Option<&'a (dyn Fn(&semver::Version) -> bool + Sync)>,
+    versions: Vec<(semver::Version, String)>,
 }
 
-fn get_archive_url(registry_entry_json: &serde_json::Value) -> Result<url::Url> {
-    Ok(url::Url::parse(
-        registry_entry_json["download_url"]
-            .as_str()
-            .ok_or(format_err!("Failed to parse package archive URL."))?,
-    )?)
+impl<'a> VersionPages<'a> {
+    fn new(
+        registry: &'a registry::Registry,
+        package_name: &str,
+        max_pages: usize,
+        is_wanted: Option<&'a (dyn Fn(&semver::Version) -> bool + Sync)>,
+    ) -> Result<Self> {
+        Ok(Self {
+            registry,
+            max_pages,
+            page_count: 0,
+            page_url: Some(registry.versions_list_url(package_name)?),
+            is_wanted,
+            versions: Vec::new(),
+        })
+    }
+
+    /// Returns the URL of the next page to read, or None once reading is done.
+    fn next_url(&self) -> Option<url::Url> {
+        if self.page_count >= self.max_pages {
+            return None;
+        }
+        self.page_url.clone()
+    }
+
+    /// Returns the available version entries of a page, see `is_version_entry_available`.
+    ///
+    /// The caller passes the entries to keep to `add_versions`.
+    fn read_page<'j>(
+        &mut self,
+        page_json: &'j serde_json::Value,
+    ) -> Result<Vec<&'j serde_json::Value>> {
+        let page_entries = get_page_version_entries(page_json, self.registry)?
+            .iter()
+            .filter(|entry| is_version_entry_available(entry))
+            .collect();
+        self.page_url = get_next_page_url(page_json, &self.registry.root_url)?;
+        self.page_count += 1;
+        Ok(page_entries)
+    }
+
+    fn add_versions<'j>(&mut self, version_entries: impl Iterator<Item = &'j serde_json::Value>) {
+        self.versions.extend(get_entry_versions(version_entries));
+        if self
+            .is_wanted
+            .is_some_and(|is_wanted| has_newest_wanted_version(&self.versions, is_wanted))
+        {
+            self.page_url = None;
+        }
+    }
+
+    fn into_versions(self) -> Vec<(semver::Version, String)> {
+        self.versions
+    }
+}
+
+/// Returns next page URL from paginated registry response.
+fn get_next_page_url(
+    page_json: &serde_json::Value,
+    registry_root_url: &url::Url,
+) -> Result<Option<url::Url>> {
+    let next_page = page_json["links"]["next"]
+        .as_str()
+        .or(page_json["next"].as_str());
+    Ok(match next_page {
+        Some(next_page) => Some(registry_root_url.join(next_page)?),
+        None => None,
+    })
+}
+
+#[test]
+fn test_get_next_page_url() -> Result<()> {
+    let root_url = url::Url::parse("https://galaxy.ansible.com")?;
+
+    let v2_page = serde_json::json!({
+        "next": "https://galaxy.ansible.com/api/v2/collections/community/general/versions/?page=2",
+        "results": []
+    });
+    assert_eq!(
+        get_next_page_url(&v2_page, &root_url)?.map(|url| url.to_string()),
+        Some(
+            "https://galaxy.ansible.com/api/v2/collections/community/general/versions/?page=2"
+                .to_string()
+        )
+    );
+
+    let v3_page = serde_json::json!({
+        "links": {
+            "next": "/api/v3/collections/community/general/versions/?limit=10&offset=10"
+        },
+        "data": []
+    });
+    assert_eq!(
+        get_next_page_url(&v3_page, &root_url)?.map(|url| url.to_string()),
+        Some(
+            "https://galaxy.ansible.com/api/v3/collections/community/general/versions/?limit=10&offset=10"
+                .to_string()
+        )
+    );
+
+    let last_page = serde_json::json!({"links": {"next": null}, "data": []});
+    assert_eq!(get_next_page_url(&last_page, &root_url)?, None);
+    Ok(())
+}
+
+fn get_registry_entry_json(
+    extension: &AnsibleExtension,
+    registry: &registry::Registry,
+    package_name: &str,
+    package_version: &str,
+) -> Result<serde_json::Value> {
+    extension.get_memoized_json(
+        registry,
+        &registry.version_url(package_name, package_version)?,
+    )
+}
+
+/// Returns package archive URL from registry entry.
+///
+/// v3 responses may give the download URL as a path relative to the API base.
+/// Relative paths are therefore resolved against the registry root URL.
+fn get_archive_url(
+    registry_entry_json: &serde_json::Value,
+    registry_root_url: &url::Url,
+) -> Result<url::Url> {
+    let download_url = registry_entry_json["download_url"]
+        .as_str()
+        .ok_or(format_err!("Failed to parse package archive URL."))?;
+    Ok(registry_root_url.join(download_url)?)
+}
+
+#[test]
+fn test_get_archive_url() -> Result<()> {
+    let root_url = url::Url::parse("https://galaxy.ansible.com")?;
+
+    let entry_json = serde_json::json!({
+        "download_url": "/api/v3/plugin/ansible/content/published/collections/artifacts/community-general-3.0.0.tar.gz"
+    });
+    assert_eq!(
+        get_archive_url(&entry_json, &root_url)?.as_str(),
+        "https://galaxy.ansible.com/api/v3/plugin/ansible/content/published/collections/artifacts/community-general-3.0.0.tar.gz"
+    );
+
+    let entry_json = serde_json::json!({
+        "download_url": "https://example.com/download/community-general-3.0.0.tar.gz"
+    });
+    assert_eq!(
+        get_archive_url(&entry_json, &root_url)?.as_str(),
+        "https://example.com/download/community-general-3.0.0.tar.gz"
+    );
+    Ok(())
 }
 
 /// Package dependency file types.
@@ -200,6 +2393,7 @@ fn get_archive_url(registry_entry_json: &serde_json::Value) -> Result<url::Url>
 enum DependencyFileType {
     GalaxyManifest,
     GalaxyYml,
+    RequirementsYml,
 }
 
 impl DependencyFileType {
@@ -208,6 +2402,7 @@ impl DependencyFileType {
         match self {
             Self::GalaxyManifest => std::path::PathBuf::from("MANIFEST.json"),
             Self::GalaxyYml => std::path::PathBuf::from("galaxy.yml"),
+            Self::RequirementsYml => std::path::PathBuf::from("requirements.yml"),
         }
     }
 }
@@ -219,32 +2414,37 @@ struct DependencyFile {
     path: std::path::PathBuf,
 }
 
+/// Returns collection version requirements, as written, declared by a dependency file.
+fn get_dependency_file_requirements(
+    dependency_file: &DependencyFile,
+) -> Result<Vec<(String, String)>> {
+    match dependency_file.r#type {
+        DependencyFileType::GalaxyManifest => {
+            galaxy::get_manifest_requirements(&dependency_file.path)
+        }
+        DependencyFileType::GalaxyYml => galaxy::get_galaxy_yml_requirements(&dependency_file.path),
+        DependencyFileType::RequirementsYml => {
+            galaxy::get_requirements_yml_requirements(&dependency_file.path)
+        }
+    }
+}
+
 /// Select preferred galaxy.yml dependency file type.
 fn select_preferred_dependency_file(
-    dependency_files: &Vec<DependencyFile>,
+    dependency_files: &[DependencyFile],
 ) -> Option<&DependencyFile> {
-    if dependency_files.iter().any(|file| match file.r#type {
-        DependencyFileType::GalaxyYml => true,
-        _ => false,
-    }) {
-        dependency_files
-            .into_iter()
-            .filter(|file| match file.r#type {
-                DependencyFileType::GalaxyYml => true,
-                _ => false,
-            })
-            .next()
-    } else {
-        dependency_files.into_iter().next()
-    }
+    dependency_files
+        .iter()
+        .find(|file| matches!(file.r#type, DependencyFileType::GalaxyYml))
+        .or_else(|| dependency_files.first())
 }
 
 /// Returns a vector of identified package dependency definition files.
 ///
 /// Walks up the directory tree directory tree until the first positive result is found.
-fn identify_dependency_files(working_directory: &std::path::PathBuf) -> Vec<DependencyFile> {
+fn identify_dependency_files(working_directory: &std::path::Path) -> Vec<DependencyFile> {
     assert!(working_directory.is_absolute());
-    let mut working_directory = working_directory.clone();
+    let mut working_directory = working_directory.to_path_buf();
 
     loop {
         // If at least one target is found, assume package is present.
@@ -265,13 +2465,174 @@ fn identify_dependency_files(working_directory: &std::path::PathBuf) -> Vec<Depe
             return dependency_files;
         }
 
-        // No need to move further up the directory tree after this loop.
-        if working_directory == std::path::PathBuf::from("/") {
+        // Move further up the directory tree, stopping at the root, such as `/` or `C:\`.
+        if !working_directory.pop() {
             break;
         }
-
-        // Move further up the directory tree.
-        working_directory.pop();
     }
     Vec::new()
 }
+
+#[test]
+fn test_offline_resolution_makes_no_requests() -> Result<()> {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    listener.set_nonblocking(true)?;
+    let root_url = url::Url::parse(&format!("http://{}/", listener.local_addr()?))?;
+    let extension =
+        AnsibleExtension::from_registries(vec![registry::Registry::private(&root_url)?])?;
+
+    let directory = tempfile::tempdir()?;
+    std::fs::write(
+        directory.path().join("requirements.yml"),
+        "collections:\n  - name: community.general\n  - name: ansible.posix\n    version: \"*\"\n",
+    )?;
+    let dependency_specs = extension.get_file_defined_dependencies(
+        directory.path(),
+        &["--version-resolution=offline".to_string()],
+        true,
+    )?;
+    assert_eq!(dependency_specs[0].dependencies.len(), 2);
+    assert_eq!(
+        listener.accept().map(|_| ()).map_err(|error| error.kind()),
+        Err(std::io::ErrorKind::WouldBlock)
+    );
+    Ok(())
+}
+
+/// Serves registry requests on a local port until the test process exits. `respond` maps a
+/// request path to a JSON body, or None for 404 Not Found. Returns the registry root URL.
+#[cfg(test)]
+fn serve_test_registry(respond: fn(&str) -> Option<serde_json::Value>) -> Result<url::Url> {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let root_url = url::Url::parse(&format!("http://{}/", listener.local_addr()?))?;
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut buffer = [0; 4096];
+            let length = std::io::Read::read(&mut stream, &mut buffer).unwrap_or(0);
+            let request = String::from_utf8_lossy(&buffer[..length]);
+            let path = request.split_whitespace().nth(1).unwrap_or("/");
+            let (status, body) = match respond(path) {
+                Some(json) => ("200 OK", json.to_string()),
+                None => ("404 Not Found", String::new()),
+            };
+            let _ = std::io::Write::write_all(
+                &mut stream,
+                format!(
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                )
+                .as_bytes(),
+            );
+        }
+    });
+    Ok(root_url)
+}
+
+#[test]
+fn test_dependency_graph_skips_roles_and_missing_metadata() -> Result<()> {
+    let root_url = serve_test_registry(|_| None)?;
+    let extension =
+        AnsibleExtension::from_registries(vec![registry::Registry::private(&root_url)?])?;
+
+    let directory = tempfile::tempdir()?;
+    std::fs::write(
+        directory.path().join("requirements.yml"),
+        "collections:\n  - name: example.missing\n    version: 1.0.0\n\
+         roles:\n  - name: geerlingguy.docker\n    version: 4.0.0\n",
+    )?;
+    let graph = extension.dependency_graph(directory.path(), &[])?;
+
+    let node = graph::DependencyNode {
+        name: "example.missing".to_string(),
+        version: "1.0.0".to_string(),
+    };
+    assert_eq!(graph.nodes().collect::<Vec<_>>(), vec![&node]);
+    assert_eq!(graph.dependencies(&node).count(), 0);
+    assert_eq!(
+        graph.unresolved,
+        vec![graph::UnresolvedDependency {
+            dependent: None,
+            name: "example.missing".to_string(),
+            version_requirement: "1.0.0".to_string(),
+        }]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_get_latest_version_skips_yanked_and_stops_early() -> Result<()> {
+    // The second versions page is not served, so reading it fails.
+    let root_url = serve_test_registry(|path| {
+        (path.contains("/example/test/versions/") && !path.contains("page=2")).then(|| {
+            serde_json::json!({
+                "data": [{"version": "2.0.0", "yanked": true}, {"version": "1.5.0"}],
+                "links": {"next": "/example/test/versions/?page=2"},
+            })
+        })
+    })?;
+    let registry = registry::Registry::private(&root_url)?;
+    let extension = AnsibleExtension::from_registries(vec![registry.clone()])?;
+    assert_eq!(
+        get_latest_version(&extension, &registry, "example.test", false, None)?,
+        Some("1.5.0".to_string())
+    );
+    Ok(())
+}
+
+#[test]
+fn test_is_entry_ansible_compatible() -> Result<()> {
+    let root_url = serve_test_registry(|path| {
+        path.ends_with("/example/test/versions/1.0.0/")
+            .then(|| serde_json::json!({"version": "1.0.0", "requires_ansible": ">=2.99.0"}))
+    })?;
+    let registry = registry::Registry::private(&root_url)?;
+    let extension = AnsibleExtension::from_registries(vec![registry.clone()])?;
+    let ansible_core_version = semver::Version::parse("2.14.0")?;
+    let is_compatible = |entry: serde_json::Value| {
+        is_entry_ansible_compatible(
+            &extension,
+            &registry,
+            "example.test",
+            &entry,
+            Some(&ansible_core_version),
+        )
+    };
+
+    // Read from the version detail when the versions list entry lacks the field.
+    assert!(!is_compatible(serde_json::json!({"version": "1.0.0"})));
+    assert!(is_compatible(
+        serde_json::json!({"version": "1.0.0", "requires_ansible": null})
+    ));
+    assert!(!is_compatible(
+        serde_json::json!({"version": "1.1.0", "requires_ansible": ">=2.15.0"})
+    ));
+    assert!(is_compatible(serde_json::json!({"version": "2.0.0"})));
+    assert!(is_entry_ansible_compatible(
+        &extension,
+        &registry,
+        "example.test",
+        &serde_json::json!({"version": "1.0.0"}),
+        None,
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_typosquat_targets() -> Result<()> {
+    let root_url = serve_test_registry(|path| {
+        path.contains("/collections/communty/general/")
+            .then(|| serde_json::json!({"namespace": "communty", "name": "general"}))
+    })?;
+    let extension =
+        AnsibleExtension::from_registries(vec![registry::Registry::private(&root_url)?])?;
+    assert_eq!(
+        extension.typosquat_targets("comunity.general")?,
+        vec!["community.general"]
+    );
+    assert!(extension.typosquat_targets("communty.general")?.is_empty());
+    assert!(extension.typosquat_targets("example.internal")?.is_empty());
+    Ok(())
+}
@@ -0,0 +1,134 @@
+use super::error;
+use super::registry;
+use anyhow::{format_err, Context, Result};
+
+/// Collection artifact found in a local directory registry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalRelease {
+    pub version: String,
+    pub artifact_url: url::Url,
+}
+
+/// Returns collection release from a directory of `<namespace>-<name>-<version>.tar.gz` files.
+///
+/// Versions are enumerated from file names. Without a version, the highest version is selected,
+/// skipping prereleases unless `include_prereleases` is set.
+pub fn get_local_release(
+    registry: &registry::Registry,
+    package_name: &str,
+    package_version: &Option<&str>,
+    include_prereleases: bool,
+) -> Result<LocalRelease> {
+    let directory = get_directory(registry)?;
+    let versions = get_local_versions(registry, package_name)?;
+    let version = match package_version {
+        Some(package_version) => versions
+            .iter()
+            .find(|version| version == package_version)
+            .ok_or_else(|| {
+                get_not_found_error(registry).context(format!(
+                    "Failed to find artifact for {} version {} in directory: {}",
+                    package_name,
+                    package_version,
+                    directory.display()
+                ))
+            })?,
+        None => versions
+            .iter()
+            .filter_map(|version| {
+                let semantic_version = super::resolution::parse_version(version)?;
+                if !super::resolution::is_selectable(&semantic_version, include_prereleases) {
+                    return None;
+                }
+                Some((semantic_version, version))
+            })
+            .max_by(|(a, _), (b, _)| super::resolution::compare_versions(a, b))
+            .map(|(_, version)| version)
+            .ok_or_else(|| {
+                get_not_found_error(registry).context(format!(
+                    "Failed to find artifact for {} in directory: {}",
+                    package_name,
+                    directory.display()
+                ))
+            })?,
+    };
+
+    let file_path = directory.join(get_artifact_file_name(package_name, version)?);
+    let artifact_url = url::Url::from_file_path(&file_path)
+        .map_err(|_| format_err!("Failed to parse file path: {}", file_path.display()))?;
+    Ok(LocalRelease {
+        version: version.clone(),
+        artifact_url,
+    })
+}
+
+/// Returns versions of the given collection found in a local directory registry.
+pub fn get_local_versions(
+    registry: &registry::Registry,
+    package_name: &str,
+) -> Result<Vec<String>> {
+    let directory = get_directory(registry)?;
+    let file_names = std::fs::read_dir(&directory)
+        .context(format!("Failed to read directory: {}", directory.display()))?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_file())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect::<Vec<_>>();
+    get_artifact_versions(&file_names, package_name)
+}
+
+fn get_not_found_error(registry: &registry::Registry) -> anyhow::Error {
+    anyhow::Error::new(error::AnsibleExtensionError::NotFound {
+        url: registry.root_url.to_string(),
+    })
+}
+
+fn get_directory(registry: &registry::Registry) -> Result<std::path::PathBuf> {
+    registry
+        .root_url
+        .to_file_path()
+        .map_err(|_| format_err!("Failed to parse directory: {}", registry.root_url))
+}
+
+/// Returns artifact file name prefix. For example: `community-general-`.
+fn get_artifact_prefix(package_name: &str) -> Result<String> {
+    let (namespace, name) = package_name.split_once('.').ok_or(format_err!(
+        "Failed to parse collection name: {}",
+        package_name
+    ))?;
+    Ok(format!("{}-{}-", namespace, name))
+}
+
+fn get_artifact_file_name(package_name: &str, version: &str) -> Result<String> {
+    Ok(format!(
+        "{}{}.tar.gz",
+        get_artifact_prefix(package_name)?,
+        version
+    ))
+}
+
+/// Returns versions of the given collection from artifact file names.
+fn get_artifact_versions(file_names: &[String], package_name: &str) -> Result<Vec<String>> {
+    let prefix = get_artifact_prefix(package_name)?;
+    Ok(file_names
+        .iter()
+        .filter_map(|file_name| file_name.strip_prefix(&prefix))
+        .filter_map(|file_name| file_name.strip_suffix(".tar.gz"))
+        .map(|version| version.to_string())
+        .collect())
+}
+
+#[test]
+fn test_get_artifact_versions() -> Result<()> {
+    let file_names = vec![
+        "community-general-3.0.0.tar.gz".to_string(),
+        "community-general-3.1.0-beta.1.tar.gz".to_string(),
+        "community-crypto-1.0.0.tar.gz".to_string(),
+        "community-general-3.0.0.tar.gz.sha256".to_string(),
+    ];
+    assert_eq!(
+        get_artifact_versions(&file_names, "community.general")?,
+        vec!["3.0.0".to_string(), "3.1.0-beta.1".to_string()]
+    );
+    Ok(())
+}
@@ -0,0 +1,241 @@
+use anyhow::{format_err, Context, Result};
+
+/// Lockfile name, written next to the project dependency file.
+pub static LOCKFILE_NAME: &str = "vouch-ansible.lock";
+
+/// Lockfile format version.
+static LOCKFILE_FORMAT_VERSION: u32 = 1;
+
+/// Dependency pinned to an exact version.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+pub struct LockedDependency {
+    /// Collection or role name, or git, OCI or tarball source.
+    pub name: String,
+    pub version: String,
+    pub registry_host_name: String,
+    pub artifact_url: String,
+}
+
+/// Exact versions of all project dependencies, including transitive collection dependencies.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Lockfile {
+    pub format_version: u32,
+
+    /// Dependencies, sorted by name then version.
+    pub dependencies: Vec<LockedDependency>,
+}
+
+impl Lockfile {
+    pub fn new(mut dependencies: Vec<LockedDependency>) -> Self {
+        dependencies.sort();
+        dependencies.dedup();
+        Self {
+            format_version: LOCKFILE_FORMAT_VERSION,
+            dependencies,
+        }
+    }
+
+    /// Returns the pinned version of a dependency. If several versions are pinned, for
+    /// example by conflicting transitive requirements, the highest is returned.
+    pub fn get_version(&self, name: &str) -> Option<&str> {
+        self.dependencies
+            .iter()
+            .filter(|dependency| dependency.name == name)
+            .max_by(|a, b| {
+                match (
+                    super::resolution::parse_version(&a.version),
+                    super::resolution::parse_version(&b.version),
+                ) {
+                    (Some(a), Some(b)) => super::resolution::compare_versions(&a, &b),
+                    (a, b) => a.is_some().cmp(&b.is_some()),
+                }
+            })
+            .map(|dependency| dependency.version.as_str())
+    }
+
+    /// Returns the pinned version of a dependency if it satisfies the requirement, as written,
+    /// of the dependency file. Pins and requirements which are not semver are not checked.
+    fn get_matching_version(&self, name: &str, requirements: &[(String, String)]) -> Option<&str> {
+        let version = self.get_version(name)?;
+        let requirement = requirements
+            .iter()
+            .find(|(requirement_name, _)| requirement_name == name)
+            .and_then(|(_, requirement)| {
+                super::resolution::VersionRequirement::parse(requirement).ok()
+            });
+        match (super::resolution::parse_version(version), requirement) {
+            (Some(parsed_version), Some(requirement))
+                if !requirement.matches(&parsed_version, true) =>
+            {
+                None
+            }
+            _ => Some(version),
+        }
+    }
+
+    /// Replace dependency versions with pinned versions. Dependencies which are not pinned,
+    /// such as those added after the lockfile was written, are unchanged unless
+    /// `require_pinned` is set, in which case they have no version. Pins which no longer
+    /// satisfy `requirements` are ignored, see `find_stale_pins`.
+    pub fn pin_dependencies(
+        &self,
+        dependencies: std::collections::HashSet<vouch_lib::extension::Dependency>,
+        requirements: &[(String, String)],
+        require_pinned: bool,
+    ) -> std::collections::HashSet<vouch_lib::extension::Dependency> {
+        dependencies
+            .into_iter()
+            .map(
+                |dependency| match self.get_matching_version(&dependency.name, requirements) {
+                    Some(version) => vouch_lib::extension::Dependency {
+                        version: Ok(version.to_string()),
+                        ..dependency
+                    },
+                    None if require_pinned => vouch_lib::extension::Dependency {
+                        version: Err(
+                            vouch_lib::extension::common::VersionError::from_missing_version(),
+                        ),
+                        ..dependency
+                    },
+                    None => dependency,
+                },
+            )
+            .collect()
+    }
+
+    /// Returns a diagnostic for each pin which no longer satisfies the requirement of the
+    /// dependency file, for example after the requirement was edited.
+    pub fn find_stale_pins(
+        &self,
+        dependency_file_path: &std::path::Path,
+        requirements: &[(String, String)],
+    ) -> Vec<super::diagnostic::DependencyDiagnostic> {
+        requirements
+            .iter()
+            .filter_map(|(name, requirement)| {
+                let version = self.get_version(name)?;
+                if self.get_matching_version(name, requirements).is_some() {
+                    return None;
+                }
+                Some(super::diagnostic::DependencyDiagnostic {
+                    path: dependency_file_path.to_path_buf(),
+                    entry: name.clone(),
+                    message: format!(
+                        "Locked version {} does not satisfy requirement {}. Pin ignored, \
+                         regenerate the lockfile.",
+                        version, requirement
+                    ),
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the lockfile path for a project dependency file.
+    pub fn get_path(dependency_file_path: &std::path::Path) -> std::path::PathBuf {
+        dependency_file_path.with_file_name(LOCKFILE_NAME)
+    }
+
+    /// Returns lockfile JSON. Output is identical for identical dependencies.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)? + "\n")
+    }
+
+    pub fn write(&self, path: &std::path::Path) -> Result<()> {
+        std::fs::write(path, self.to_json()?)
+            .context(format!("Failed to write lockfile: {}", path.display()))
+    }
+
+    pub fn read(path: &std::path::Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .context(format!("Failed to read lockfile: {}", path.display()))?;
+        let lockfile: Self = serde_json::from_str(&content)
+            .context(format!("Failed to parse lockfile: {}", path.display()))?;
+        if lockfile.format_version != LOCKFILE_FORMAT_VERSION {
+            return Err(format_err!(
+                "Unsupported lockfile format version {}: {}",
+                lockfile.format_version,
+                path.display()
+            ));
+        }
+        Ok(lockfile)
+    }
+}
+
+#[test]
+fn test_lockfile() -> Result<()> {
+    let dependency = |name: &str| LockedDependency {
+        name: name.to_string(),
+        version: "1.0.0".to_string(),
+        registry_host_name: "galaxy.ansible.com".to_string(),
+        artifact_url: format!("https://galaxy.ansible.com/download/{}-1.0.0.tar.gz", name),
+    };
+    let lockfile = Lockfile::new(vec![
+        dependency("community.general"),
+        dependency("ansible.posix"),
+        dependency("community.general"),
+    ]);
+    assert_eq!(
+        lockfile.dependencies,
+        vec![dependency("ansible.posix"), dependency("community.general")]
+    );
+
+    let dependencies = maplit::hashset! {
+        vouch_lib::extension::Dependency {
+            name: "ansible.posix".to_string(),
+            version: Err(vouch_lib::extension::common::VersionError::from_missing_version()),
+        },
+        vouch_lib::extension::Dependency {
+            name: "ansible.utils".to_string(),
+            version: Ok("2.0.0".to_string()),
+        },
+    };
+    assert_eq!(
+        lockfile.pin_dependencies(dependencies.clone(), &[], false),
+        maplit::hashset! {
+            vouch_lib::extension::Dependency {
+                name: "ansible.posix".to_string(),
+                version: Ok("1.0.0".to_string()),
+            },
+            vouch_lib::extension::Dependency {
+                name: "ansible.utils".to_string(),
+                version: Ok("2.0.0".to_string()),
+            },
+        }
+    );
+
+    assert!(lockfile
+        .pin_dependencies(dependencies.clone(), &[], true)
+        .iter()
+        .any(|dependency| dependency.name == "ansible.utils" && dependency.version.is_err()));
+
+    // A pin outside of an edited requirement is dropped.
+    let requirements = vec![
+        ("ansible.posix".to_string(), ">=2.0.0".to_string()),
+        ("community.general".to_string(), "1.*".to_string()),
+    ];
+    assert!(lockfile
+        .pin_dependencies(dependencies.clone(), &requirements, true)
+        .iter()
+        .any(|dependency| dependency.name == "ansible.posix" && dependency.version.is_err()));
+    assert!(lockfile
+        .pin_dependencies(dependencies, &requirements, false)
+        .iter()
+        .any(|dependency| dependency.name == "ansible.posix" && dependency.version.is_err()));
+    let dependency_file_path = std::path::Path::new("requirements.yml");
+    let stale_pins = lockfile.find_stale_pins(dependency_file_path, &requirements);
+    assert_eq!(stale_pins.len(), 1);
+    assert_eq!(stale_pins[0].entry, "ansible.posix");
+    assert_eq!(stale_pins[0].path, dependency_file_path);
+
+    let directory = std::env::temp_dir().join(format!(
+        "vouch-ansible-lockfile-test-{}",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&directory)?;
+    let path = Lockfile::get_path(&directory.join("requirements.yml"));
+    lockfile.write(&path)?;
+    assert_eq!(Lockfile::read(&path)?, lockfile);
+
+    std::fs::remove_dir_all(&directory)?;
+    Ok(())
+}
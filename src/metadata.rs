@@ -0,0 +1,488 @@
+use anyhow::Result;
+
+/// Supplementary collection metadata not covered by vouch registry package metadata.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct CollectionMetadata {
+    /// Fully qualified collection name. For example: `community.general`
+    pub name: String,
+    pub registry_host_name: String,
+
+    pub deprecated: bool,
+
+    /// Deprecation notice, when the registry provides one.
+    pub deprecation_message: Option<String>,
+
+    /// Total downloads across all versions, when reported by the registry.
+    pub download_count: Option<u64>,
+}
+
+/// Parse collection detail endpoint response.
+pub fn parse_collection_metadata(
+    collection_json: &serde_json::Value,
+    package_name: &str,
+    registry_host_name: &str,
+) -> Result<CollectionMetadata> {
+    let deprecated = collection_json["deprecated"].as_bool().unwrap_or(false);
+    let deprecation_message = collection_json["deprecation_message"]
+        .as_str()
+        .or_else(|| collection_json["deprecation"]["message"].as_str())
+        .filter(|message| deprecated && !message.is_empty())
+        .map(|message| message.to_string());
+    let download_count = collection_json["download_count"]
+        .as_u64()
+        .or_else(|| collection_json["download_count"].as_str()?.parse().ok());
+    Ok(CollectionMetadata {
+        name: package_name.to_string(),
+        registry_host_name: registry_host_name.to_string(),
+        deprecated,
+        deprecation_message,
+        download_count,
+    })
+}
+
+/// Documentation links for a collection version.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct DocumentationMetadata {
+    pub package_version: String,
+
+    /// Registry rendered module documentation for the version.
+    pub docs_url: String,
+
+    /// Publisher documentation link from galaxy.yml, if any.
+    pub documentation_url: Option<String>,
+
+    /// v3 docs-blob content, when requested.
+    pub docs_blob: Option<serde_json::Value>,
+}
+
+/// Returns rendered documentation URL given the collection human URL.
+pub fn get_docs_url(human_url: &url::Url, package_version: &str) -> Result<url::Url> {
+    let mut human_url = human_url.clone();
+    if !human_url.path().ends_with('/') {
+        human_url.set_path(&format!("{}/", human_url.path()));
+    }
+    let mut docs_url = human_url.join("docs/")?;
+    docs_url
+        .query_pairs_mut()
+        .append_pair("version", package_version);
+    Ok(docs_url)
+}
+
+#[test]
+fn test_get_docs_url() -> Result<()> {
+    let human_url = url::Url::parse("https://galaxy.ansible.com/community/general")?;
+    assert_eq!(
+        get_docs_url(&human_url, "3.0.0")?.as_str(),
+        "https://galaxy.ansible.com/community/general/docs/?version=3.0.0"
+    );
+    Ok(())
+}
+
+/// Publish details of a collection version.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct VersionMetadata {
+    pub name: String,
+    pub package_version: String,
+    pub registry_host_name: String,
+
+    /// Publish time as given by the registry, usually RFC 3339.
+    /// For example: `2021-06-22T14:05:12.961563Z`
+    pub created_at: Option<String>,
+
+    /// Last modification time as given by the registry.
+    pub updated_at: Option<String>,
+
+    /// Lowercase hex sha256 digest of the collection tarball as advertised by the registry.
+    pub sha256: Option<String>,
+
+    /// Collection tarball size in bytes as reported by the registry.
+    pub artifact_size: Option<u64>,
+
+    /// Detached GPG signatures of the collection MANIFEST.json file, published by galaxy_ng.
+    pub signatures: Vec<CollectionSignature>,
+
+    /// Source repository from the galaxy.yml `repository` field.
+    pub repository: Option<String>,
+
+    /// SPDX license identifiers. See `parse_licenses`.
+    pub licenses: Vec<String>,
+
+    /// Path of the license file within the collection, from the galaxy.yml `license_file` field.
+    pub license_file: Option<String>,
+}
+
+/// Detached signature of a collection version.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct CollectionSignature {
+    /// Fingerprint of the signing public key.
+    pub pubkey_fingerprint: String,
+
+    /// ASCII armored signature body.
+    pub signature: String,
+
+    /// Signing service name. For example: `ansible-default`
+    pub signing_service: Option<String>,
+}
+
+/// Parse v3 or v2 collection version detail endpoint response.
+pub fn parse_version_metadata(
+    entry_json: &serde_json::Value,
+    package_name: &str,
+    package_version: &str,
+    registry_host_name: &str,
+) -> VersionMetadata {
+    let get_string = |keys: &[&str]| {
+        keys.iter()
+            .find_map(|key| entry_json[*key].as_str())
+            .filter(|value| !value.is_empty())
+            .map(|value| value.to_string())
+    };
+    VersionMetadata {
+        name: package_name.to_string(),
+        package_version: package_version.to_string(),
+        registry_host_name: registry_host_name.to_string(),
+        created_at: get_string(&["created_at", "created", "pulp_created"]),
+        updated_at: get_string(&["updated_at", "modified", "pulp_last_updated"]),
+        sha256: parse_artifact_sha256(entry_json),
+        artifact_size: entry_json["artifact"]["size"].as_u64(),
+        signatures: parse_signatures(entry_json),
+        repository: entry_json["metadata"]["repository"]
+            .as_str()
+            .filter(|repository| !repository.is_empty())
+            .map(|repository| repository.to_string()),
+        licenses: parse_licenses(&entry_json["metadata"]),
+        license_file: entry_json["metadata"]["license_file"]
+            .as_str()
+            .filter(|license_file| !license_file.is_empty())
+            .map(|license_file| license_file.to_string()),
+    }
+}
+
+/// Common license names and deprecated identifiers, lowercase, by SPDX identifier.
+static LICENSE_ALIASES: &[(&str, &[&str])] = &[
+    (
+        "GPL-3.0-or-later",
+        &[
+            "gpl-3.0-or-later",
+            "gpl-3.0+",
+            "gplv3+",
+            "gpl v3+",
+            "gpl-3+",
+            "gnu general public license v3.0+",
+            "gnu general public license v3 or later",
+        ],
+    ),
+    (
+        "GPL-3.0-only",
+        &[
+            "gpl-3.0-only",
+            "gpl-3.0",
+            "gplv3",
+            "gpl v3",
+            "gpl3",
+            "gnu general public license v3.0",
+        ],
+    ),
+    (
+        "GPL-2.0-or-later",
+        &["gpl-2.0-or-later", "gpl-2.0+", "gplv2+", "gpl v2+"],
+    ),
+    (
+        "GPL-2.0-only",
+        &["gpl-2.0-only", "gpl-2.0", "gplv2", "gpl v2", "gpl2"],
+    ),
+    (
+        "LGPL-3.0-or-later",
+        &["lgpl-3.0-or-later", "lgpl-3.0+", "lgplv3+"],
+    ),
+    ("LGPL-3.0-only", &["lgpl-3.0-only", "lgpl-3.0", "lgplv3"]),
+    (
+        "Apache-2.0",
+        &[
+            "apache-2.0",
+            "apache 2.0",
+            "apache2",
+            "apache license 2.0",
+            "apache license, version 2.0",
+            "apache software license 2.0",
+        ],
+    ),
+    ("MIT", &["mit", "mit license"]),
+    ("BSD-2-Clause", &["bsd-2-clause", "simplified bsd"]),
+    ("BSD-3-Clause", &["bsd-3-clause", "new bsd", "modified bsd"]),
+    ("MPL-2.0", &["mpl-2.0", "mozilla public license 2.0"]),
+    ("ISC", &["isc", "isc license"]),
+];
+
+/// Returns the SPDX identifier for a license name.
+///
+/// Unrecognized names are returned trimmed but otherwise unchanged.
+pub fn normalize_license(license: &str) -> String {
+    let license = license.trim();
+    let key = license
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    LICENSE_ALIASES
+        .iter()
+        .find(|(_, aliases)| aliases.contains(&key.as_str()))
+        .map(|(identifier, _)| identifier.to_string())
+        .unwrap_or_else(|| license.to_string())
+}
+
+/// Returns normalized licenses of a version detail `metadata` object, or of a MANIFEST.json
+/// `collection_info` object. Both follow galaxy.yml, where `license` is a list or a string.
+pub fn parse_licenses(collection_info: &serde_json::Value) -> Vec<String> {
+    let licenses: Vec<&str> = match &collection_info["license"] {
+        serde_json::Value::Array(licenses) => licenses
+            .iter()
+            .filter_map(|license| license.as_str())
+            .collect(),
+        serde_json::Value::String(license) => vec![license.as_str()],
+        _ => Vec::new(),
+    };
+    let mut normalized_licenses = Vec::new();
+    for license in licenses
+        .into_iter()
+        .filter(|license| !license.trim().is_empty())
+    {
+        let license = normalize_license(license);
+        if !normalized_licenses.contains(&license) {
+            normalized_licenses.push(license);
+        }
+    }
+    normalized_licenses
+}
+
+#[test]
+fn test_parse_licenses() {
+    let collection_info = serde_json::json!({
+        "license": ["GPL-3.0+", "gplv3+", "Apache  License 2.0", "LicenseRef-Custom", ""]
+    });
+    assert_eq!(
+        parse_licenses(&collection_info),
+        vec!["GPL-3.0-or-later", "Apache-2.0", "LicenseRef-Custom"]
+    );
+    assert_eq!(
+        parse_licenses(&serde_json::json!({"license": "MIT License"})),
+        vec!["MIT"]
+    );
+    assert!(parse_licenses(&serde_json::json!({"license_file": "LICENSE"})).is_empty());
+}
+
+/// Returns the artifact sha256 digest of a v3 or v2 collection version detail response.
+pub fn parse_artifact_sha256(entry_json: &serde_json::Value) -> Option<String> {
+    entry_json["artifact"]["sha256"]
+        .as_str()
+        .filter(|digest| digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit()))
+        .map(|digest| digest.to_ascii_lowercase())
+}
+
+/// Returns the signatures of a v3 collection version detail response.
+///
+/// Entries without a signature body or key fingerprint are skipped.
+pub fn parse_signatures(entry_json: &serde_json::Value) -> Vec<CollectionSignature> {
+    let signatures = match entry_json["signatures"].as_array() {
+        Some(signatures) => signatures,
+        None => return Vec::new(),
+    };
+    signatures
+        .iter()
+        .filter_map(|signature| {
+            let get_string = |key: &str| {
+                signature[key]
+                    .as_str()
+                    .filter(|value| !value.is_empty())
+                    .map(|value| value.to_string())
+            };
+            Some(CollectionSignature {
+                pubkey_fingerprint: get_string("pubkey_fingerprint")?,
+                signature: get_string("signature")?,
+                signing_service: get_string("signing_service"),
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn test_parse_version_metadata() {
+    let entry_json = serde_json::json!({
+        "version": "3.0.0",
+        "created": "2021-06-22T14:05:12.961563Z",
+        "modified": "2021-06-23T09:00:00Z",
+        "metadata": {"license": ["GPL-3.0+"], "license_file": ""},
+        "artifact": {
+            "filename": "community-general-3.0.0.tar.gz",
+            "sha256": "A1B2C3D4E5F60718293A4B5C6D7E8F90A1B2C3D4E5F60718293A4B5C6D7E8F90",
+            "size": 1024,
+        },
+        "signatures": [
+            {
+                "signature": "-----BEGIN PGP SIGNATURE-----\n...\n-----END PGP SIGNATURE-----\n",
+                "pubkey_fingerprint": "2E4C1B7D5D3A8F0E9C6B4A2D1F0E8C7B6A5D4C3B",
+                "signing_service": "ansible-default",
+                "pulp_created": "2021-06-22T14:06:00Z",
+            },
+            {"signature": "", "pubkey_fingerprint": "unsigned"},
+        ],
+    });
+    let metadata = parse_version_metadata(
+        &entry_json,
+        "community.general",
+        "3.0.0",
+        "galaxy.ansible.com",
+    );
+    assert_eq!(
+        metadata.created_at,
+        Some("2021-06-22T14:05:12.961563Z".to_string())
+    );
+    assert_eq!(
+        metadata.updated_at,
+        Some("2021-06-23T09:00:00Z".to_string())
+    );
+    assert_eq!(
+        metadata.sha256,
+        Some("a1b2c3d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f60718293a4b5c6d7e8f90".to_string())
+    );
+
+    assert_eq!(
+        metadata.signatures,
+        vec![CollectionSignature {
+            pubkey_fingerprint: "2E4C1B7D5D3A8F0E9C6B4A2D1F0E8C7B6A5D4C3B".to_string(),
+            signature: "-----BEGIN PGP SIGNATURE-----\n...\n-----END PGP SIGNATURE-----\n"
+                .to_string(),
+            signing_service: Some("ansible-default".to_string()),
+        }]
+    );
+
+    assert_eq!(metadata.artifact_size, Some(1024));
+    assert_eq!(metadata.licenses, vec!["GPL-3.0-or-later"]);
+    assert_eq!(metadata.license_file, None);
+
+    let entry_json = serde_json::json!({"artifact": {"sha256": "not-a-digest"}});
+    assert_eq!(parse_artifact_sha256(&entry_json), None);
+}
+
+/// Published collection version.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct CollectionVersion {
+    /// Version as published. For example: `3.0.0` or `v1.2`
+    pub version: String,
+    pub is_prerelease: bool,
+    pub registry_host_name: String,
+}
+
+/// Collection publisher namespace details.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct NamespaceMetadata {
+    pub name: String,
+    pub registry_host_name: String,
+    pub company: Option<String>,
+    pub description: Option<String>,
+    pub email: Option<String>,
+    pub avatar_url: Option<String>,
+
+    /// Verified vendor or partner namespace.
+    pub verified: bool,
+
+    /// Owner user and group names.
+    pub owners: Vec<String>,
+
+    /// Namespace links as `(name, url)` pairs.
+    pub links: Vec<(String, String)>,
+}
+
+/// Parse v3 or v1 namespace endpoint response.
+pub fn parse_namespace_metadata(
+    namespace_json: &serde_json::Value,
+    namespace: &str,
+    registry_host_name: &str,
+) -> Result<NamespaceMetadata> {
+    let get_string = |key: &str| {
+        namespace_json[key]
+            .as_str()
+            .filter(|value| !value.is_empty())
+            .map(|value| value.to_string())
+    };
+    let get_array = |key: &str| namespace_json[key].as_array().cloned().unwrap_or_default();
+
+    let owners = get_array("owners")
+        .iter()
+        .chain(get_array("groups").iter())
+        .filter_map(|owner| {
+            owner
+                .as_str()
+                .or_else(|| owner["username"].as_str())
+                .or_else(|| owner["name"].as_str())
+        })
+        .map(|owner| owner.to_string())
+        .collect();
+    let links = get_array("links")
+        .iter()
+        .filter_map(|link| {
+            let url = link["url"].as_str()?;
+            let name = link["name"].as_str().unwrap_or(url);
+            Some((name.to_string(), url.to_string()))
+        })
+        .collect();
+    let verified = ["is_vendor", "partner", "verified"]
+        .iter()
+        .any(|key| namespace_json[*key].as_bool().unwrap_or(false));
+
+    Ok(NamespaceMetadata {
+        name: namespace.to_string(),
+        registry_host_name: registry_host_name.to_string(),
+        company: get_string("company"),
+        description: get_string("description"),
+        email: get_string("email"),
+        avatar_url: get_string("avatar_url"),
+        verified,
+        owners,
+        links,
+    })
+}
+
+#[test]
+fn test_parse_namespace_metadata() -> Result<()> {
+    let namespace_json = serde_json::json!({
+        "name": "community",
+        "company": "Ansible Community",
+        "description": "",
+        "groups": [{"name": "community_admins", "id": 1}],
+        "links": [{"name": "GitHub", "url": "https://github.com/ansible-collections"}],
+    });
+    let metadata = parse_namespace_metadata(&namespace_json, "community", "galaxy.ansible.com")?;
+    assert_eq!(metadata.company, Some("Ansible Community".to_string()));
+    assert_eq!(metadata.description, None);
+    assert_eq!(metadata.owners, vec!["community_admins".to_string()]);
+    assert_eq!(
+        metadata.links,
+        vec![(
+            "GitHub".to_string(),
+            "https://github.com/ansible-collections".to_string()
+        )]
+    );
+    assert!(!metadata.verified);
+    Ok(())
+}
+
+#[test]
+fn test_parse_collection_metadata() -> Result<()> {
+    let collection_json = serde_json::json!({
+        "namespace": "community",
+        "name": "kubernetes",
+        "deprecated": true,
+        "download_count": 125103,
+        "highest_version": {"version": "2.0.1"},
+    });
+    let metadata = parse_collection_metadata(
+        &collection_json,
+        "community.kubernetes",
+        "galaxy.ansible.com",
+    )?;
+    assert!(metadata.deprecated);
+    assert_eq!(metadata.deprecation_message, None);
+    assert_eq!(metadata.download_count, Some(125103));
+    Ok(())
+}
@@ -0,0 +1,338 @@
+use super::{error, fqcn, git, http, oci, registry, resolution, roles, tarball, AnsibleExtension};
+use anyhow::{format_err, Result};
+
+/// Async variant of the Ansible extension, for hosts already running a tokio runtime.
+///
+/// Registry collection lookups use the reqwest async client, so many lookups can be driven
+/// concurrently from one thread. Git, OCI, tarball, local directory and standalone role
+/// lookups are delegated to the blocking extension on the tokio blocking thread pool.
+#[derive(Debug, Clone)]
+pub struct AsyncAnsibleExtension {
+    extension: AnsibleExtension,
+    client: http::AsyncClient,
+}
+
+impl AsyncAnsibleExtension {
+    /// Wrap a blocking extension, sharing its registries and HTTP settings.
+    pub fn new(extension: AnsibleExtension) -> Result<Self> {
+        let client = http::build_async_client(&extension.http_config_)?;
+        Ok(Self { extension, client })
+    }
+
+    /// Returns the wrapped blocking extension.
+    pub fn extension(&self) -> &AnsibleExtension {
+        &self.extension
+    }
+
+    /// Async equivalent of `Extension::registries_package_metadata`.
+    pub async fn registries_package_metadata(
+        &self,
+        package_name: &str,
+        package_version: Option<&str>,
+    ) -> Result<Vec<vouch_lib::extension::RegistryPackageMetadata>> {
+        if git::is_git_source(package_name)
+            || oci::is_oci_source(package_name)
+            || tarball::is_tarball_url(package_name)
+        {
+            let extension = self.extension.clone();
+            let package_name = package_name.to_string();
+            let package_version = package_version.map(|v| v.to_string());
+            return spawn_blocking(move || {
+                vouch_lib::extension::Extension::registries_package_metadata(
+                    &extension,
+                    &package_name,
+                    &package_version.as_deref(),
+                )
+            })
+            .await;
+        }
+
+        fqcn::check_package_name(package_name)?;
+
+        // Query registries in priority order. The first registry which has the package is primary.
+        let mut registries_metadata = Vec::new();
+        let mut errors = Vec::new();
+        for registry in self.extension.configured_registries()? {
+            match self
+                .get_registry_package_metadata(registry, package_name, package_version)
+                .await
+            {
+                Ok(mut metadata) => {
+                    metadata.is_primary = registries_metadata.is_empty();
+                    registries_metadata.push(metadata);
+                }
+                Err(error) => errors.push((registry.host_name.clone(), error)),
+            }
+        }
+
+        if registries_metadata.is_empty() {
+            return Err(super::get_registries_error(
+                super::get_package_not_found_message(package_name),
+                errors,
+            ));
+        }
+        Ok(registries_metadata)
+    }
+
+    async fn get_registry_package_metadata(
+        &self,
+        registry: &registry::Registry,
+        package_name: &str,
+        package_version: Option<&str>,
+    ) -> Result<vouch_lib::extension::RegistryPackageMetadata> {
+        if registry.is_local() {
+            let extension = self.extension.clone();
+            let registry = registry.clone();
+            let package_name = package_name.to_string();
+            let package_version = package_version.map(|v| v.to_string());
+            return spawn_blocking(move || {
+                super::get_registry_package_metadata(
+                    &extension,
+                    &registry,
+                    &package_name,
+                    &package_version.as_deref(),
+                )
+            })
+            .await;
+        }
+
+        let (package_version, artifact_url) = match self
+            .get_collection_release(registry, package_name, package_version)
+            .await
+        {
+            Ok(release) => release,
+            Err(error) if error::is_not_found(&error) => {
+                let extension = self.extension.clone();
+                let role_registry = registry.clone();
+                let role_name = package_name.to_string();
+                let role_version = package_version.map(|v| v.to_string());
+                let release = spawn_blocking(move || {
+                    roles::get_role_release(
+                        &extension.client()?,
+                        &role_registry,
+                        &role_name,
+                        &role_version.as_deref(),
+                        extension.max_version_pages_,
+                    )
+                })
+                .await
+                .map_err(|role_error| super::get_role_fallback_error(error, role_error))?;
+                (release.version, release.artifact_url)
+            }
+            Err(error) => return Err(error),
+        };
+
+        let human_url = super::get_registry_human_url(registry, package_name)?;
+        Ok(vouch_lib::extension::RegistryPackageMetadata {
+            registry_host_name: registry.host_name.clone(),
+            human_url: human_url.to_string(),
+            artifact_url: artifact_url.to_string(),
+            is_primary: true,
+            package_version,
+        })
+    }
+
+    async fn get_collection_release(
+        &self,
+        registry: &registry::Registry,
+        package_name: &str,
+        package_version: Option<&str>,
+    ) -> Result<(String, url::Url)> {
+        let package_version = match package_version {
+            Some(package_version) => package_version.to_string(),
+            None => self.get_latest_version(registry, package_name).await?,
+        };
+        let url = registry.version_url(package_name, &package_version)?;
+        let entry_json = self.get_memoized_json(registry, &url).await?;
+        let artifact_url =
+            super::get_entry_artifact_url(&entry_json, registry, package_name, &package_version)?;
+        Ok((package_version, artifact_url))
+    }
+
+    /// Async equivalent of `get_latest_version`.
+    async fn get_latest_version(
+        &self,
+        registry: &registry::Registry,
+        package_name: &str,
+    ) -> Result<String> {
+        let include_prereleases = self.extension.include_prereleases_;
+        // Runs `ansible --version` on first use.
+        let extension = self.extension.clone();
+        let ansible_core_version =
+            spawn_blocking(move || Ok(extension.get_ansible_core_version(&[]))).await?;
+        if ansible_core_version.is_none() {
+            let url = registry.collection_url(package_name)?;
+            let highest_version = self
+                .get_memoized_json(registry, &url)
+                .await
+                .ok()
+                .and_then(|json| super::get_highest_version(&json, include_prereleases));
+            if let Some(highest_version) = highest_version {
+                return Ok(highest_version);
+            }
+        }
+
+        let is_wanted =
+            |version: &semver::Version| resolution::is_selectable(version, include_prereleases);
+        let mut pages = super::VersionPages::new(
+            registry,
+            package_name,
+            self.extension.max_version_pages_,
+            Some(&is_wanted),
+        )?;
+        while let Some(url) = pages.next_url() {
+            let json = self.get_memoized_json(registry, &url).await?;
+            let mut compatible_entries = Vec::new();
+            for entry in pages.read_page(&json)? {
+                if self
+                    .is_entry_ansible_compatible(
+                        registry,
+                        package_name,
+                        entry,
+                        ansible_core_version.as_ref(),
+                    )
+                    .await
+                {
+                    compatible_entries.push(entry);
+                }
+            }
+            pages.add_versions(compatible_entries.into_iter());
+        }
+        super::select_latest_version(&pages.into_versions(), include_prereleases)
+    }
+
+    /// Async equivalent of `is_entry_ansible_compatible`.
+    async fn is_entry_ansible_compatible(
+        &self,
+        registry: &registry::Registry,
+        package_name: &str,
+        version_entry: &serde_json::Value,
+        ansible_core_version: Option<&semver::Version>,
+    ) -> bool {
+        let ansible_core_version = match ansible_core_version {
+            Some(ansible_core_version) => ansible_core_version,
+            None => return true,
+        };
+        let requires_ansible = match super::get_entry_requires_ansible(version_entry) {
+            Some(requires_ansible) => {
+                requires_ansible.map(|requires_ansible| requires_ansible.to_string())
+            }
+            None => self
+                .get_entry_json(registry, package_name, version_entry)
+                .await
+                .ok()
+                .and_then(|entry_json| super::get_detail_requires_ansible(&entry_json)),
+        };
+        resolution::is_ansible_compatible(requires_ansible.as_deref(), ansible_core_version)
+    }
+
+    /// Returns the version detail of a version entry.
+    async fn get_entry_json(
+        &self,
+        registry: &registry::Registry,
+        package_name: &str,
+        version_entry: &serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        let version = super::get_version_entry_version(version_entry)?;
+        let url = registry.version_url(package_name, version)?;
+        self.get_memoized_json(registry, &url).await
+    }
+
+    /// Async equivalent of `AnsibleExtension::get_memoized_json`, sharing its memo.
+    async fn get_memoized_json(
+        &self,
+        registry: &registry::Registry,
+        url: &url::Url,
+    ) -> Result<serde_json::Value> {
+        if let Some(json) = self.extension.get_memoized_response(url)? {
+            return Ok(json);
+        }
+        let json = http::get_json_async(&self.client, registry, url.as_str()).await?;
+        self.extension.memoize_response(url, &json)?;
+        Ok(json)
+    }
+}
+
+/// Run blocking work on the tokio blocking thread pool.
+async fn spawn_blocking<T, F>(function: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(function)
+        .await
+        .map_err(|error| format_err!("Blocking task failed: {}", error))?
+}
+
+#[cfg(test)]
+fn block_on<F: std::future::Future>(future: F) -> Result<F::Output> {
+    Ok(tokio::runtime::Builder::new()
+        .basic_scheduler()
+        .enable_all()
+        .build()?
+        .block_on(future))
+}
+
+#[test]
+fn test_registries_package_metadata_latest_version() -> Result<()> {
+    // The second versions page is not served, so reading it fails.
+    let root_url = super::serve_test_registry(|path| {
+        if path.contains("/example/test/versions/1.5.0/") {
+            return Some(serde_json::json!({
+                "version": "1.5.0",
+                "download_url": "/download/example-test-1.5.0.tar.gz",
+            }));
+        }
+        (path.contains("/example/test/versions/") && !path.contains("page=2")).then(|| {
+            serde_json::json!({
+                "data": [
+                    {"version": "2.0.0", "yanked": true},
+                    {"version": "1.6.0-rc1"},
+                    {"version": "1.5.0"},
+                ],
+                "links": {"next": "/example/test/versions/?page=2"},
+            })
+        })
+    })?;
+    let extension = AsyncAnsibleExtension::new(AnsibleExtension::from_registries(vec![
+        registry::Registry::private(&root_url)?,
+    ])?)?;
+    // Hosts may spawn lookups onto a multi-threaded runtime.
+    fn assert_send<T: Send>(future: T) -> T {
+        future
+    }
+    let metadata = block_on(assert_send(
+        extension.registries_package_metadata("example.test", None),
+    ))??;
+    assert_eq!(metadata.len(), 1);
+    assert_eq!(metadata[0].package_version, "1.5.0");
+    assert!(metadata[0]
+        .artifact_url
+        .ends_with("/download/example-test-1.5.0.tar.gz"));
+    Ok(())
+}
+
+#[test]
+fn test_registries_package_metadata_role_fallback() -> Result<()> {
+    let root_url = super::serve_test_registry(|path| {
+        path.contains("owner__username=example&name=role").then(|| {
+            serde_json::json!({"results": [
+                {"id": 1, "github_user": "example", "github_repo": "ansible-role"},
+            ]})
+        })
+    })?;
+    let extension = AsyncAnsibleExtension::new(AnsibleExtension::from_registries(vec![
+        registry::Registry::private(&root_url)?,
+    ])?)?;
+    let metadata = block_on(extension.registries_package_metadata("example.role", Some("1.0.0")))??;
+    assert_eq!(
+        metadata[0].artifact_url,
+        "https://github.com/example/ansible-role/archive/1.0.0.tar.gz"
+    );
+
+    // Neither a collection nor a role: the collection error is kept.
+    let error = block_on(extension.registries_package_metadata("example.missing", Some("1.0.0")))?
+        .unwrap_err();
+    assert!(error::is_not_found(&error), "{:#}", error);
+    Ok(())
+}
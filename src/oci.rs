@@ -0,0 +1,325 @@
+use anyhow::{format_err, Context, Result};
+
+static MANIFEST_MEDIA_TYPES: &str =
+    "application/vnd.oci.image.manifest.v1+json, application/vnd.docker.distribution.manifest.v2+json";
+
+/// Collection artifact repository hosted on an OCI registry.
+///
+/// Given as `oci://<registry host>/<repository>`, optionally followed by `:<tag>` or by a
+/// manifest digest reference such as `@sha256:<hex>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OciSource {
+    pub host_name: String,
+    pub repository: String,
+    pub tag: Option<String>,
+
+    /// Manifest digest. For example: `sha256:4d2c...`
+    pub digest: Option<String>,
+}
+
+/// Returns true if the package name refers to an OCI repository.
+pub fn is_oci_source(package_name: &str) -> bool {
+    package_name.starts_with("oci://")
+}
+
+impl OciSource {
+    pub fn parse(package_name: &str) -> Result<Self> {
+        let reference = package_name
+            .strip_prefix("oci://")
+            .ok_or(format_err!("Failed to parse OCI source: {}", package_name))?;
+        let (host_name, repository) = reference.split_once('/').ok_or(format_err!(
+            "OCI source is missing a repository: {}",
+            package_name
+        ))?;
+
+        // A digest follows `@`, otherwise a tag follows the last colon of the repository path.
+        // Port colons belong to the host.
+        let (repository, tag, digest) = match repository.split_once('@') {
+            Some((_, digest)) if !digest.contains(':') => {
+                return Err(format_err!(
+                    "Failed to parse OCI digest reference: {}",
+                    package_name
+                ))
+            }
+            Some((repository, digest)) => (repository, None, Some(digest.to_string())),
+            None => match repository.rsplit_once(':') {
+                Some((repository, tag)) => (repository, Some(tag.to_string()), None),
+                None => (repository, None, None),
+            },
+        };
+        Ok(Self {
+            host_name: host_name.to_string(),
+            repository: repository.trim_end_matches('/').to_string(),
+            tag,
+            digest,
+        })
+    }
+
+    /// Returns repository web URL.
+    ///
+    /// Registries such as ghcr.io and quay.io redirect it to their repository page.
+    pub fn human_url(&self) -> Result<url::Url> {
+        Ok(url::Url::parse(&format!(
+            "https://{}/{}",
+            self.host_name, self.repository
+        ))?)
+    }
+
+    fn api_url(&self, path: &str) -> Result<url::Url> {
+        Ok(url::Url::parse(&format!(
+            "https://{}/v2/{}/{}",
+            self.host_name, self.repository, path
+        ))?)
+    }
+
+    /// Returns tags list endpoint URL.
+    pub fn tags_url(&self) -> Result<url::Url> {
+        self.api_url("tags/list")
+    }
+
+    /// Returns manifest endpoint URL for the given tag or digest.
+    pub fn manifest_url(&self, reference: &str) -> Result<url::Url> {
+        self.api_url(&format!("manifests/{}", reference))
+    }
+
+    /// Returns blob download URL for the given digest.
+    pub fn blob_url(&self, digest: &str) -> Result<url::Url> {
+        self.api_url(&format!("blobs/{}", digest))
+    }
+}
+
+/// OCI hosted collection release.
+#[derive(Debug, Clone)]
+pub struct OciRelease {
+    /// Tag, or manifest digest for digest references.
+    pub version: String,
+
+    pub human_url: url::Url,
+
+    /// Blob URL of the collection tarball layer. Registries usually require an anonymous
+    /// bearer token to download it, see `open_blob`.
+    pub artifact_url: url::Url,
+}
+
+/// Resolve an OCI hosted collection using the OCI distribution API.
+///
+/// The tag is the given version, the tag or digest embedded in the source, or else the highest
+/// version tag. The artifact URL is the blob URL of the manifest's collection tarball layer.
+pub fn get_oci_release(
+    client: &reqwest::blocking::Client,
+    package_name: &str,
+    package_version: &Option<&str>,
+    include_prereleases: bool,
+) -> Result<OciRelease> {
+    let source = OciSource::parse(package_name)?;
+    let tag = match package_version
+        .map(|v| v.to_string())
+        .or_else(|| source.tag.clone())
+        .or_else(|| source.digest.clone())
+    {
+        Some(tag) => tag,
+        None => {
+            let tags_json = get_json(client, &source.tags_url()?, "application/json")?;
+            select_latest_tag(&tags_json, include_prereleases)?
+        }
+    };
+
+    let manifest_url = source.manifest_url(&tag)?;
+    let manifest_json = get_json(client, &manifest_url, MANIFEST_MEDIA_TYPES)?;
+    let digest = get_tarball_layer_digest(&manifest_json)?;
+
+    let artifact_url = source.blob_url(digest)?;
+    super::registry::check_remote_url(&artifact_url)?;
+    Ok(OciRelease {
+        artifact_url,
+        human_url: source.human_url()?,
+        version: tag,
+    })
+}
+
+/// Open a blob, such as a release artifact URL, for streaming.
+pub fn open_blob(
+    client: &reqwest::blocking::Client,
+    url: &url::Url,
+) -> Result<Box<dyn std::io::Read>> {
+    Ok(Box::new(send(client, url, "*/*")?))
+}
+
+/// GET JSON from an OCI registry.
+fn get_json(
+    client: &reqwest::blocking::Client,
+    url: &url::Url,
+    accept: &str,
+) -> Result<serde_json::Value> {
+    let body = send(client, url, accept)?.text()?;
+    serde_json::from_str(&body).context(format!("JSON was not well-formatted:\n{}", body))
+}
+
+/// GET from an OCI registry, using the anonymous bearer token flow when challenged.
+fn send(
+    client: &reqwest::blocking::Client,
+    url: &url::Url,
+    accept: &str,
+) -> Result<reqwest::blocking::Response> {
+    let request = || {
+        client
+            .get(url.clone())
+            .header(reqwest::header::ACCEPT, accept)
+    };
+    let mut response = request().send()?;
+    if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+        let challenge = response
+            .headers()
+            .get(reqwest::header::WWW_AUTHENTICATE)
+            .and_then(|value| value.to_str().ok())
+            .ok_or(format_err!("Unauthorized without challenge: {}", url))?;
+        let token = get_bearer_token(client, challenge)?;
+        response = request().bearer_auth(token).send()?;
+    }
+    response
+        .error_for_status()
+        .context(format!("Failed to query OCI registry: {}", url))
+}
+
+/// Request an anonymous bearer token for a `WWW-Authenticate: Bearer` challenge.
+fn get_bearer_token(client: &reqwest::blocking::Client, challenge: &str) -> Result<String> {
+    let parameters = parse_bearer_challenge(challenge)?;
+    let realm = parameters.get("realm").ok_or(format_err!(
+        "Bearer challenge is missing realm: {}",
+        challenge
+    ))?;
+    let mut token_url = url::Url::parse(realm)?;
+    super::registry::check_remote_url(&token_url)?;
+    for key in &["service", "scope"] {
+        if let Some(value) = parameters.get(*key) {
+            token_url.query_pairs_mut().append_pair(key, value);
+        }
+    }
+
+    let body = client
+        .get(token_url.clone())
+        .send()?
+        .error_for_status()
+        .context(format!("Failed to request OCI token: {}", token_url))?
+        .text()?;
+    let json: serde_json::Value =
+        serde_json::from_str(&body).context(format!("JSON was not well-formatted:\n{}", body))?;
+    json["token"]
+        .as_str()
+        .or_else(|| json["access_token"].as_str())
+        .map(|token| token.to_string())
+        .ok_or(format_err!("Failed to parse OCI token response."))
+}
+
+static BEARER_PARAMETER_REGEX: once_cell::sync::Lazy<regex::Regex> =
+    once_cell::sync::Lazy::new(|| regex::Regex::new(r#"(\w+)="([^"]*)""#).unwrap());
+
+/// Parse `Bearer realm="...",service="...",scope="..."` challenge parameters.
+fn parse_bearer_challenge(challenge: &str) -> Result<std::collections::HashMap<String, String>> {
+    let parameters = challenge.trim().strip_prefix("Bearer ").ok_or(format_err!(
+        "Unsupported authentication challenge: {}",
+        challenge
+    ))?;
+    Ok(BEARER_PARAMETER_REGEX
+        .captures_iter(parameters)
+        .map(|captures| (captures[1].to_string(), captures[2].to_string()))
+        .collect())
+}
+
+/// Select highest version tag from a tags list response.
+///
+/// Prereleases are skipped unless `include_prereleases` is set.
+fn select_latest_tag(tags_json: &serde_json::Value, include_prereleases: bool) -> Result<String> {
+    tags_json["tags"]
+        .as_array()
+        .ok_or(format_err!("Failed to parse OCI tags list."))?
+        .iter()
+        .filter_map(|tag| tag.as_str())
+        .filter_map(|tag| Some((super::resolution::parse_version(tag)?, tag)))
+        .filter(|(version, _)| super::resolution::is_selectable(version, include_prereleases))
+        .max_by(|(a, _), (b, _)| super::resolution::compare_versions(a, b))
+        .map(|(_, tag)| tag.to_string())
+        .ok_or(format_err!("Failed to find any stable version tags."))
+}
+
+/// Returns digest of the collection tarball layer.
+///
+/// Prefers a gzip layer or a layer titled `*.tar.gz`, falling back to the only layer.
+fn get_tarball_layer_digest(manifest_json: &serde_json::Value) -> Result<&str> {
+    let layers = manifest_json["layers"]
+        .as_array()
+        .ok_or(format_err!("Failed to parse OCI manifest layers."))?;
+    let layer = layers
+        .iter()
+        .find(|layer| {
+            let media_type = layer["mediaType"].as_str().unwrap_or("");
+            let title = layer["annotations"]["org.opencontainers.image.title"]
+                .as_str()
+                .unwrap_or("");
+            media_type.ends_with("gzip") || title.ends_with(".tar.gz")
+        })
+        .or_else(|| {
+            if layers.len() == 1 {
+                layers.first()
+            } else {
+                None
+            }
+        })
+        .ok_or(format_err!("Failed to find collection tarball layer."))?;
+    layer["digest"]
+        .as_str()
+        .ok_or(format_err!("Failed to parse OCI layer digest."))
+}
+
+#[test]
+fn test_oci_source() -> Result<()> {
+    let source = OciSource::parse("oci://ghcr.io/org/collections/community.general:3.0.0")?;
+    assert_eq!(source.tag, Some("3.0.0".to_string()));
+    assert_eq!(
+        source.blob_url("sha256:abc")?.as_str(),
+        "https://ghcr.io/v2/org/collections/community.general/blobs/sha256:abc"
+    );
+
+    assert_eq!(
+        source.human_url()?.as_str(),
+        "https://ghcr.io/org/collections/community.general"
+    );
+
+    let source = OciSource::parse("oci://registry.example.com:5000/community.general")?;
+    assert_eq!(source.host_name, "registry.example.com:5000");
+    assert_eq!(source.tag, None);
+
+    let source = OciSource::parse("oci://quay.io/org/community.general@sha256:4d2c")?;
+    assert_eq!(source.repository, "org/community.general");
+    assert_eq!(source.tag, None);
+    assert_eq!(source.digest, Some("sha256:4d2c".to_string()));
+    assert_eq!(
+        source.manifest_url("sha256:4d2c")?.as_str(),
+        "https://quay.io/v2/org/community.general/manifests/sha256:4d2c"
+    );
+    assert!(OciSource::parse("oci://quay.io/org/community.general@4d2c").is_err());
+    assert_eq!(
+        select_latest_tag(
+            &serde_json::json!({"tags": ["1.0.0", "v1.2.0", "latest"]}),
+            false
+        )?,
+        "v1.2.0"
+    );
+
+    let manifest_json = serde_json::json!({"layers": [{
+        "mediaType": "application/vnd.oci.image.layer.v1.tar+gzip",
+        "digest": "sha256:abc",
+    }]});
+    assert_eq!(get_tarball_layer_digest(&manifest_json)?, "sha256:abc");
+    Ok(())
+}
+
+#[test]
+fn test_parse_bearer_challenge() -> Result<()> {
+    let parameters = parse_bearer_challenge(
+        r#"Bearer realm="https://ghcr.io/token",service="ghcr.io",scope="repository:org/repo:pull""#,
+    )?;
+    assert_eq!(parameters["realm"], "https://ghcr.io/token");
+    assert_eq!(parameters["scope"], "repository:org/repo:pull");
+    Ok(())
+}
@@ -0,0 +1,73 @@
+/// Default maximum number of concurrent lookups in batch operations.
+pub static DEFAULT_MAX_PARALLELISM: usize = 8;
+
+/// Returns batch parallelism bound.
+///
+/// Can be overridden using the VOUCH_ANSIBLE_MAX_PARALLELISM environment variable.
+pub fn get_max_parallelism() -> usize {
+    std::env::var("VOUCH_ANSIBLE_MAX_PARALLELISM")
+        .ok()
+        .and_then(|value| value.parse::<usize>().ok())
+        .filter(|value| *value > 0)
+        .unwrap_or(DEFAULT_MAX_PARALLELISM)
+}
+
+/// Apply function to each item using at most `max_parallelism` threads.
+///
+/// Results are returned in item order.
+pub fn map_bounded<T, R, F>(items: &[T], max_parallelism: usize, function: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let thread_count = max_parallelism.max(1).min(items.len());
+    if thread_count <= 1 {
+        return items.iter().map(function).collect();
+    }
+
+    let next_index = std::sync::atomic::AtomicUsize::new(0);
+    let results = std::sync::Mutex::new(Vec::with_capacity(items.len()));
+    std::thread::scope(|scope| {
+        for _ in 0..thread_count {
+            scope.spawn(|| loop {
+                let index = next_index.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                let item = match items.get(index) {
+                    Some(item) => item,
+                    None => break,
+                };
+                let result = function(item);
+                match results.lock() {
+                    Ok(mut results) => results.push((index, result)),
+                    Err(poisoned) => poisoned.into_inner().push((index, result)),
+                }
+            });
+        }
+    });
+
+    let mut results = match results.into_inner() {
+        Ok(results) => results,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+#[test]
+fn test_map_bounded() {
+    let items: Vec<u64> = (0..20).collect();
+    let active = std::sync::atomic::AtomicUsize::new(0);
+    let max_active = std::sync::atomic::AtomicUsize::new(0);
+    let results = map_bounded(&items, 4, |item| {
+        let count = active.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+        max_active.fetch_max(count, std::sync::atomic::Ordering::SeqCst);
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        active.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+        item * 2
+    });
+    assert_eq!(
+        results,
+        items.iter().map(|item| item * 2).collect::<Vec<_>>()
+    );
+    assert!(max_active.load(std::sync::atomic::Ordering::SeqCst) <= 4);
+}
@@ -0,0 +1,823 @@
+use super::auth;
+use super::config;
+use anyhow::{format_err, Result};
+
+static GALAXY_HOST_NAME: &str = "galaxy.ansible.com";
+static AUTOMATION_HUB_HOST_NAME: &str = "console.redhat.com";
+static AUTOMATION_HUB_AUTH_URL: &str =
+    "https://sso.redhat.com/auth/realms/redhat-external/protocol/openid-connect/token";
+
+/// Galaxy API version used for versions endpoints.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ApiVersion {
+    V2,
+    V3,
+}
+
+impl ApiVersion {
+    fn path(&self) -> &str {
+        match self {
+            Self::V2 => "v2",
+            Self::V3 => "v3",
+        }
+    }
+}
+
+/// Red Hat Automation Hub content repositories.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AutomationHubRepository {
+    /// Red Hat certified content. Synced into private hubs as `rh-certified`.
+    Certified,
+    /// Red Hat validated content.
+    Validated,
+}
+
+impl AutomationHubRepository {
+    /// Returns repository distribution base path.
+    pub fn base_path(&self) -> &str {
+        match self {
+            Self::Certified => "published",
+            Self::Validated => "validated",
+        }
+    }
+}
+
+impl std::str::FromStr for AutomationHubRepository {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "certified" | "rh-certified" | "published" => Ok(Self::Certified),
+            "validated" => Ok(Self::Validated),
+            _ => Err(format_err!("Unknown Automation Hub repository: {}", value)),
+        }
+    }
+}
+
+/// Galaxy API compatible package registry.
+#[derive(Debug, Clone)]
+pub struct Registry {
+    pub host_name: String,
+    pub root_url: url::Url,
+
+    /// API path prefix relative to the root URL. For example: `api/`.
+    ///
+    /// A prefix starting with `/` replaces any root URL path.
+    pub api_prefix: String,
+
+    /// Pulp distribution base path. For example: `published`, `staging` or `rh-certified`.
+    ///
+    /// When set, API URLs are built under `<api_prefix>content/<distribution>/`.
+    pub distribution: Option<String>,
+
+    pub api_version: ApiVersion,
+    pub human_url_template: String,
+
+    /// Offline (refresh) token or API token.
+    pub token: Option<String>,
+
+    /// SSO endpoint used to exchange an offline token for an access token.
+    pub auth_url: Option<url::Url>,
+
+    /// Tolerate the response shapes of Artifactory and Nexus Ansible proxies.
+    pub proxy_compatibility: bool,
+
+    /// Mirror root URLs tried in order when the registry is unavailable.
+    pub mirror_urls: Vec<url::Url>,
+
+    access_token_: std::sync::Arc<std::sync::Mutex<Option<auth::AccessToken>>>,
+}
+
+impl Registry {
+    /// Returns the public Ansible Galaxy registry.
+    pub fn galaxy() -> Self {
+        Self {
+            host_name: GALAXY_HOST_NAME.to_string(),
+            root_url: url::Url::parse("https://galaxy.ansible.com").unwrap(),
+            api_prefix: "/api/".to_string(),
+            distribution: None,
+            api_version: ApiVersion::V2,
+            human_url_template: "https://galaxy.ansible.com/{{package_name}}".to_string(),
+            token: None,
+            auth_url: None,
+            proxy_compatibility: false,
+            mirror_urls: Vec::new(),
+            access_token_: Default::default(),
+        }
+    }
+
+    /// Returns a self-hosted galaxy_ng or Pulp registry served from the given root URL.
+    ///
+    /// Defaults to the standard galaxy_ng layout. The API prefix and human URL template
+    /// can be adjusted for installations behind custom paths.
+    pub fn private(root_url: &url::Url) -> Result<Self> {
+        // Without a trailing slash, relative joins would replace the last path segment.
+        let mut root_url = root_url.clone();
+        if !root_url.path().ends_with('/') {
+            root_url.set_path(&format!("{}/", root_url.path()));
+        }
+        let root_url = &root_url;
+        let host_name = root_url
+            .host_str()
+            .ok_or(format_err!("Registry URL is missing a host: {}", root_url))?
+            .to_string();
+        let human_url_template = root_url
+            .join("ui/repo/{{distribution}}/{{package_name}}/")?
+            .to_string()
+            .replace("%7B", "{")
+            .replace("%7D", "}");
+        Ok(Self {
+            host_name,
+            root_url: root_url.clone(),
+            api_prefix: "api/galaxy/".to_string(),
+            distribution: None,
+            api_version: ApiVersion::V3,
+            human_url_template,
+            token: None,
+            auth_url: None,
+            proxy_compatibility: is_package_proxy_url(root_url),
+            mirror_urls: Vec::new(),
+            access_token_: Default::default(),
+        })
+    }
+
+    /// Returns an air-gapped registry backed by a directory of built collection artifacts.
+    ///
+    /// Artifacts are named `<namespace>-<name>-<version>.tar.gz`, as produced by
+    /// `ansible-galaxy collection build` or `download`. No network requests are made.
+    /// The host name is `local:` followed by the directory name.
+    pub fn local(directory: &std::path::Path) -> Result<Self> {
+        let root_url = url::Url::from_directory_path(directory).map_err(|_| {
+            format_err!(
+                "Registry directory must be an absolute path: {}",
+                directory.display()
+            )
+        })?;
+        let directory_name = directory
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Ok(Self {
+            host_name: format!("local:{}", directory_name),
+            root_url,
+            api_prefix: String::new(),
+            distribution: None,
+            api_version: ApiVersion::V3,
+            human_url_template: String::new(),
+            token: None,
+            auth_url: None,
+            proxy_compatibility: false,
+            mirror_urls: Vec::new(),
+            access_token_: Default::default(),
+        })
+    }
+
+    /// Returns the Red Hat Automation Hub registry for the given repository.
+    ///
+    /// The offline token is obtained from https://console.redhat.com/ansible/automation-hub/token
+    pub fn automation_hub(repository: AutomationHubRepository, offline_token: &str) -> Self {
+        Self {
+            host_name: AUTOMATION_HUB_HOST_NAME.to_string(),
+            root_url: url::Url::parse("https://console.redhat.com").unwrap(),
+            api_prefix: "/api/automation-hub/".to_string(),
+            distribution: Some(repository.base_path().to_string()),
+            api_version: ApiVersion::V3,
+            human_url_template:
+                "https://console.redhat.com/ansible/automation-hub/repo/{{distribution}}/{{package_name}}"
+                    .to_string(),
+            token: Some(offline_token.to_string()),
+            auth_url: Some(url::Url::parse(AUTOMATION_HUB_AUTH_URL).unwrap()),
+            proxy_compatibility: false,
+            mirror_urls: Vec::new(),
+            access_token_: Default::default(),
+        }
+    }
+
+    /// Returns registry for an ansible.cfg Galaxy server definition.
+    pub fn from_galaxy_server(server: &config::GalaxyServer) -> Result<Self> {
+        let url = url::Url::parse(&server.url)?;
+        if url.scheme() == "file" {
+            let directory = url
+                .to_file_path()
+                .map_err(|_| format_err!("Failed to parse directory: {}", url))?;
+            return Self::local(&directory);
+        }
+        let mut registry = match url.host_str() {
+            Some(host) if host == GALAXY_HOST_NAME => Self::galaxy(),
+            Some(host) if host == AUTOMATION_HUB_HOST_NAME => {
+                let repository = if url.path().contains("content/validated") {
+                    AutomationHubRepository::Validated
+                } else {
+                    AutomationHubRepository::Certified
+                };
+                Self::automation_hub(repository, "")
+            }
+            _ => {
+                let mut registry = Self::private(&url.join("/")?)?;
+                let mut path = url.path().to_string();
+                if !path.ends_with('/') {
+                    path.push('/');
+                }
+                if !path.contains("/api/") {
+                    path.push_str("api/");
+                }
+                registry.api_prefix = path;
+                registry.proxy_compatibility = is_package_proxy_url(&url);
+                registry
+            }
+        };
+
+        registry.token = server.token.clone();
+        if let Some(auth_url) = &server.auth_url {
+            registry.auth_url = Some(url::Url::parse(auth_url)?);
+        }
+        if let Some(proxy_compatibility) = server.proxy_compatibility {
+            registry.proxy_compatibility = proxy_compatibility;
+        }
+        if let Some(distribution) = &server.distribution {
+            registry.distribution = Some(distribution.clone());
+        }
+        registry.mirror_urls = server
+            .mirrors
+            .iter()
+            .map(|mirror| url::Url::parse(mirror))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(registry)
+    }
+
+    /// Returns true if the registry is a local artifacts directory.
+    pub fn is_local(&self) -> bool {
+        self.root_url.scheme() == "file"
+    }
+
+    /// Returns Authorization header value for API requests, if a token is configured.
+    ///
+    /// Galaxy API tokens use the `Token` scheme. When an SSO `auth_url` is set, the token
+    /// is an offline token which is exchanged for a short-lived `Bearer` access token.
+    pub fn authorization_header(
+        &self,
+        client: &reqwest::blocking::Client,
+    ) -> Result<Option<String>> {
+        let token = match &self.token {
+            Some(token) => token,
+            None => return Ok(None),
+        };
+        match &self.auth_url {
+            Some(auth_url) => {
+                let mut access_token = self
+                    .access_token_
+                    .lock()
+                    .map_err(|_| format_err!("Access token lock poisoned."))?;
+                let token = match access_token.as_ref() {
+                    Some(access_token) if access_token.is_valid() => access_token.token.clone(),
+                    _ => {
+                        let new_access_token =
+                            auth::exchange_offline_token(client, auth_url, token)?;
+                        let token = new_access_token.token.clone();
+                        *access_token = Some(new_access_token);
+                        token
+                    }
+                };
+                Ok(Some(format!("Bearer {}", token)))
+            }
+            None => Ok(Some(format!("Token {}", token))),
+        }
+    }
+
+    /// Returns Authorization header value for API requests made with an async client.
+    #[cfg(feature = "async")]
+    pub async fn authorization_header_async(
+        &self,
+        client: &reqwest::Client,
+    ) -> Result<Option<String>> {
+        let token = match &self.token {
+            Some(token) => token,
+            None => return Ok(None),
+        };
+        let auth_url = match &self.auth_url {
+            Some(auth_url) => auth_url,
+            None => return Ok(Some(format!("Token {}", token))),
+        };
+
+        // The lock is not held across the token exchange.
+        let cached_token = self
+            .access_token_
+            .lock()
+            .map_err(|_| format_err!("Access token lock poisoned."))?
+            .as_ref()
+            .filter(|access_token| access_token.is_valid())
+            .map(|access_token| access_token.token.clone());
+        let token = match cached_token {
+            Some(token) => token,
+            None => {
+                let new_access_token =
+                    auth::exchange_offline_token_async(client, auth_url, token).await?;
+                let token = new_access_token.token.clone();
+                *self
+                    .access_token_
+                    .lock()
+                    .map_err(|_| format_err!("Access token lock poisoned."))? =
+                    Some(new_access_token);
+                token
+            }
+        };
+        Ok(Some(format!("Bearer {}", token)))
+    }
+
+    /// Returns true if the registry token may be sent with a request to the given URL.
+    ///
+    /// Tokens are only sent to the registry origin, never to mirrors or other hosts.
+    pub fn is_token_url(&self, url: &str) -> bool {
+        url::Url::parse(url)
+            .map(|url| url.origin() == self.root_url.origin())
+            .unwrap_or(false)
+    }
+
+    /// Returns the given registry URL followed by its equivalent on each mirror.
+    pub fn mirrored_urls(&self, url: &str) -> Vec<String> {
+        let mut urls = vec![url.to_string()];
+        if let Some(path) = url.strip_prefix(self.root_url.as_str()) {
+            for mirror_url in &self.mirror_urls {
+                let mut mirror_url = mirror_url.to_string();
+                if !mirror_url.ends_with('/') {
+                    mirror_url.push('/');
+                }
+                urls.push(mirror_url + path);
+            }
+        }
+        urls
+    }
+
+    /// Returns API base URL, including any distribution path.
+    pub fn api_url(&self) -> Result<url::Url> {
+        let api_url = self.root_url.join(&self.api_prefix)?;
+        Ok(match &self.distribution {
+            Some(distribution) => api_url.join(&format!("content/{}/", distribution))?,
+            None => api_url,
+        })
+    }
+
+    /// Returns distribution name used in human URLs.
+    pub fn human_url_distribution(&self) -> &str {
+        self.distribution.as_deref().unwrap_or("published")
+    }
+
+    /// Returns collection detail endpoint URL.
+    pub fn collection_url(&self, package_name: &str) -> Result<url::Url> {
+        let mut segments = vec!["v3", "collections"];
+        segments.extend(package_name.split('.'));
+        segments.push("");
+        join_path_segments(&self.api_url()?, &segments)
+    }
+
+    /// Returns collection versions endpoint URL.
+    pub fn versions_url(&self, package_name: &str) -> Result<url::Url> {
+        let mut segments = vec![self.api_version.path(), "collections"];
+        segments.extend(package_name.split('.'));
+        segments.extend(&["versions", ""]);
+        join_path_segments(&self.api_url()?, &segments)
+    }
+
+    /// Returns versions endpoint URL for listing versions, newest first.
+    ///
+    /// Newest first ordering means the newest releases are seen even when enumeration is capped.
+    /// Package proxies are given the plain versions endpoint URL.
+    pub fn versions_list_url(&self, package_name: &str) -> Result<url::Url> {
+        let mut url = self.versions_url(package_name)?;
+        if !self.proxy_compatibility {
+            url.query_pairs_mut().append_pair("ordering", "-version");
+        }
+        Ok(url)
+    }
+
+    /// Returns collection version detail endpoint URL.
+    pub fn version_url(&self, package_name: &str, package_version: &str) -> Result<url::Url> {
+        join_path_segments(&self.versions_url(package_name)?, &[package_version, ""])
+    }
+
+    /// Returns v3 namespace detail endpoint URL.
+    pub fn namespace_url(&self, namespace: &str) -> Result<url::Url> {
+        join_path_segments(
+            &self.root_url.join(&self.api_prefix)?,
+            &["v3", "namespaces", namespace, ""],
+        )
+    }
+
+    /// Returns collection search endpoint URL, selecting highest versions only.
+    pub fn search_url(&self, query: &str) -> Result<url::Url> {
+        let mut url = self
+            .root_url
+            .join(&self.api_prefix)?
+            .join("v3/plugin/ansible/search/collection-versions/")?;
+        url.query_pairs_mut()
+            .append_pair("keywords", query)
+            .append_pair("is_highest", "true");
+        if let Some(distribution) = &self.distribution {
+            url.query_pairs_mut()
+                .append_pair("distribution", distribution);
+        }
+        Ok(url)
+    }
+
+    /// Returns v3 collection version docs-blob endpoint URL.
+    pub fn docs_blob_url(&self, package_name: &str, package_version: &str) -> Result<url::Url> {
+        let mut segments = vec!["v3", "collections"];
+        segments.extend(package_name.split('.'));
+        segments.extend(&["versions", package_version, "docs-blob", ""]);
+        join_path_segments(&self.api_url()?, &segments)
+    }
+
+    /// Returns Galaxy v1 standalone roles endpoint URL.
+    pub fn roles_url(&self) -> Result<url::Url> {
+        Ok(self.root_url.join(&self.api_prefix)?.join("v1/roles/")?)
+    }
+
+    /// Returns conventional artifact download URL.
+    ///
+    /// Used for package proxies which omit `download_url` from version entries.
+    pub fn download_url(&self, package_name: &str, package_version: &str) -> Result<url::Url> {
+        let file_name = format!(
+            "{}-{}.tar.gz",
+            package_name.replace(".", "-"),
+            package_version
+        );
+        join_path_segments(&self.api_url()?, &["download", &file_name])
+    }
+
+    /// Check that an artifact URL given in registry JSON may be fetched.
+    ///
+    /// The host must be the registry or a mirror host, a subdomain of one, or be listed in the
+    /// comma separated VOUCH_ANSIBLE_ALLOWED_ARTIFACT_HOSTS. https is required unless the
+    /// registry itself uses http. Link-local and cloud metadata addresses are always rejected.
+    /// Host names are not resolved.
+    pub fn check_artifact_url(&self, url: &url::Url) -> Result<()> {
+        let allowed_hosts: Vec<String> = std::env::var("VOUCH_ANSIBLE_ALLOWED_ARTIFACT_HOSTS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|host| host.trim().to_lowercase())
+            .filter(|host| !host.is_empty())
+            .collect();
+        self.check_artifact_url_with_hosts(url, &allowed_hosts)
+    }
+
+    fn check_artifact_url_with_hosts(
+        &self,
+        url: &url::Url,
+        allowed_hosts: &[String],
+    ) -> Result<()> {
+        let registry_urls: Vec<&url::Url> = std::iter::once(&self.root_url)
+            .chain(self.mirror_urls.iter())
+            .collect();
+        let is_allowed_scheme = url.scheme() == "https"
+            || (url.scheme() == "http"
+                && registry_urls
+                    .iter()
+                    .any(|registry_url| registry_url.scheme() == "http"));
+        if !is_allowed_scheme {
+            return Err(format_err!(
+                "Refusing artifact URL with disallowed scheme: {}",
+                url
+            ));
+        }
+
+        let host = url
+            .host()
+            .ok_or(format_err!("Artifact URL is missing a host: {}", url))?;
+        if is_metadata_host(&host) {
+            return Err(format_err!(
+                "Refusing artifact URL with link-local or metadata address: {}",
+                url
+            ));
+        }
+        let host = host.to_string().to_lowercase();
+        let is_allowed_host = registry_urls
+            .iter()
+            .filter_map(|registry_url| registry_url.host_str())
+            .map(|registry_host| registry_host.to_lowercase())
+            .chain(allowed_hosts.iter().cloned())
+            .any(|allowed_host| {
+                host == allowed_host || host.ends_with(&format!(".{}", allowed_host))
+            });
+        if !is_allowed_host {
+            return Err(format_err!(
+                "Refusing artifact URL outside registry {}: {}. \
+                 Allow the host with VOUCH_ANSIBLE_ALLOWED_ARTIFACT_HOSTS.",
+                self.host_name,
+                url
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Check that a URL taken from remote JSON outside of any registry may be fetched.
+///
+/// https is required. Link-local and cloud metadata addresses are rejected, see
+/// `check_redirect_url`.
+pub fn check_remote_url(url: &url::Url) -> Result<()> {
+    if url.scheme() != "https" {
+        return Err(format_err!("Refusing URL with disallowed scheme: {}", url));
+    }
+    check_redirect_url(None, url)
+}
+
+/// Check that a redirect to the given URL may be followed.
+///
+/// Link-local and cloud metadata addresses are rejected, as are redirects from https to
+/// another scheme. Redirects are not limited to registry hosts, as registries commonly serve
+/// artifacts from object storage. Host names are not resolved.
+pub fn check_redirect_url(previous_url: Option<&url::Url>, url: &url::Url) -> Result<()> {
+    if previous_url.is_some_and(|previous_url| previous_url.scheme() == "https")
+        && url.scheme() != "https"
+    {
+        return Err(format_err!("Refusing redirect from https to: {}", url));
+    }
+    let host = url
+        .host()
+        .ok_or(format_err!("URL is missing a host: {}", url))?;
+    if is_metadata_host(&host) {
+        return Err(format_err!(
+            "Refusing URL with link-local or metadata address: {}",
+            url
+        ));
+    }
+    Ok(())
+}
+
+/// Returns true for link-local hosts, which include cloud instance metadata services, and
+/// other well known metadata endpoints.
+fn is_metadata_host(host: &url::Host<&str>) -> bool {
+    let is_metadata_ipv4 = |address: &std::net::Ipv4Addr| {
+        address.is_link_local()
+            || address.is_unspecified()
+            || address.octets() == [100, 100, 100, 200]
+            || address.octets() == [192, 0, 0, 192]
+    };
+    match host {
+        url::Host::Domain(domain) => {
+            let domain = domain.trim_end_matches('.').to_lowercase();
+            domain == "metadata.google.internal" || domain == "metadata"
+        }
+        url::Host::Ipv4(address) => is_metadata_ipv4(address),
+        url::Host::Ipv6(address) => {
+            (address.segments()[0] & 0xffc0) == 0xfe80
+                || address.is_unspecified()
+                || address.segments() == [0xfd00, 0xec2, 0, 0, 0, 0, 0, 0x254]
+                || address
+                    .to_ipv4_mapped()
+                    .map(|address| is_metadata_ipv4(&address))
+                    .unwrap_or(false)
+        }
+    }
+}
+
+/// Characters percent-encoded within a URL path segment.
+static PATH_SEGMENT_ENCODE_SET: &percent_encoding::AsciiSet = &percent_encoding::CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// Check that a package name component or version can be used as a URL path segment.
+///
+/// Rejects empty and dot segments, path separators, whitespace and control characters, which
+/// never occur in valid names or versions and would otherwise give a broken request.
+pub fn check_path_segment(segment: &str) -> Result<()> {
+    if segment.is_empty() || segment == "." || segment == ".." {
+        return Err(format_err!(
+            "Invalid empty or relative component: {:?}",
+            segment
+        ));
+    }
+    if segment
+        .chars()
+        .any(|c| c == '/' || c == '\\' || c.is_whitespace() || c.is_control())
+    {
+        return Err(format_err!(
+            "Invalid character in package name or version: {:?}",
+            segment
+        ));
+    }
+    Ok(())
+}
+
+/// Returns percent-encoded URL path segment. See `check_path_segment`.
+pub fn encode_path_segment(segment: &str) -> Result<String> {
+    check_path_segment(segment)?;
+    Ok(percent_encoding::utf8_percent_encode(segment, PATH_SEGMENT_ENCODE_SET).to_string())
+}
+
+/// Returns URL with the given path segments appended, each checked and percent-encoded.
+///
+/// A trailing empty segment gives the trailing slash which registry endpoints expect.
+fn join_path_segments(base_url: &url::Url, segments: &[&str]) -> Result<url::Url> {
+    if let Some((_, leading_segments)) = segments.split_last() {
+        for segment in leading_segments {
+            check_path_segment(segment)?;
+        }
+    }
+    let mut url = base_url.clone();
+    url.path_segments_mut()
+        .map_err(|_| format_err!("Registry URL cannot be a base: {}", base_url))?
+        .pop_if_empty()
+        .extend(segments);
+    Ok(url)
+}
+
+/// Returns true if the URL looks like an Artifactory or Nexus Ansible proxy.
+fn is_package_proxy_url(url: &url::Url) -> bool {
+    let host = url.host_str().unwrap_or("").to_lowercase();
+    let path = url.path().to_lowercase();
+    host.contains("artifactory")
+        || host.contains("nexus")
+        || path.starts_with("/artifactory/")
+        || path.starts_with("/repository/")
+}
+
+#[test]
+fn test_registry_url_encoding() -> Result<()> {
+    let registry = Registry::galaxy();
+    assert_eq!(
+        registry
+            .version_url("community.general", "1.0.0+build?1")?
+            .as_str(),
+        "https://galaxy.ansible.com/api/v2/collections/community/general/versions/1.0.0+build%3F1/"
+    );
+    assert!(registry
+        .version_url("community.general", "../1.0.0")
+        .is_err());
+    assert!(registry.collection_url("community..general").is_err());
+    assert!(registry.collection_url("community.gen eral").is_err());
+    assert_eq!(encode_path_segment("a#b")?, "a%23b");
+    Ok(())
+}
+
+#[test]
+fn test_private_registry_urls() -> Result<()> {
+    let mut registry = Registry::private(&url::Url::parse("https://hub.example.com/galaxy/")?)?;
+    assert_eq!(registry.host_name, "hub.example.com");
+    assert_eq!(
+        registry.human_url_template,
+        "https://hub.example.com/galaxy/ui/repo/{{distribution}}/{{package_name}}/"
+    );
+
+    assert_eq!(
+        registry.versions_url("community.general")?.as_str(),
+        "https://hub.example.com/galaxy/api/galaxy/v3/collections/community/general/versions/"
+    );
+    assert_eq!(
+        Registry::private(&url::Url::parse("https://hub.example.com/pulp")?)?
+            .collection_url("community.general")?
+            .as_str(),
+        "https://hub.example.com/pulp/api/galaxy/v3/collections/community/general/"
+    );
+
+    registry.api_prefix = "api/".to_string();
+    assert_eq!(
+        registry.versions_url("community.general")?.as_str(),
+        "https://hub.example.com/galaxy/api/v3/collections/community/general/versions/"
+    );
+    assert_eq!(
+        registry.versions_list_url("community.general")?.query(),
+        Some("ordering=-version")
+    );
+
+    registry.distribution = Some("staging".to_string());
+    assert_eq!(
+        registry.versions_url("community.general")?.as_str(),
+        "https://hub.example.com/galaxy/api/content/staging/v3/collections/community/general/versions/"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_local_registry() -> Result<()> {
+    let registry = Registry::local(std::path::Path::new("/srv/ansible/artifacts"))?;
+    assert_eq!(registry.host_name, "local:artifacts");
+    assert_eq!(registry.root_url.as_str(), "file:///srv/ansible/artifacts/");
+    assert!(registry.is_local());
+    assert!(Registry::local(std::path::Path::new("artifacts")).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_from_galaxy_server() -> Result<()> {
+    let registry = Registry::from_galaxy_server(&config::GalaxyServer {
+        name: "private_hub".to_string(),
+        url: "https://hub.example.com/api/galaxy/content/rh-certified/".to_string(),
+        token: Some("secret".to_string()),
+        auth_url: None,
+        mirrors: vec!["https://mirror.example.com/".to_string()],
+        distribution: None,
+        proxy_compatibility: None,
+    })?;
+    assert!(!registry.proxy_compatibility);
+    assert_eq!(
+        registry.collection_url("redhat.satellite")?.as_str(),
+        "https://hub.example.com/api/galaxy/content/rh-certified/v3/collections/redhat/satellite/"
+    );
+    assert_eq!(
+        registry.mirrored_urls("https://hub.example.com/api/galaxy/v3/collections/"),
+        vec![
+            "https://hub.example.com/api/galaxy/v3/collections/".to_string(),
+            "https://mirror.example.com/api/galaxy/v3/collections/".to_string(),
+        ]
+    );
+    assert_eq!(
+        registry.authorization_header(&reqwest::blocking::Client::new())?,
+        Some("Token secret".to_string())
+    );
+    assert!(registry.is_token_url("https://hub.example.com/api/galaxy/v3/collections/"));
+    assert!(!registry.is_token_url("https://mirror.example.com/api/galaxy/v3/collections/"));
+    assert!(!registry.is_token_url("http://hub.example.com/api/galaxy/v3/collections/"));
+    Ok(())
+}
+
+#[test]
+fn test_proxy_compatibility_detection() -> Result<()> {
+    let registry = Registry::from_galaxy_server(&config::GalaxyServer {
+        name: "artifactory".to_string(),
+        url: "https://example.jfrog.io/artifactory/api/ansible/ansible-remote/".to_string(),
+        token: None,
+        auth_url: None,
+        mirrors: Vec::new(),
+        distribution: None,
+        proxy_compatibility: None,
+    })?;
+    assert!(registry.proxy_compatibility);
+    assert_eq!(
+        registry.download_url("community.general", "3.8.0")?.as_str(),
+        "https://example.jfrog.io/artifactory/api/ansible/ansible-remote/download/community-general-3.8.0.tar.gz"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_automation_hub_urls() -> Result<()> {
+    let registry = Registry::automation_hub(AutomationHubRepository::Validated, "token");
+    assert_eq!(
+        registry.version_url("redhat.rhel_system_roles", "1.0.0")?.as_str(),
+        "https://console.redhat.com/api/automation-hub/content/validated/v3/collections/redhat/rhel_system_roles/versions/1.0.0/"
+    );
+    assert_eq!(
+        Registry::galaxy()
+            .versions_url("community.general")?
+            .as_str(),
+        "https://galaxy.ansible.com/api/v2/collections/community/general/versions/"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_check_redirect_url() -> Result<()> {
+    let registry_url = url::Url::parse("https://galaxy.ansible.com/download/a.tar.gz")?;
+    let check = |url: &str| check_redirect_url(Some(&registry_url), &url::Url::parse(url)?);
+    assert!(check("https://ansible-galaxy.s3.amazonaws.com/a.tar.gz").is_ok());
+    assert!(check("http://ansible-galaxy.s3.amazonaws.com/a.tar.gz").is_err());
+    assert!(check("https://169.254.169.254/latest/meta-data/").is_err());
+    assert!(check("https://[fe80::1]/a.tar.gz").is_err());
+
+    let http_url = url::Url::parse("http://localhost:8080/a.tar.gz")?;
+    assert!(check_redirect_url(Some(&http_url), &url::Url::parse("http://localhost/b")?).is_ok());
+    assert!(check_remote_url(&url::Url::parse("http://github.com/a.tar.gz")?).is_err());
+    assert!(check_remote_url(&url::Url::parse("https://github.com/a.tar.gz")?).is_ok());
+    Ok(())
+}
+
+#[test]
+fn test_check_artifact_url() -> Result<()> {
+    let registry = Registry::galaxy();
+    let check = |url: &str| {
+        registry.check_artifact_url_with_hosts(
+            &url::Url::parse(url).unwrap(),
+            &["cdn.example.com".to_string()],
+        )
+    };
+    assert!(check("https://galaxy.ansible.com/download/community-general-3.0.0.tar.gz").is_ok());
+    assert!(check("https://files.galaxy.ansible.com/community-general-3.0.0.tar.gz").is_ok());
+    assert!(check("https://cdn.example.com/community-general-3.0.0.tar.gz").is_ok());
+    assert!(check("http://galaxy.ansible.com/download/community-general-3.0.0.tar.gz").is_err());
+    assert!(check("https://example.com/community-general-3.0.0.tar.gz").is_err());
+    assert!(check("https://evilgalaxy.ansible.com.example.com/artifact.tar.gz").is_err());
+    assert!(check("http://169.254.169.254/latest/meta-data/").is_err());
+    assert!(check("https://[::ffff:169.254.169.254]/").is_err());
+    assert!(check("file:///etc/passwd").is_err());
+
+    let mut registry = Registry::galaxy();
+    registry.root_url = url::Url::parse("http://169.254.1.1:8080/")?;
+    assert!(registry
+        .check_artifact_url_with_hosts(&url::Url::parse("http://169.254.1.1:8080/a.tar.gz")?, &[])
+        .is_err());
+    registry.root_url = url::Url::parse("http://127.0.0.1:8080/")?;
+    assert!(registry
+        .check_artifact_url_with_hosts(&url::Url::parse("http://127.0.0.1:8080/a.tar.gz")?, &[])
+        .is_ok());
+    Ok(())
+}
@@ -0,0 +1,556 @@
+use anyhow::{format_err, Context, Result};
+
+/// How dependency version requirements are turned into concrete versions.
+///
+/// A lockfile next to the dependency file takes precedence in every mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionResolution {
+    /// Use a matching installed version, else the version named by the requirement. For
+    /// example: `>=2.0` gives `2.0.0`. Requirements which name no version, such as `*`, use the
+    /// latest registry version unless installed, so may query the registry.
+    Installed,
+
+    /// As `Installed`, but never query the registry. Requirements which name no version and
+    /// are not installed have no version.
+    Offline,
+
+    /// Use the highest registry version which satisfies the requirement. Falls back to
+    /// installed mode for packages which are not found.
+    Registry,
+
+    /// Use lockfile pinned versions only. Dependencies which are not pinned have no version.
+    Lockfile,
+}
+
+impl std::str::FromStr for VersionResolution {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "installed" => Ok(Self::Installed),
+            "offline" => Ok(Self::Offline),
+            "registry" => Ok(Self::Registry),
+            "lockfile" => Ok(Self::Lockfile),
+            _ => Err(format_err!(
+                "Unknown version resolution mode {:?}, expected installed, offline, registry or lockfile.",
+                value
+            )),
+        }
+    }
+}
+
+impl VersionResolution {
+    /// Returns resolution mode given by the `--version-resolution=<mode>` extension argument,
+    /// then the VOUCH_ANSIBLE_VERSION_RESOLUTION environment variable. Defaults to installed.
+    pub fn from_args(extension_args: &[String]) -> Result<Self> {
+        let argument = extension_args
+            .iter()
+            .find_map(|arg| arg.strip_prefix("--version-resolution="));
+        if let Some(value) = argument {
+            return value.parse();
+        }
+        match std::env::var("VOUCH_ANSIBLE_VERSION_RESOLUTION") {
+            Ok(value) => value.parse(),
+            Err(_) => Ok(Self::Installed),
+        }
+    }
+}
+
+/// PEP 440 style prerelease version. For example: `2.0.0rc1`
+static PRERELEASE_VERSION_REGEX: once_cell::sync::Lazy<regex::Regex> =
+    once_cell::sync::Lazy::new(|| {
+        regex::Regex::new(
+            r"^(\d+(?:\.\d+){0,2})[.-]?(dev|a|alpha|b|beta|c|rc|pre|preview)[.-]?(\d*)$",
+        )
+        .unwrap()
+    });
+
+/// Parse a registry or tag version, tolerating a leading `v` and missing minor or patch
+/// numbers. For example: `v2.3` gives `2.3.0`.
+///
+/// PEP 440 style prereleases are also accepted. For example: `2.0.0rc1` gives `2.0.0-rc1`.
+pub fn parse_version(version: &str) -> Option<semver::Version> {
+    let version = version.trim().trim_start_matches('v');
+    let normalized_version = super::galaxy::normalize_version(version).ok()?;
+    if let Ok(version) = semver::Version::parse(&normalized_version) {
+        return Some(version);
+    }
+
+    let captures = PRERELEASE_VERSION_REGEX.captures(version)?;
+    let version = format!("{}-{}{}", &captures[1], &captures[2], &captures[3]);
+    let version = super::galaxy::normalize_version(&version).ok()?;
+    semver::Version::parse(&version).ok()
+}
+
+/// Prerelease identifier part. Numbers order before text, as in semver.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum PrereleaseToken {
+    Number(u64),
+
+    /// Well known labels are ranked as `dev` < `alpha` < `beta` < `rc`. Other labels follow,
+    /// ordered by text.
+    Text(u8, String),
+}
+
+/// Split prerelease into tokens of digits or letters. For example: `rc10` gives
+/// `rc`, `10`.
+fn get_prerelease_tokens(prerelease: &str) -> Vec<PrereleaseToken> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut characters = prerelease.chars().peekable();
+    while let Some(character) = characters.next() {
+        if !matches!(character, '.' | '-' | '_') {
+            token.push(character);
+        }
+        let is_token_end = match characters.peek() {
+            Some(next) => {
+                matches!(character, '.' | '-' | '_')
+                    || matches!(next, '.' | '-' | '_')
+                    || next.is_ascii_digit() != character.is_ascii_digit()
+            }
+            None => true,
+        };
+        if is_token_end && !token.is_empty() {
+            tokens.push(get_prerelease_token(&token));
+            token.clear();
+        }
+    }
+    tokens
+}
+
+fn get_prerelease_token(token: &str) -> PrereleaseToken {
+    if let Ok(number) = token.parse::<u64>() {
+        return PrereleaseToken::Number(number);
+    }
+    let token = token.to_lowercase();
+    let rank = match token.as_str() {
+        "dev" => 0,
+        "a" | "alpha" => 1,
+        "b" | "beta" => 2,
+        "c" | "rc" | "pre" | "preview" => 3,
+        _ => 4,
+    };
+    PrereleaseToken::Text(rank, token)
+}
+
+/// Order versions as published on Galaxy and expected by pip users.
+///
+/// Unlike plain semver ordering, prerelease numbers compare numerically wherever they appear,
+/// so `1.0.0-rc9` orders before `1.0.0-rc10`, and `dev` releases order before alphas.
+/// Prerelease labels are compared ignoring case. A release orders after its prereleases.
+pub fn compare_versions(a: &semver::Version, b: &semver::Version) -> std::cmp::Ordering {
+    (a.major, a.minor, a.patch)
+        .cmp(&(b.major, b.minor, b.patch))
+        .then_with(|| match (a.pre.is_empty(), b.pre.is_empty()) {
+            (true, true) => std::cmp::Ordering::Equal,
+            (true, false) => std::cmp::Ordering::Greater,
+            (false, true) => std::cmp::Ordering::Less,
+            (false, false) => {
+                get_prerelease_tokens(a.pre.as_str()).cmp(&get_prerelease_tokens(b.pre.as_str()))
+            }
+        })
+        // Prerelease labels differing only in case, like `RC1` and `rc1`, are equal.
+        .then_with(|| {
+            a.pre
+                .as_str()
+                .to_lowercase()
+                .cmp(&b.pre.as_str().to_lowercase())
+        })
+        .then_with(|| a.build.cmp(&b.build))
+}
+
+#[test]
+fn test_compare_versions() -> Result<()> {
+    // Ascending order.
+    let versions = [
+        "0.9.0",
+        "1.0.0-dev1",
+        "1.0.0-alpha",
+        "1.0.0-alpha.1",
+        "1.0.0-alpha.2",
+        "1.0.0-alpha.10",
+        "1.0.0-beta",
+        "1.0.0-rc1",
+        "1.0.0-rc9",
+        "1.0.0-rc10",
+        "1.0.0",
+        "1.0.1",
+        "1.10.0",
+    ]
+    .iter()
+    .map(|version| semver::Version::parse(version))
+    .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    for (i, a) in versions.iter().enumerate() {
+        for (j, b) in versions.iter().enumerate() {
+            // Ordering is total and consistent with the listed order.
+            assert_eq!(compare_versions(a, b), i.cmp(&j), "{} vs {}", a, b);
+            // Semver ordering agrees for releases.
+            if a.pre.is_empty() && b.pre.is_empty() {
+                assert_eq!(compare_versions(a, b), a.cmp(b));
+            }
+        }
+    }
+
+    // Prerelease labels rank regardless of case.
+    assert_eq!(
+        compare_versions(
+            &semver::Version::parse("1.0.0-RC1")?,
+            &semver::Version::parse("1.0.0-rc1")?
+        ),
+        std::cmp::Ordering::Equal
+    );
+    assert_eq!(
+        compare_versions(
+            &semver::Version::parse("1.0.0-Beta")?,
+            &semver::Version::parse("1.0.0-rc1")?
+        ),
+        std::cmp::Ordering::Less
+    );
+
+    let mut shuffled = versions.clone();
+    shuffled.reverse();
+    shuffled.swap(0, 5);
+    shuffled.sort_by(compare_versions);
+    assert_eq!(shuffled, versions);
+    Ok(())
+}
+
+#[test]
+fn test_compare_versions_properties() {
+    let prereleases = [
+        "", "dev1", "alpha", "alpha.2", "beta.10", "rc1", "rc2", "rc10", "post",
+    ];
+    let mut versions = Vec::new();
+    for major in 0..3 {
+        for minor in [0, 2, 10] {
+            for prerelease in prereleases.iter() {
+                let mut version = format!("{}.{}", major, minor);
+                if !prerelease.is_empty() {
+                    version = format!("{}-{}", version, prerelease);
+                }
+                versions.push(version);
+            }
+        }
+    }
+    let parsed_versions: Vec<semver::Version> = versions
+        .iter()
+        .map(|version| parse_version(version).expect(version))
+        .collect();
+
+    for (version, parsed_version) in versions.iter().zip(&parsed_versions) {
+        // Parsing is consistent with ordering: a published version and its normalized form
+        // are equal, and parsing the normalized form is lossless.
+        assert_eq!(
+            parse_version(&parsed_version.to_string()).as_ref(),
+            Some(parsed_version),
+            "{}",
+            version
+        );
+        assert_eq!(
+            parse_version(&format!("v{}", version)).map(|v| compare_versions(&v, parsed_version)),
+            Some(std::cmp::Ordering::Equal),
+            "{}",
+            version
+        );
+    }
+
+    for a in &parsed_versions {
+        for b in &parsed_versions {
+            let ordering = compare_versions(a, b);
+            // Antisymmetry.
+            assert_eq!(ordering, compare_versions(b, a).reverse(), "{} vs {}", a, b);
+            assert_eq!(
+                ordering == std::cmp::Ordering::Equal,
+                a == b,
+                "{} vs {}",
+                a,
+                b
+            );
+            // Releases order as in semver, after their prereleases.
+            if a.pre.is_empty() && b.pre.is_empty() {
+                assert_eq!(ordering, a.cmp(b), "{} vs {}", a, b);
+            }
+            let (a_release, b_release) = ((a.major, a.minor, a.patch), (b.major, b.minor, b.patch));
+            if a_release != b_release {
+                assert_eq!(ordering, a_release.cmp(&b_release), "{} vs {}", a, b);
+            } else if a.pre.is_empty() && !b.pre.is_empty() {
+                assert_eq!(ordering, std::cmp::Ordering::Greater, "{} vs {}", a, b);
+            }
+            // Transitivity.
+            for c in &parsed_versions {
+                if ordering != std::cmp::Ordering::Greater
+                    && compare_versions(b, c) != std::cmp::Ordering::Greater
+                {
+                    assert_ne!(
+                        compare_versions(a, c),
+                        std::cmp::Ordering::Greater,
+                        "{} <= {} <= {}",
+                        a,
+                        b,
+                        c
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_parse_version() -> Result<()> {
+    assert_eq!(
+        parse_version("v2.3.1"),
+        Some(semver::Version::parse("2.3.1")?)
+    );
+    assert_eq!(parse_version("1.0"), Some(semver::Version::parse("1.0.0")?));
+    assert_eq!(
+        parse_version("2.0.0rc1"),
+        Some(semver::Version::parse("2.0.0-rc1")?)
+    );
+    assert_eq!(parse_version("latest"), None);
+    Ok(())
+}
+
+/// Returns true if prereleases should be considered, given by the `--include-prereleases`
+/// extension argument or the VOUCH_ANSIBLE_INCLUDE_PRERELEASES environment variable.
+pub fn get_include_prereleases(extension_args: &[String]) -> bool {
+    extension_args
+        .iter()
+        .any(|arg| arg == "--include-prereleases")
+        || std::env::var("VOUCH_ANSIBLE_INCLUDE_PRERELEASES")
+            .ok()
+            .and_then(|value| super::config::parse_bool(&value))
+            .unwrap_or(false)
+}
+
+/// Returns true if collection versions should be checked against the installed ansible-core
+/// version, given by the `--check-requires-ansible` extension argument or the
+/// VOUCH_ANSIBLE_CHECK_REQUIRES_ANSIBLE environment variable.
+pub fn get_check_requires_ansible(extension_args: &[String]) -> bool {
+    extension_args
+        .iter()
+        .any(|arg| arg == "--check-requires-ansible")
+        || std::env::var("VOUCH_ANSIBLE_CHECK_REQUIRES_ANSIBLE")
+            .ok()
+            .and_then(|value| super::config::parse_bool(&value))
+            .unwrap_or(false)
+}
+
+/// Returns true if a collection version's `requires_ansible` requirement, such as
+/// `>=2.14.0`, is satisfied by the ansible-core version. Missing or unparsable
+/// requirements are assumed to be satisfied.
+pub fn is_ansible_compatible(
+    requires_ansible: Option<&str>,
+    ansible_core_version: &semver::Version,
+) -> bool {
+    match requires_ansible.map(VersionRequirement::parse) {
+        Some(Ok(requirement)) => requirement.matches(ansible_core_version, true),
+        _ => true,
+    }
+}
+
+#[test]
+fn test_is_ansible_compatible() -> Result<()> {
+    let ansible_core_version = semver::Version::parse("2.14.3")?;
+    assert!(is_ansible_compatible(
+        Some(">=2.9.10"),
+        &ansible_core_version
+    ));
+    assert!(!is_ansible_compatible(
+        Some(">=2.15.0"),
+        &ansible_core_version
+    ));
+    assert!(is_ansible_compatible(None, &ansible_core_version));
+    Ok(())
+}
+
+/// Returns true if the version satisfies the requirement.
+///
+/// Semver requirements only match prereleases they name explicitly. With `include_prereleases`,
+/// a prerelease also matches if its release version does. For example: `>=2.0.0` matches
+/// `2.1.0-rc1`.
+pub fn matches(
+    version_requirement: &semver::VersionReq,
+    version: &semver::Version,
+    include_prereleases: bool,
+) -> bool {
+    if version_requirement.matches(version) {
+        return true;
+    }
+    if !include_prereleases || version.pre.is_empty() {
+        return false;
+    }
+    let release_version = semver::Version::new(version.major, version.minor, version.patch);
+    version_requirement.matches(&release_version)
+}
+
+/// Collection version requirement, as accepted by ansible-galaxy.
+///
+/// Comma separated semver comparators, plus pip-style `==` and `!=` specifiers. A bare version
+/// is an exact requirement. For example: `>=1.0.0, !=1.4.0, <2.0.0`. An empty requirement,
+/// like `*`, matches any version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionRequirement {
+    /// Requirement formed from all specifiers except exclusions.
+    pub requirement: semver::VersionReq,
+
+    /// Versions excluded by `!=` specifiers.
+    pub excluded_versions: Vec<semver::Version>,
+}
+
+impl VersionRequirement {
+    pub fn parse(value: &str) -> Result<Self> {
+        let mut comparators = Vec::new();
+        let mut excluded_versions = Vec::new();
+        for specifier in value.split(',').map(|specifier| specifier.trim()) {
+            if specifier.is_empty() || specifier == "*" {
+                continue;
+            }
+            if let Some(version) = specifier.strip_prefix("!=") {
+                let version = parse_version(version).ok_or(format_err!(
+                    "Failed to parse excluded version {:?} in requirement: {}",
+                    version.trim(),
+                    value
+                ))?;
+                excluded_versions.push(version);
+                continue;
+            }
+
+            let specifier = match specifier.strip_prefix("==") {
+                Some(version) => format!(
+                    "={}",
+                    parse_version(version).ok_or(format_err!(
+                        "Failed to parse version {:?} in requirement: {}",
+                        version.trim(),
+                        value
+                    ))?
+                ),
+                None => match parse_version(specifier) {
+                    Some(version) => format!("={}", version),
+                    None => specifier.to_string(),
+                },
+            };
+            comparators.push(
+                semver::Comparator::parse(&specifier)
+                    .context(format!("Failed to parse version requirement: {}", value))?,
+            );
+        }
+        Ok(Self {
+            requirement: semver::VersionReq { comparators },
+            excluded_versions,
+        })
+    }
+
+    /// Returns true if any version satisfies the requirement.
+    pub fn is_any(&self) -> bool {
+        self.requirement.comparators.is_empty() && self.excluded_versions.is_empty()
+    }
+
+    /// Returns the version if the requirement names a single exact version, such as `1.2.0`
+    /// or `==1.2.0`.
+    pub fn exact_version(&self) -> Option<semver::Version> {
+        match self.requirement.comparators.as_slice() {
+            [comparator] if comparator.op == semver::Op::Exact => Some(semver::Version {
+                major: comparator.major,
+                minor: comparator.minor?,
+                patch: comparator.patch?,
+                pre: comparator.pre.clone(),
+                build: semver::BuildMetadata::EMPTY,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the version satisfies the requirement and is not excluded.
+    pub fn matches(&self, version: &semver::Version, include_prereleases: bool) -> bool {
+        matches(&self.requirement, version, include_prereleases)
+            && !self.excluded_versions.contains(version)
+    }
+}
+
+#[test]
+fn test_version_requirement() -> Result<()> {
+    let requirement = VersionRequirement::parse(">=1.0.0, !=1.4.0, <2.0.0")?;
+    assert!(requirement.matches(&semver::Version::parse("1.3.0")?, false));
+    assert!(!requirement.matches(&semver::Version::parse("1.4.0")?, false));
+    assert!(!requirement.matches(&semver::Version::parse("2.0.0")?, false));
+
+    let requirement = VersionRequirement::parse("==1.2.3")?;
+    assert_eq!(
+        requirement.requirement,
+        semver::VersionReq::parse("=1.2.3")?
+    );
+    let requirement = VersionRequirement::parse("1.2")?;
+    assert_eq!(
+        requirement.requirement,
+        semver::VersionReq::parse("=1.2.0")?
+    );
+    assert_eq!(
+        requirement.exact_version(),
+        Some(semver::Version::parse("1.2.0")?)
+    );
+    assert_eq!(VersionRequirement::parse(">=1.2.0")?.exact_version(), None);
+
+    let requirement = VersionRequirement::parse("==1.2")?;
+    assert!(requirement.matches(&semver::Version::parse("1.2.0")?, false));
+    assert!(!requirement.matches(&semver::Version::parse("1.2.1")?, false));
+    assert_eq!(
+        requirement.exact_version(),
+        Some(semver::Version::parse("1.2.0")?)
+    );
+    assert!(VersionRequirement::parse("==latest").is_err());
+
+    assert!(VersionRequirement::parse("*")?.is_any());
+    assert!(VersionRequirement::parse("")?.is_any());
+    assert!(VersionRequirement::parse("!=latest").is_err());
+    Ok(())
+}
+
+/// Returns true if the version may be selected as a latest version.
+pub fn is_selectable(version: &semver::Version, include_prereleases: bool) -> bool {
+    include_prereleases || version.pre.is_empty()
+}
+
+/// Returns the highest version which satisfies the requirement.
+pub fn select_matching_version(
+    versions: &[semver::Version],
+    version_requirement: &VersionRequirement,
+    include_prereleases: bool,
+) -> Option<semver::Version> {
+    versions
+        .iter()
+        .filter(|version| version_requirement.matches(version, include_prereleases))
+        .max_by(|a, b| compare_versions(a, b))
+        .cloned()
+}
+
+#[test]
+fn test_select_matching_version() -> Result<()> {
+    let versions = ["1.0.0", "2.0.0", "2.3.1", "3.0.0"]
+        .iter()
+        .map(|version| semver::Version::parse(version))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let requirement = VersionRequirement::parse(">=2.0.0, <3.0.0")?;
+    assert_eq!(
+        select_matching_version(&versions, &requirement, false),
+        Some(semver::Version::parse("2.3.1")?)
+    );
+    assert_eq!(
+        select_matching_version(&versions, &VersionRequirement::parse(">=4.0.0")?, false),
+        None
+    );
+
+    let versions = vec![
+        semver::Version::parse("2.3.1")?,
+        semver::Version::parse("2.4.0-rc1")?,
+    ];
+    assert_eq!(
+        select_matching_version(&versions, &requirement, false),
+        Some(semver::Version::parse("2.3.1")?)
+    );
+    assert_eq!(
+        select_matching_version(&versions, &requirement, true),
+        Some(semver::Version::parse("2.4.0-rc1")?)
+    );
+    Ok(())
+}
@@ -0,0 +1,188 @@
+use super::{error, http, registry};
+use anyhow::{format_err, Result};
+
+/// Standalone role release resolved through the Galaxy v1 roles API.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoleRelease {
+    pub version: String,
+    pub artifact_url: url::Url,
+}
+
+/// Returns release for a standalone role named `namespace.rolename`.
+///
+/// The latest tagged version is selected when no version is given. Role archives
+/// are served from the git repository the role was imported from.
+pub fn get_role_release(
+    client: &http::Client,
+    registry: &registry::Registry,
+    package_name: &str,
+    package_version: &Option<&str>,
+    max_pages: usize,
+) -> Result<RoleRelease> {
+    let (namespace, name) = split_role_name(package_name)?;
+
+    let mut search_url = registry.roles_url()?;
+    search_url
+        .query_pairs_mut()
+        .append_pair("owner__username", namespace)
+        .append_pair("name", name);
+    let search_json = http::get_json(client, registry, search_url.as_str())?;
+    // Reported as not found, so that the collection lookup error is kept.
+    let role_json = search_json["results"]
+        .as_array()
+        .and_then(|results| results.first())
+        .ok_or_else(|| {
+            anyhow::Error::new(error::AnsibleExtensionError::NotFound {
+                url: search_url.to_string(),
+            })
+            .context(format!("Failed to find role: {}", package_name))
+        })?;
+
+    let version = match package_version {
+        Some(version) => version.to_string(),
+        None => {
+            let role_id = role_json["id"]
+                .as_u64()
+                .ok_or(format_err!("Failed to parse role id."))?;
+            let versions = get_role_versions(client, registry, role_id, max_pages)?;
+            match select_latest_role_version(&versions) {
+                Some(version) => version,
+                None => role_json["github_branch"]
+                    .as_str()
+                    .filter(|branch| !branch.is_empty())
+                    .unwrap_or("master")
+                    .to_string(),
+            }
+        }
+    };
+
+    let artifact_url = get_role_artifact_url(role_json, &version)?;
+    Ok(RoleRelease {
+        version,
+        artifact_url,
+    })
+}
+
+/// Returns the archive URL of a role version on the git server the role was imported from.
+///
+/// The server is given by the role `github_server` field, defaulting to GitHub.
+fn get_role_artifact_url(role_json: &serde_json::Value, version: &str) -> Result<url::Url> {
+    let github_server = role_json["github_server"]
+        .as_str()
+        .filter(|server| !server.is_empty())
+        .unwrap_or("https://github.com");
+    let github_user = role_json["github_user"]
+        .as_str()
+        .ok_or(format_err!("Failed to parse role github_user."))?;
+    let github_repo = role_json["github_repo"]
+        .as_str()
+        .ok_or(format_err!("Failed to parse role github_repo."))?;
+
+    let artifact_url = url::Url::parse(&format!(
+        "{}/{}/{}/archive/{}.tar.gz",
+        github_server.trim_end_matches('/'),
+        github_user,
+        github_repo,
+        version
+    ))?;
+    registry::check_remote_url(&artifact_url)?;
+    Ok(artifact_url)
+}
+
+#[test]
+fn test_get_role_artifact_url() -> Result<()> {
+    let role_json =
+        serde_json::json!({"github_user": "geerlingguy", "github_repo": "ansible-role-docker"});
+    assert_eq!(
+        get_role_artifact_url(&role_json, "6.1.0")?.as_str(),
+        "https://github.com/geerlingguy/ansible-role-docker/archive/6.1.0.tar.gz"
+    );
+
+    let role_json = serde_json::json!({
+        "github_server": "https://git.example.com/",
+        "github_user": "org",
+        "github_repo": "role",
+    });
+    assert_eq!(
+        get_role_artifact_url(&role_json, "1.0.0")?.as_str(),
+        "https://git.example.com/org/role/archive/1.0.0.tar.gz"
+    );
+    assert!(get_role_artifact_url(
+        &serde_json::json!({"github_server": "http://169.254.169.254", "github_user": "a", "github_repo": "b"}),
+        "1.0.0"
+    )
+    .is_err());
+    Ok(())
+}
+
+fn split_role_name(package_name: &str) -> Result<(&str, &str)> {
+    let mut split = package_name.splitn(2, '.');
+    match (split.next(), split.next()) {
+        (Some(namespace), Some(name)) if !namespace.is_empty() && !name.is_empty() => {
+            Ok((namespace, name))
+        }
+        _ => Err(format_err!(
+            "Role name must take the form namespace.rolename: {}",
+            package_name
+        )),
+    }
+}
+
+/// Returns role version tag names from all pages of the role versions endpoint.
+fn get_role_versions(
+    client: &http::Client,
+    registry: &registry::Registry,
+    role_id: u64,
+    max_pages: usize,
+) -> Result<Vec<String>> {
+    let mut versions = Vec::new();
+    let mut page_url = Some(
+        registry
+            .roles_url()?
+            .join(&format!("{}/versions/", role_id))?,
+    );
+    let mut page_count = 0;
+    while let Some(url) = page_url {
+        if page_count >= max_pages {
+            break;
+        }
+        let json = http::get_json(client, registry, url.as_str())?;
+        let page_entries = json["results"]
+            .as_array()
+            .ok_or(format_err!("Failed to find results JSON section."))?;
+        versions.extend(
+            page_entries
+                .iter()
+                .filter_map(|entry| entry["name"].as_str())
+                .map(|name| name.to_string()),
+        );
+
+        page_url = super::get_next_page_url(&json, &registry.root_url)?;
+        page_count += 1;
+    }
+    Ok(versions)
+}
+
+/// Returns the highest version tag. Tags which are not version numbers are ignored.
+fn select_latest_role_version(versions: &[String]) -> Option<String> {
+    versions
+        .iter()
+        .filter_map(|tag| Some((super::resolution::parse_version(tag)?, tag)))
+        .max_by(|(a, _), (b, _)| super::resolution::compare_versions(a, b))
+        .map(|(_, tag)| tag.clone())
+}
+
+#[test]
+fn test_select_latest_role_version() {
+    let versions = vec![
+        "v1.2.0".to_string(),
+        "1.10.0".to_string(),
+        "stable".to_string(),
+        "1.9".to_string(),
+    ];
+    assert_eq!(
+        select_latest_role_version(&versions),
+        Some("1.10.0".to_string())
+    );
+    assert_eq!(select_latest_role_version(&["main".to_string()]), None);
+}
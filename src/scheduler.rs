@@ -0,0 +1,150 @@
+/// Default per host request rate. Keeps big scans friendly to galaxy.ansible.com.
+static DEFAULT_MAX_REQUESTS_PER_SECOND: u32 = 10;
+
+/// Default per host limit on requests awaiting a response.
+static DEFAULT_MAX_IN_FLIGHT: usize = 4;
+
+/// Interval at which a request waiting for an in-flight slot checks again.
+static IN_FLIGHT_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(10);
+
+#[derive(Debug, Default)]
+struct HostState {
+    next_send_at: Option<std::time::Instant>,
+    in_flight: usize,
+}
+
+/// Paces requests per registry host. Shared by all clones.
+#[derive(Debug, Clone)]
+pub struct RequestScheduler {
+    /// Maximum requests started per second for each host. Zero disables pacing.
+    pub max_requests_per_second: u32,
+
+    /// Maximum requests awaiting a response for each host. Zero disables the limit.
+    pub max_in_flight: usize,
+
+    hosts_: std::sync::Arc<std::sync::Mutex<std::collections::HashMap<String, HostState>>>,
+}
+
+impl Default for RequestScheduler {
+    fn default() -> Self {
+        Self {
+            max_requests_per_second: DEFAULT_MAX_REQUESTS_PER_SECOND,
+            max_in_flight: DEFAULT_MAX_IN_FLIGHT,
+            hosts_: Default::default(),
+        }
+    }
+}
+
+impl RequestScheduler {
+    /// Returns scheduler read from the VOUCH_ANSIBLE_MAX_REQUESTS_PER_SECOND and
+    /// VOUCH_ANSIBLE_MAX_IN_FLIGHT_PER_HOST environment variables.
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            max_requests_per_second: std::env::var("VOUCH_ANSIBLE_MAX_REQUESTS_PER_SECOND")
+                .ok()
+                .and_then(|value| value.parse::<u32>().ok())
+                .unwrap_or(default.max_requests_per_second),
+            max_in_flight: std::env::var("VOUCH_ANSIBLE_MAX_IN_FLIGHT_PER_HOST")
+                .ok()
+                .and_then(|value| value.parse::<usize>().ok())
+                .unwrap_or(default.max_in_flight),
+            hosts_: Default::default(),
+        }
+    }
+
+    /// Block until a request to the URL host may start.
+    pub fn acquire(&self, url: &str) -> RequestPermit {
+        loop {
+            match self.try_acquire(url) {
+                Ok(permit) => return permit,
+                Err(wait) => std::thread::sleep(wait),
+            }
+        }
+    }
+
+    /// Returns a permit if a request to the URL host may start now.
+    /// Otherwise, returns the time to wait before trying again.
+    pub fn try_acquire(&self, url: &str) -> Result<RequestPermit, std::time::Duration> {
+        let host = get_host(url);
+        let mut hosts = match self.hosts_.lock() {
+            Ok(hosts) => hosts,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let state = hosts.entry(host.clone()).or_default();
+
+        if self.max_in_flight > 0 && state.in_flight >= self.max_in_flight {
+            return Err(IN_FLIGHT_POLL_INTERVAL);
+        }
+        let now = std::time::Instant::now();
+        if self.max_requests_per_second > 0 {
+            if let Some(next_send_at) = state.next_send_at.filter(|at| *at > now) {
+                return Err(next_send_at - now);
+            }
+            let interval = std::time::Duration::from_secs(1) / self.max_requests_per_second;
+            state.next_send_at = Some(now + interval);
+        }
+        state.in_flight += 1;
+        Ok(RequestPermit {
+            hosts: self.hosts_.clone(),
+            host,
+        })
+    }
+}
+
+/// Returns URL host name, or an empty string if the URL has none.
+pub fn get_host(url: &str) -> String {
+    url::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| host.to_string()))
+        .unwrap_or_default()
+}
+
+/// Held while a request is in flight. Frees the host slot when dropped.
+#[derive(Debug)]
+pub struct RequestPermit {
+    hosts: std::sync::Arc<std::sync::Mutex<std::collections::HashMap<String, HostState>>>,
+    host: String,
+}
+
+impl Drop for RequestPermit {
+    fn drop(&mut self) {
+        let mut hosts = match self.hosts.lock() {
+            Ok(hosts) => hosts,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if let Some(state) = hosts.get_mut(&self.host) {
+            state.in_flight = state.in_flight.saturating_sub(1);
+        }
+    }
+}
+
+#[test]
+fn test_request_scheduler() {
+    let scheduler = RequestScheduler {
+        max_requests_per_second: 0,
+        max_in_flight: 1,
+        hosts_: Default::default(),
+    };
+    let permit = scheduler.try_acquire("https://galaxy.ansible.com/api/");
+    assert!(permit.is_ok());
+    assert!(scheduler
+        .try_acquire("https://galaxy.ansible.com/api/v3/")
+        .is_err());
+    assert!(scheduler.try_acquire("https://example.com/api/").is_ok());
+    drop(permit);
+    assert!(scheduler
+        .try_acquire("https://galaxy.ansible.com/api/v3/")
+        .is_ok());
+
+    let scheduler = RequestScheduler {
+        max_requests_per_second: 1,
+        max_in_flight: 0,
+        hosts_: Default::default(),
+    };
+    assert!(scheduler.try_acquire("https://galaxy.ansible.com/").is_ok());
+    let wait = scheduler
+        .try_acquire("https://galaxy.ansible.com/")
+        .unwrap_err();
+    assert!(wait <= std::time::Duration::from_secs(1));
+}
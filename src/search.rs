@@ -0,0 +1,248 @@
+use super::http;
+use super::registry;
+use anyhow::{format_err, Result};
+
+/// Maximum edit distance between a package name and a suggested collection name.
+static MAX_SUGGESTION_DISTANCE: usize = 3;
+
+/// Maximum edit distance between a package name and a popular collection name for the
+/// package to be reported as a likely typosquat.
+static MAX_TYPOSQUAT_DISTANCE: usize = 2;
+
+/// Name parts of up to this length allow a single edit only. Short real names, such as
+/// `community.dns` and `community.aws`, are often two edits apart.
+static MAX_SHORT_NAME_PART_LENGTH: usize = 4;
+
+/// Widely used collections, which are the likeliest typosquatting targets.
+static POPULAR_COLLECTIONS: &[&str] = &[
+    "amazon.aws",
+    "ansible.netcommon",
+    "ansible.posix",
+    "ansible.utils",
+    "ansible.windows",
+    "arista.eos",
+    "azure.azcollection",
+    "cisco.ios",
+    "cisco.iosxr",
+    "cisco.nxos",
+    "community.aws",
+    "community.crypto",
+    "community.docker",
+    "community.general",
+    "community.grafana",
+    "community.hashi_vault",
+    "community.libvirt",
+    "community.mongodb",
+    "community.mysql",
+    "community.network",
+    "community.postgresql",
+    "community.rabbitmq",
+    "community.vmware",
+    "community.windows",
+    "community.zabbix",
+    "containers.podman",
+    "fortinet.fortios",
+    "google.cloud",
+    "junipernetworks.junos",
+    "kubernetes.core",
+    "netbox.netbox",
+    "openstack.cloud",
+    "vmware.vmware_rest",
+];
+
+/// Returns popular collections which the package name closely resembles, closest first.
+///
+/// Names are compared case insensitively with `-` read as `_`, so that `Community.General`
+/// and `community-general` lookalikes are caught too. The namespace and collection name are
+/// compared separately, allowing fewer edits in short parts. Popular collections themselves
+/// are never reported.
+pub fn find_typosquat_targets(package_name: &str) -> Vec<&'static str> {
+    if POPULAR_COLLECTIONS.contains(&package_name) {
+        return Vec::new();
+    }
+    let normalize = |name: &str| name.to_lowercase().replace('-', "_");
+    let normalized_name = normalize(package_name);
+    let mut targets: Vec<(usize, &'static str)> = POPULAR_COLLECTIONS
+        .iter()
+        .filter_map(|popular_name| {
+            let distance = get_typosquat_distance(&normalized_name, &normalize(popular_name))?;
+            Some((distance, *popular_name))
+        })
+        .collect();
+    targets.sort();
+    targets.into_iter().map(|(_, name)| name).collect()
+}
+
+/// Returns the edit distance between two normalized names if it is small enough for one to
+/// pass as the other.
+fn get_typosquat_distance(name: &str, popular_name: &str) -> Option<usize> {
+    let get_max_distance = |part: &str| {
+        if part.chars().count() <= MAX_SHORT_NAME_PART_LENGTH {
+            1
+        } else {
+            MAX_TYPOSQUAT_DISTANCE
+        }
+    };
+    let distance = match (name.split_once('.'), popular_name.split_once('.')) {
+        (Some((namespace, collection)), Some((popular_namespace, popular_collection))) => {
+            let namespace_distance = edit_distance(namespace, popular_namespace);
+            let collection_distance = edit_distance(collection, popular_collection);
+            if namespace_distance > get_max_distance(popular_namespace)
+                || collection_distance > get_max_distance(popular_collection)
+            {
+                return None;
+            }
+            namespace_distance + collection_distance
+        }
+        // Without a namespace separator, as in `community_general`, compare whole names.
+        _ => edit_distance(name, popular_name),
+    };
+    (distance <= MAX_TYPOSQUAT_DISTANCE).then_some(distance)
+}
+
+#[test]
+fn test_find_typosquat_targets() {
+    assert_eq!(
+        find_typosquat_targets("comunity.general"),
+        vec!["community.general"]
+    );
+    assert_eq!(
+        find_typosquat_targets("Community-General"),
+        vec!["community.general"]
+    );
+    assert_eq!(find_typosquat_targets("cisco.iso"), Vec::<&str>::new());
+    assert_eq!(
+        find_typosquat_targets("cisco.ioss"),
+        vec!["cisco.ios", "cisco.iosxr"]
+    );
+    assert_eq!(
+        find_typosquat_targets("ansible.psoix"),
+        vec!["ansible.posix"]
+    );
+    assert!(find_typosquat_targets("community.general").is_empty());
+    assert!(find_typosquat_targets("cisco.iosxr").is_empty());
+    assert!(find_typosquat_targets("example.internal").is_empty());
+
+    // Real collections with short names.
+    assert!(find_typosquat_targets("community.dns").is_empty());
+    assert!(find_typosquat_targets("cisco.ise").is_empty());
+    assert!(find_typosquat_targets("cisco.nso").is_empty());
+}
+
+/// Collection found by a registry search.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct SearchResult {
+    /// Fully qualified collection name. For example: `community.general`
+    pub name: String,
+    pub version: Option<String>,
+    pub registry_host_name: String,
+}
+
+/// Query the registry collection search endpoint.
+pub fn search_registry(
+    client: &http::Client,
+    registry: &registry::Registry,
+    query: &str,
+) -> Result<Vec<SearchResult>> {
+    let json = http::get_json(client, registry, registry.search_url(query)?.as_str())?;
+    parse_search_results(&json, &registry.host_name)
+}
+
+/// Parse v3 plugin search, v3 collections, or v2 search response.
+fn parse_search_results(
+    json: &serde_json::Value,
+    registry_host_name: &str,
+) -> Result<Vec<SearchResult>> {
+    let entries = json["data"]
+        .as_array()
+        .or_else(|| json["results"].as_array())
+        .ok_or(format_err!("Failed to parse search results."))?;
+
+    let mut results = Vec::new();
+    for entry in entries {
+        // v3 plugin search nests the collection under `collection_version`.
+        let collection = if entry["collection_version"].is_object() {
+            &entry["collection_version"]
+        } else {
+            entry
+        };
+        let namespace = collection["namespace"]
+            .as_str()
+            .or_else(|| collection["namespace"]["name"].as_str());
+        let name = collection["name"].as_str();
+        let (namespace, name) = match (namespace, name) {
+            (Some(namespace), Some(name)) => (namespace, name),
+            _ => continue,
+        };
+        let version = collection["version"]
+            .as_str()
+            .or_else(|| collection["latest_version"]["version"].as_str())
+            .or_else(|| collection["highest_version"]["version"].as_str())
+            .map(|version| version.to_string());
+        results.push(SearchResult {
+            name: format!("{}.{}", namespace, name),
+            version,
+            registry_host_name: registry_host_name.to_string(),
+        });
+    }
+    Ok(results)
+}
+
+/// Order search results by similarity to the package name and drop distant matches.
+pub fn select_suggestions(package_name: &str, results: Vec<SearchResult>) -> Vec<SearchResult> {
+    let mut suggestions: Vec<(usize, SearchResult)> = results
+        .into_iter()
+        .filter(|result| result.name != package_name)
+        .map(|result| (edit_distance(package_name, &result.name), result))
+        .filter(|(distance, _)| *distance <= MAX_SUGGESTION_DISTANCE)
+        .collect();
+    suggestions.sort_by(|(a_distance, a), (b_distance, b)| {
+        a_distance.cmp(b_distance).then_with(|| a.name.cmp(&b.name))
+    });
+    let mut names = std::collections::HashSet::new();
+    suggestions
+        .into_iter()
+        .map(|(_, result)| result)
+        .filter(|result| names.insert(result.name.clone()))
+        .collect()
+}
+
+/// Levenshtein distance between two strings.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous_row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut row = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution_cost = if a_char == *b_char { 0 } else { 1 };
+            row.push(
+                (previous_row[j] + substitution_cost)
+                    .min(previous_row[j + 1] + 1)
+                    .min(row[j] + 1),
+            );
+        }
+        previous_row = row;
+    }
+    previous_row[b.len()]
+}
+
+#[test]
+fn test_select_suggestions() -> Result<()> {
+    let json = serde_json::json!({"data": [
+        {"collection_version": {"namespace": "community", "name": "general", "version": "3.0.0"}},
+        {"collection_version": {"namespace": "community", "name": "crypto", "version": "1.0.0"}},
+    ]});
+    let results = parse_search_results(&json, "galaxy.ansible.com")?;
+    assert_eq!(results.len(), 2);
+
+    let suggestions = select_suggestions("community.genaral", results);
+    assert_eq!(
+        suggestions,
+        vec![SearchResult {
+            name: "community.general".to_string(),
+            version: Some("3.0.0".to_string()),
+            registry_host_name: "galaxy.ansible.com".to_string(),
+        }]
+    );
+    Ok(())
+}
@@ -0,0 +1,282 @@
+use super::config;
+use super::error;
+use super::metadata;
+use anyhow::{Context, Result};
+
+/// GPG public keys trusted to sign collections.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Keyring {
+    /// Keyring file, as created by `gpg --no-default-keyring --keyring <path> --import`.
+    File(std::path::PathBuf),
+
+    /// ASCII armored public keys.
+    ArmoredKeys(String),
+}
+
+impl Keyring {
+    /// Returns the keyring configured for a project directory, if any. See `from_config`.
+    pub fn from_project_directory(project_directory: &std::path::Path) -> Option<Self> {
+        let config_file_path = config::find_project_config_file(project_directory);
+        let ansible_config = config_file_path
+            .as_ref()
+            .and_then(|path| config::AnsibleConfig::from_file(path).ok())
+            .unwrap_or_default();
+        let config_directory = config_file_path
+            .as_ref()
+            .and_then(|path| path.parent())
+            .unwrap_or(project_directory);
+        Self::from_config(&ansible_config, config_directory)
+    }
+
+    /// Returns the configured keyring, if any.
+    ///
+    /// VOUCH_ANSIBLE_GPG_KEYS may hold ASCII armored public keys. Otherwise the keyring file
+    /// is given by ANSIBLE_GALAXY_GPG_KEYRING or `[galaxy] gpg_keyring`, as for ansible-galaxy.
+    /// A relative `gpg_keyring` is relative to `config_directory`, the directory of the
+    /// ansible.cfg file, as in ansible itself.
+    pub fn from_config(
+        ansible_config: &config::AnsibleConfig,
+        config_directory: &std::path::Path,
+    ) -> Option<Self> {
+        if let Some(keys) = std::env::var("VOUCH_ANSIBLE_GPG_KEYS")
+            .ok()
+            .filter(|keys| !keys.trim().is_empty())
+        {
+            return Some(Self::ArmoredKeys(keys));
+        }
+        if let Some(path) = std::env::var("ANSIBLE_GALAXY_GPG_KEYRING")
+            .ok()
+            .filter(|path| !path.is_empty())
+        {
+            return Some(Self::File(config::expand_home_directory(&path)));
+        }
+        ansible_config
+            .get("galaxy", "gpg_keyring")
+            .filter(|path| !path.is_empty())
+            .map(|path| Self::File(config_directory.join(config::expand_home_directory(path))))
+    }
+}
+
+#[test]
+fn test_keyring_from_config() -> Result<()> {
+    let ansible_config =
+        config::AnsibleConfig::parse("[galaxy]\ngpg_keyring = keys/pubring.kbx\n")?;
+    if std::env::var_os("VOUCH_ANSIBLE_GPG_KEYS").is_none()
+        && std::env::var_os("ANSIBLE_GALAXY_GPG_KEYRING").is_none()
+    {
+        assert_eq!(
+            Keyring::from_config(&ansible_config, std::path::Path::new("/project")),
+            Some(Keyring::File(std::path::PathBuf::from(
+                "/project/keys/pubring.kbx"
+            )))
+        );
+        assert_eq!(
+            Keyring::from_config(
+                &config::AnsibleConfig::default(),
+                std::path::Path::new("/project")
+            ),
+            None
+        );
+    }
+    Ok(())
+}
+
+/// Signature verification outcome.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum SignatureStatus {
+    Good,
+
+    /// Signature does not match MANIFEST.json.
+    Bad,
+
+    ExpiredSignature,
+    ExpiredKey,
+    RevokedKey,
+
+    /// Signing key is not in the keyring.
+    MissingKey,
+
+    /// Signature could not be checked, for example because it is malformed.
+    Error,
+}
+
+/// Verification result of a single collection signature.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct SignatureVerification {
+    /// Signing key fingerprint as advertised by the registry.
+    pub pubkey_fingerprint: String,
+
+    pub signing_service: Option<String>,
+    pub status: SignatureStatus,
+
+    /// Primary key fingerprint of the key which made a valid signature, as reported by gpg.
+    pub signer_fingerprint: Option<String>,
+}
+
+/// Verify detached signatures of a collection MANIFEST.json file against a keyring.
+///
+/// Verification runs gpg, or VOUCH_ANSIBLE_GPG_BIN, in a temporary home directory so that
+/// the user's default keyring is never consulted. Each gpg run is killed after
+/// VOUCH_ANSIBLE_GPG_TIMEOUT_SECS (default 60) seconds.
+pub fn verify_signatures(
+    manifest: &[u8],
+    signatures: &[metadata::CollectionSignature],
+    keyring: &Keyring,
+) -> Result<Vec<SignatureVerification>> {
+    let home_directory = create_gpg_home_directory()?;
+    let result = (|| {
+        let manifest_path = home_directory.join("MANIFEST.json");
+        std::fs::write(&manifest_path, manifest)?;
+        let keyring_path = match keyring {
+            Keyring::File(path) => path.clone(),
+            Keyring::ArmoredKeys(keys) => {
+                let keys_path = home_directory.join("keys.asc");
+                std::fs::write(&keys_path, keys)?;
+                let keyring_path = home_directory.join("keyring.kbx");
+                let output = run_gpg(
+                    &home_directory,
+                    &keyring_path,
+                    &["--import".as_ref(), keys_path.as_os_str()],
+                )?;
+                if !output.status.success() {
+                    return Err(anyhow::Error::new(
+                        error::AnsibleExtensionError::SubprocessFailed {
+                            command: "gpg --import".to_string(),
+                            status: output.status.code(),
+                            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+                        },
+                    )
+                    .context("Failed to import GPG keys."));
+                }
+                keyring_path
+            }
+        };
+
+        let mut verifications = Vec::new();
+        for (index, signature) in signatures.iter().enumerate() {
+            let signature_path = home_directory.join(format!("signature-{}.asc", index));
+            std::fs::write(&signature_path, &signature.signature)?;
+            let output = run_gpg(
+                &home_directory,
+                &keyring_path,
+                &[
+                    "--status-fd".as_ref(),
+                    "1".as_ref(),
+                    "--verify".as_ref(),
+                    signature_path.as_os_str(),
+                    manifest_path.as_os_str(),
+                ],
+            )?;
+            let (status, signer_fingerprint) =
+                parse_gpg_status(&String::from_utf8_lossy(&output.stdout));
+            verifications.push(SignatureVerification {
+                pubkey_fingerprint: signature.pubkey_fingerprint.clone(),
+                signing_service: signature.signing_service.clone(),
+                status,
+                signer_fingerprint,
+            });
+        }
+        Ok(verifications)
+    })();
+    let _ = std::fs::remove_dir_all(&home_directory);
+    result
+}
+
+fn create_gpg_home_directory() -> Result<std::path::PathBuf> {
+    static COUNTER: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+    let home_directory = std::env::temp_dir().join(format!(
+        "vouch-ansible-gpg-{}-{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst)
+    ));
+    let mut builder = std::fs::DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(&home_directory).context(format!(
+        "Failed to create directory: {}",
+        home_directory.display()
+    ))?;
+    Ok(home_directory)
+}
+
+static DEFAULT_GPG_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+static MAX_GPG_OUTPUT_SIZE: u64 = 1024 * 1024;
+
+fn run_gpg(
+    home_directory: &std::path::Path,
+    keyring_path: &std::path::Path,
+    args: &[&std::ffi::OsStr],
+) -> Result<std::process::Output> {
+    let gpg_bin = std::env::var_os("VOUCH_ANSIBLE_GPG_BIN")
+        .filter(|path| !path.is_empty())
+        .map(std::path::PathBuf::from)
+        .unwrap_or_else(|| std::path::PathBuf::from("gpg"));
+    let child = std::process::Command::new(&gpg_bin)
+        .arg("--homedir")
+        .arg(home_directory)
+        .args(["--batch", "--no-tty", "--no-default-keyring", "--keyring"])
+        .arg(keyring_path)
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .context(format!("Failed to run gpg: {}", gpg_bin.display()))?;
+    let timeout = super::http::get_env_seconds("VOUCH_ANSIBLE_GPG_TIMEOUT_SECS")
+        .unwrap_or(DEFAULT_GPG_TIMEOUT);
+    super::galaxy::wait_with_limits(child, timeout, MAX_GPG_OUTPUT_SIZE)
+        .context(format!("Failed to run gpg: {}", gpg_bin.display()))
+}
+
+/// Returns signature status and signer primary key fingerprint from gpg `--status-fd` output.
+fn parse_gpg_status(output: &str) -> (SignatureStatus, Option<String>) {
+    let mut status = SignatureStatus::Error;
+    let mut signer_fingerprint = None;
+    for line in output.lines() {
+        let mut fields = line
+            .strip_prefix("[GNUPG:] ")
+            .unwrap_or_default()
+            .split_whitespace();
+        match fields.next() {
+            Some("GOODSIG") if status == SignatureStatus::Error => status = SignatureStatus::Good,
+            Some("BADSIG") => status = SignatureStatus::Bad,
+            Some("EXPSIG") => status = SignatureStatus::ExpiredSignature,
+            Some("EXPKEYSIG") => status = SignatureStatus::ExpiredKey,
+            Some("REVKEYSIG") => status = SignatureStatus::RevokedKey,
+            Some("NO_PUBKEY") => status = SignatureStatus::MissingKey,
+            Some("VALIDSIG") => signer_fingerprint = fields.nth(9).map(|f| f.to_string()),
+            _ => {}
+        }
+    }
+    if status != SignatureStatus::Good {
+        signer_fingerprint = None;
+    }
+    (status, signer_fingerprint)
+}
+
+#[test]
+fn test_parse_gpg_status() {
+    let output = "[GNUPG:] NEWSIG\n\
+        [GNUPG:] GOODSIG 20764B5DF2C820D6 test@example.com\n\
+        [GNUPG:] VALIDSIG C907DC3FFFDD08B3D43EFB4220764B5DF2C820D6 2021-06-22 1624370721 0 4 0 \
+        22 8 00 C907DC3FFFDD08B3D43EFB4220764B5DF2C820D6\n\
+        [GNUPG:] TRUST_UNDEFINED 0 pgp\n";
+    assert_eq!(
+        parse_gpg_status(output),
+        (
+            SignatureStatus::Good,
+            Some("C907DC3FFFDD08B3D43EFB4220764B5DF2C820D6".to_string())
+        )
+    );
+
+    let output = "[GNUPG:] NEWSIG\n[GNUPG:] BADSIG 20764B5DF2C820D6 test@example.com\n";
+    assert_eq!(parse_gpg_status(output), (SignatureStatus::Bad, None));
+
+    let output = "[GNUPG:] ERRSIG 20764B5DF2C820D6 22 8 00 1624370721 9 -\n\
+        [GNUPG:] NO_PUBKEY 20764B5DF2C820D6\n";
+    assert_eq!(
+        parse_gpg_status(output),
+        (SignatureStatus::MissingKey, None)
+    );
+    assert_eq!(parse_gpg_status(""), (SignatureStatus::Error, None));
+}
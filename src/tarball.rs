@@ -0,0 +1,127 @@
+use super::http;
+use super::registry;
+use anyhow::{format_err, Context, Result};
+
+/// Collection identity recovered from a tarball MANIFEST.json file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TarballRelease {
+    /// Fully qualified collection name. For example: `community.general`
+    pub name: String,
+    pub version: String,
+}
+
+/// Returns true if the package name is a direct https URL to a collection tarball.
+pub fn is_tarball_url(package_name: &str) -> bool {
+    let url = match url::Url::parse(package_name) {
+        Ok(url) => url,
+        Err(_) => return false,
+    };
+    matches!(url.scheme(), "https" | "http")
+        && (url.path().ends_with(".tar.gz") || url.path().ends_with(".tgz"))
+}
+
+/// Download tarball and recover collection name and version from its MANIFEST.json file.
+///
+/// The response body is streamed and reading stops at MANIFEST.json, which ansible-galaxy
+/// places near the start of built artifacts, so usually only a prefix is downloaded.
+pub fn get_tarball_release(client: &http::Client, url: &url::Url) -> Result<TarballRelease> {
+    let reader = open_artifact(client, None, url)?;
+    read_manifest_release(reader).context(format!("Failed to read tarball: {}", url))
+}
+
+/// Open artifact for streaming from an http(s) or file URL.
+///
+/// http(s) downloads are retried as by `http::get_download`, which sends the token of the
+/// given registry.
+pub fn open_artifact(
+    client: &http::Client,
+    registry: Option<&registry::Registry>,
+    url: &url::Url,
+) -> Result<Box<dyn std::io::Read>> {
+    if url.scheme() == "file" {
+        let path = url
+            .to_file_path()
+            .map_err(|_| format_err!("Failed to parse file URL: {}", url))?;
+        let file = std::fs::File::open(&path)
+            .context(format!("Failed to open artifact: {}", path.display()))?;
+        return Ok(Box::new(file));
+    }
+    let response = http::get_download(client, registry, url)
+        .context(format!("Failed to download tarball: {}", url))?;
+    Ok(Box::new(response))
+}
+
+/// Returns content of the first of the given files found in a gzipped tarball stream.
+///
+/// Paths are relative to the tarball root. Reading stops once a file is found.
+pub fn read_file<R: std::io::Read>(
+    reader: R,
+    file_paths: &[&str],
+) -> Result<Option<(String, Vec<u8>)>> {
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(reader));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_path_buf();
+        let matching_path = file_paths.iter().find(|file_path| {
+            path.components()
+                .filter(|component| !matches!(component, std::path::Component::CurDir))
+                .eq(std::path::Path::new(file_path).components())
+        });
+        if let Some(matching_path) = matching_path {
+            let mut content = Vec::new();
+            std::io::Read::read_to_end(&mut entry, &mut content)?;
+            return Ok(Some((matching_path.to_string(), content)));
+        }
+    }
+    Ok(None)
+}
+
+/// Read collection release from gzipped tarball stream.
+fn read_manifest_release<R: std::io::Read>(reader: R) -> Result<TarballRelease> {
+    let (_, content) = read_file(reader, &["MANIFEST.json"])?
+        .ok_or(format_err!("Failed to find MANIFEST.json in tarball."))?;
+    let manifest: serde_json::Value =
+        serde_json::from_slice(&content).context("Failed to parse MANIFEST.json.")?;
+    let collection_info = &manifest["collection_info"];
+    let get_field = |field: &str| {
+        collection_info[field].as_str().ok_or(format_err!(
+            "Failed to find collection_info.{} in MANIFEST.json.",
+            field
+        ))
+    };
+    Ok(TarballRelease {
+        name: format!("{}.{}", get_field("namespace")?, get_field("name")?),
+        version: get_field("version")?.to_string(),
+    })
+}
+
+#[test]
+fn test_read_manifest_release() -> Result<()> {
+    let manifest = serde_json::json!({
+        "collection_info": {"namespace": "community", "name": "general", "version": "3.0.0"}
+    })
+    .to_string();
+
+    let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+        Vec::new(),
+        flate2::Compression::default(),
+    ));
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest.len() as u64);
+    header.set_cksum();
+    builder.append_data(&mut header, "MANIFEST.json", manifest.as_bytes())?;
+    let tarball = builder.into_inner()?.finish()?;
+
+    assert_eq!(
+        read_manifest_release(tarball.as_slice())?,
+        TarballRelease {
+            name: "community.general".to_string(),
+            version: "3.0.0".to_string(),
+        }
+    );
+    assert!(is_tarball_url(
+        "https://example.com/community-general-3.0.0.tar.gz"
+    ));
+    assert!(!is_tarball_url("community.general"));
+    Ok(())
+}